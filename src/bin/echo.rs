@@ -1,5 +1,7 @@
+use udith::{message::ParseOptions, transport::Udp};
+
 #[tokio::main]
 async fn main() {
-    let socket = tokio::net::UdpSocket::bind("0.0.0.0:8080").await.unwrap();
-    udith::run(socket).await;
+    let transport = Udp::bind("0.0.0.0:8080").await.unwrap();
+    udith::run(transport.parse_options(ParseOptions::LENIENT)).await;
 }
@@ -0,0 +1,319 @@
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{
+    message::{Limits, Message},
+    parse_utils::CRLF,
+};
+
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 65535;
+
+// Frames SIP messages on a byte stream such as a TCP or TLS connection
+#[derive(Debug)]
+pub struct Codec {
+    max_message_size: usize,
+    limits: Limits,
+    // Where the line to scan next starts and how many headers came before
+    // it, so every byte of a head is looked at once however it arrives
+    line: usize,
+    headers: usize,
+    // A parsed head waiting for its body, with the length of the head and
+    // of the body
+    head: Option<(Message, usize, usize)>,
+}
+
+#[derive(Debug)]
+pub enum CodecError {
+    Io(std::io::Error),
+    Parse(crate::error::Error),
+    // Content-Length is mandatory on stream transports (RFC 3261 18.3)
+    MissingContentLength,
+    TooLarge { limit: usize },
+}
+
+impl Codec {
+    pub fn new() -> Self {
+        Self {
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            limits: Limits::DEFAULT,
+            line: 0,
+            headers: 0,
+            head: None,
+        }
+    }
+
+    // Limit on start line, headers and body together
+    pub fn max_message_size(mut self, limit: usize) -> Self {
+        self.max_message_size = limit;
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+}
+
+// A copy frames a stream of its own, from the start of a message
+impl Clone for Codec {
+    fn clone(&self) -> Self {
+        Self {
+            max_message_size: self.max_message_size,
+            limits: self.limits,
+            ..Self::new()
+        }
+    }
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for Codec {
+    type Item = Message;
+    type Error = CodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let (mut message, head_len, body_len) = match self.head.take() {
+            Some(head) => head,
+            None => {
+                // Keep-alives are bare CRLFs between messages (RFC 5626 3.5.1)
+                if self.line == 0 {
+                    let keep_alive = src
+                        .iter()
+                        .take_while(|c| **c == b'\r' || **c == b'\n')
+                        .count();
+                    src.advance(keep_alive);
+                }
+                let Some(head_len) = self.scan(src)? else {
+                    return Ok(None);
+                };
+                self.parse_head(src, head_len)?
+            }
+        };
+        let frame_len = head_len + body_len;
+        if src.len() < frame_len {
+            src.reserve(frame_len - src.len());
+            self.head = Some((message, head_len, body_len));
+            return Ok(None);
+        }
+
+        let frame = src.split_to(frame_len);
+        message.body = frame[head_len..].into();
+        Ok(Some(message))
+    }
+}
+
+impl Codec {
+    // Looks for the end of the head in what arrived since the last call,
+    // checking the limits line by line
+    fn scan(&mut self, src: &[u8]) -> Result<Option<usize>, CodecError> {
+        while let Some(at) = src[self.line..].windows(2).position(|w| w == CRLF) {
+            let line = &src[self.line..self.line + at];
+            if line.is_empty() {
+                let head_len = self.line + CRLF.len();
+                self.line = 0;
+                self.headers = 0;
+                return Ok(Some(head_len));
+            }
+            // Folded lines continue the header above them
+            if self.line > 0 && !line.starts_with(b" ") && !line.starts_with(b"\t") {
+                self.headers += 1;
+            }
+            if line.len() > self.limits.max_line_length || self.headers > self.limits.max_headers {
+                return Err(self.head_error(src));
+            }
+            self.line += at + CRLF.len();
+        }
+        // A line never ending is caught as early as one that does
+        if src.len() - self.line > self.limits.max_line_length {
+            return Err(self.head_error(src));
+        }
+        if src.len() > self.max_message_size {
+            return Err(CodecError::TooLarge {
+                limit: self.max_message_size,
+            });
+        }
+        Ok(None)
+    }
+
+    // The one parse of the head, once it is all there
+    fn parse_head(
+        &self,
+        src: &[u8],
+        head_len: usize,
+    ) -> Result<(Message, usize, usize), CodecError> {
+        let (message, content_length) =
+            Message::parse_head(&src[..head_len]).map_err(CodecError::Parse)?;
+        let content_length = content_length.ok_or(CodecError::MissingContentLength)?;
+        self.limits
+            .check_body(content_length, &src[head_len..])
+            .map_err(|e| CodecError::Parse(e.locate(src)))?;
+        if head_len.saturating_add(content_length) > self.max_message_size {
+            return Err(CodecError::TooLarge {
+                limit: self.max_message_size,
+            });
+        }
+        Ok((message, head_len, content_length))
+    }
+
+    // The error the limits give for the head, located in it
+    fn head_error(&mut self, src: &[u8]) -> CodecError {
+        self.line = 0;
+        self.headers = 0;
+        match self.limits.check_head(src) {
+            Err(e) => CodecError::Parse(e),
+            Ok(()) => unreachable!("the scan stops where the limits do"),
+        }
+    }
+}
+
+impl Encoder<&Message> for Codec {
+    type Error = CodecError;
+
+    fn encode(&mut self, item: &Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        item.write_to(&mut dst.writer())?;
+        Ok(())
+    }
+}
+
+impl Encoder<Message> for Codec {
+    type Error = CodecError;
+
+    fn encode(&mut self, item: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode(&item, dst)
+    }
+}
+
+impl From<std::io::Error> for CodecError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl std::fmt::Display for CodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodecError::Io(e) => write!(f, "{}", e),
+            CodecError::Parse(e) => write!(f, "{}", e),
+            CodecError::MissingContentLength => {
+                write!(
+                    f,
+                    "missing Content-Length on a stream transport (RFC 3261 18.3)"
+                )
+            }
+            CodecError::TooLarge { limit } => {
+                write!(f, "message larger than {} bytes", limit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BYE: &[u8] = b"BYE sip:bob@192.0.2.4 SIP/2.0\r\nCall-ID: a84b4c76e66710\r\nCSeq: 231 BYE\r\nContent-Length: 5\r\n\r\nhello";
+
+    #[test]
+    fn partial_input_works() {
+        let mut codec = Codec::new();
+        let mut buf = BytesMut::new();
+        for chunk in BYE.chunks(7) {
+            assert!(codec.decode(&mut buf).unwrap().is_none());
+            buf.extend_from_slice(chunk);
+        }
+        let message = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(b"hello".as_slice(), &*message.body);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn byte_by_byte_works() {
+        let mut codec = Codec::new().limits(Limits {
+            max_line_length: 40,
+            ..Limits::DEFAULT
+        });
+        let mut buf = BytesMut::new();
+        let mut messages = vec![];
+        for byte in [BYE, b"\r\n", BYE].concat() {
+            buf.extend_from_slice(&[byte]);
+            messages.extend(codec.decode(&mut buf).unwrap());
+        }
+        assert_eq!(2, messages.len());
+        assert!(messages.iter().all(|message| &*message.body == b"hello"));
+        assert!(buf.is_empty());
+
+        // Caught before the line ends
+        buf.extend_from_slice(&[b'a'; 41]);
+        let Err(CodecError::Parse(e)) = codec.decode(&mut buf) else {
+            panic!("line too long")
+        };
+        assert_eq!(Some(0), e.offset());
+    }
+
+    #[test]
+    fn pipelined_messages_work() {
+        let mut codec = Codec::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"\r\n\r\n");
+        buf.extend_from_slice(BYE);
+        buf.extend_from_slice(b"\r\n");
+        buf.extend_from_slice(BYE);
+        buf.extend_from_slice(b"SIP/2.0 200");
+        assert!(codec.decode(&mut buf).unwrap().is_some());
+        assert!(codec.decode(&mut buf).unwrap().is_some());
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        assert_eq!(b"SIP/2.0 200".as_slice(), &buf[..]);
+    }
+
+    #[test]
+    fn content_length_is_required() {
+        let mut buf = BytesMut::from(&b"OPTIONS sip:x.com SIP/2.0\r\nCall-ID: x\r\n\r\n"[..]);
+        assert!(matches!(
+            Codec::new().decode(&mut buf),
+            Err(CodecError::MissingContentLength)
+        ));
+    }
+
+    #[test]
+    fn limits_are_enforced() {
+        let mut codec = Codec::new().max_message_size(64);
+        let mut buf = BytesMut::from(BYE);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(CodecError::TooLarge { limit: 64 })
+        ));
+
+        // No end of headers in sight
+        let mut buf = BytesMut::from(&[b'a'; 65][..]);
+        assert!(codec.decode(&mut buf).is_err());
+
+        let mut buf =
+            BytesMut::from(&b"BYE sip:x.com SIP/2.0\r\nl: 99999999999999999999999\r\n\r\n"[..]);
+        assert!(Codec::new().decode(&mut buf).is_err());
+
+        let mut codec = Codec::new().limits(Limits {
+            max_headers: 2,
+            ..Limits::DEFAULT
+        });
+        let mut buf = BytesMut::from(&BYE[..60]);
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        buf.extend_from_slice(&BYE[60..]);
+        assert!(matches!(codec.decode(&mut buf), Err(CodecError::Parse(_))));
+    }
+
+    #[test]
+    fn encoder_works() {
+        let mut codec = Codec::new();
+        let mut buf = BytesMut::from(BYE);
+        let message = codec.decode(&mut buf).unwrap().unwrap();
+        codec.encode(&message, &mut buf).unwrap();
+        codec.encode(message, &mut buf).unwrap();
+        assert_eq!([BYE, BYE].concat(), buf);
+    }
+}
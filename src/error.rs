@@ -0,0 +1,224 @@
+use nom::error::ErrorKind;
+
+use crate::parse_utils::ParseResult;
+
+// The part of a SIP message a parse error was found in, outermost first
+#[derive(Debug, Clone, PartialEq)]
+pub enum Element {
+    StartLine,
+    Method,
+    Uri,
+    SipVersion,
+    StatusCode,
+    HeaderName,
+    Header(String),
+    ViaParam,
+    Body,
+}
+
+impl Element {
+    pub fn reference(&self) -> &'static str {
+        // Where the grammar of the element is defined
+        match self {
+            Element::StartLine => "RFC 3261 7.1",
+            Element::Method => "RFC 3261 7.1",
+            Element::Uri => "RFC 3261 19.1",
+            Element::SipVersion => "RFC 3261 7.1",
+            Element::StatusCode => "RFC 3261 7.2",
+            Element::HeaderName => "RFC 3261 7.3",
+            Element::Header(_) => "RFC 3261 20",
+            Element::ViaParam => "RFC 3261 20.42",
+            Element::Body => "RFC 3261 7.4",
+        }
+    }
+}
+
+impl std::fmt::Display for Element {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Element::StartLine => write!(f, "start line"),
+            Element::Method => write!(f, "method"),
+            Element::Uri => write!(f, "URI"),
+            Element::SipVersion => write!(f, "SIP version"),
+            Element::StatusCode => write!(f, "status code"),
+            Element::HeaderName => write!(f, "header name"),
+            Element::Header(name) => write!(f, "{} header", name),
+            Element::ViaParam => write!(f, "Via parameter"),
+            Element::Body => write!(f, "body"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    kind: ErrorKind,
+    // Length of the input left when the error happened. It turns into an
+    // offset once the error reaches the caller holding the whole input.
+    remaining: usize,
+    offset: Option<usize>,
+    elements: Vec<Element>,
+}
+
+impl Error {
+    pub fn new(element: Element, remaining: &[u8], kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            remaining: remaining.len(),
+            offset: None,
+            elements: vec![element],
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    // Byte offset of the error from the start of the parsed message
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    pub fn elements(&self) -> &[Element] {
+        &self.elements
+    }
+
+    // The outermost element, e.g. the header the error is in
+    pub fn element(&self) -> Option<&Element> {
+        self.elements.first()
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self.kind {
+            ErrorKind::Tag => "unexpected character",
+            ErrorKind::Digit => "digit expected",
+            ErrorKind::HexDigit => "hex digit expected",
+            ErrorKind::Alpha | ErrorKind::AlphaNumeric => "alphanumeric expected",
+            ErrorKind::Space | ErrorKind::MultiSpace => "whitespace expected",
+            ErrorKind::CrLf => "CRLF expected",
+            ErrorKind::Eof | ErrorKind::Complete => "unexpected end of input",
+            ErrorKind::Verify => "value out of range",
+            ErrorKind::TooLarge => "limit exceeded",
+            ErrorKind::Count => "only one value allowed",
+            ErrorKind::TakeWhile1 | ErrorKind::Many1 | ErrorKind::SeparatedList => "value expected",
+            _ => "does not match grammar",
+        }
+    }
+
+    pub fn reason_phrase(&self) -> String {
+        // Suitable for the reason phrase of a 400 Bad Request, or of a
+        // 513 Message Too Large when a limit was exceeded
+        if self.kind == ErrorKind::TooLarge {
+            return "Message Too Large".to_owned();
+        }
+        match self.element() {
+            Some(element) => format!("Malformed {}", element),
+            None => "Bad Request".to_owned(),
+        }
+    }
+
+    pub fn locate(mut self, src: &[u8]) -> Self {
+        self.offset = Some(src.len().saturating_sub(self.remaining));
+        self
+    }
+
+    // For an error located in a part of the message, e.g. a header value,
+    // that starts `by` bytes into it
+    pub fn shift(mut self, by: usize) -> Self {
+        self.offset = self.offset.map(|offset| offset + by);
+        self
+    }
+
+    pub fn within(mut self, element: Element) -> Self {
+        self.elements.insert(0, element);
+        self
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut elements = self.elements.iter();
+        match elements.next() {
+            Some(element) => write!(f, "malformed {}", element)?,
+            None => write!(f, "malformed message")?,
+        }
+        for element in elements {
+            write!(f, ", {}", element)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {}", offset)?;
+        }
+        write!(f, ": {}", self.description())?;
+        if let Some(element) = self.elements.last() {
+            write!(f, " ({})", element.reference())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl nom::error::ParseError<&[u8]> for Error {
+    fn from_error_kind(input: &[u8], kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            remaining: input.len(),
+            offset: None,
+            elements: vec![],
+        }
+    }
+
+    fn append(_: &[u8], _: ErrorKind, other: Self) -> Self {
+        // The innermost error is the most specific one
+        other
+    }
+
+    fn or(self, other: Self) -> Self {
+        // Keep the alternative that got furthest into the input
+        if self.remaining < other.remaining {
+            self
+        } else {
+            other
+        }
+    }
+}
+
+impl From<nom::Err<Error>> for Error {
+    fn from(err: nom::Err<Error>) -> Self {
+        match err {
+            nom::Err::Error(e) | nom::Err::Failure(e) => e,
+            nom::Err::Incomplete(_) => Self {
+                kind: ErrorKind::Complete,
+                remaining: 0,
+                offset: None,
+                elements: vec![],
+            },
+        }
+    }
+}
+
+// Marks errors coming out of the parser as happening inside the element
+pub fn within<'a, O>(
+    element: Element,
+    mut parser: impl FnMut(&'a [u8]) -> ParseResult<'a, O>,
+) -> impl FnMut(&'a [u8]) -> ParseResult<'a, O> {
+    move |src| parser(src).map_err(|e| e.map(|e| e.within(element.clone())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_works() {
+        let e = Error::new(Element::ViaParam, b";ttl=x\r\n", ErrorKind::Digit)
+            .within(Element::Header("Via".to_owned()))
+            .locate(b"0123456789;ttl=x\r\n");
+        assert_eq!(Some(10), e.offset());
+        assert_eq!(Some(&Element::Header("Via".to_owned())), e.element());
+        assert_eq!(
+            "malformed Via header, Via parameter at byte 10: digit expected (RFC 3261 20.42)",
+            e.to_string()
+        );
+        assert_eq!("Malformed Via header", e.reason_phrase());
+    }
+}
@@ -1,41 +1,69 @@
+pub mod codec;
+pub mod error;
 pub mod message;
 pub mod parse_utils;
+pub mod transport;
 
-use std::{net::SocketAddr, sync::Arc};
-use tokio::net::UdpSocket;
+use std::sync::Arc;
 
-pub async fn run(socket: UdpSocket) {
-    let sock = Arc::new(socket);
-    listen(sock).await.unwrap();
+use transport::{Incoming, Transport, TransportError};
+
+pub async fn run(transport: impl Transport) {
+    listen(Arc::new(transport)).await.unwrap();
 }
 
-async fn listen(sock: Arc<UdpSocket>) -> Result<(), anyhow::Error> {
-    let mut buf = [0; 65535];
+async fn listen<T: Transport>(transport: Arc<T>) -> Result<(), anyhow::Error> {
     loop {
-        let (len, addr) = sock.recv_from(&mut buf).await?;
-        println!("{:?} bytes received from {:?}", len, addr);
-        let packet_handler = handle(Arc::clone(&sock), message::Raw::new(&buf[..len]), addr);
+        let incoming = match transport.recv().await {
+            Ok(incoming) => incoming,
+            // Not worth an answer, or no way to send one (RFC 3261 18.3)
+            Err(e @ (TransportError::Malformed { .. } | TransportError::Handshake { .. })) => {
+                eprintln!("{}", e);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        println!(
+            "Message received from {} over {}",
+            incoming.source, incoming.transport
+        );
+        let message_handler = handle(Arc::clone(&transport), incoming);
         tokio::spawn(async move {
-            packet_handler
+            message_handler
                 .await
                 .inspect_err(|e| eprintln!("Handle error: {:?}", e))
         });
     }
 }
 
-async fn handle(
-    sock: Arc<UdpSocket>,
-    packet: message::Raw,
-    from: SocketAddr,
-) -> Result<(), anyhow::Error> {
-    if let Ok((rest, msg)) = message::Message::parse(&packet) {
-        println!(
-            "The message is {:?}\n{:#?}",
-            msg,
-            msg.headers.sip_sweet_six()
-        );
-        println!("Message parsed until: {:?}", std::str::from_utf8(rest));
+async fn handle<T: Transport>(transport: Arc<T>, incoming: Incoming) -> Result<(), anyhow::Error> {
+    let Incoming {
+        message: msg,
+        source: from,
+        ..
+    } = incoming;
+    // Legacy gateways get answered, with what they got wrong logged
+    for warning in &msg.warnings {
+        eprintln!("Lenient parse of message from {}: {}", from, warning);
+    }
+    println!(
+        "The message is {:?}\n{:#?}",
+        msg,
+        msg.headers.sip_sweet_six()
+    );
+    if msg.start_line.is_request() {
+        let res = match msg.validate() {
+            Ok(()) => message::Response::from_request(&msg, message::StatusCode::OK)?,
+            Err(violations) => {
+                eprintln!("Invalid request from {}: {:?}", from, violations);
+                let mut res = message::Response::from_request(&msg, violations[0].status_code())?;
+                if let message::StartLine::Status(line) = &mut res.start_line {
+                    line.reason_phrase = violations[0].reason_phrase().into_bytes().into();
+                }
+                res
+            }
+        };
+        transport.send(&res, from).await?;
     }
-    sock.send_to(b"OK", from).await?;
     Ok(())
 }
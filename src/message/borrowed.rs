@@ -0,0 +1,396 @@
+use nom::{
+    bytes::complete::{tag, take_till1, take_until},
+    sequence::{terminated, tuple},
+};
+use ouroboros::self_referencing;
+
+use super::{
+    header::{Header, Value, WellKnown},
+    start_line::{RequestLine, StatusLine},
+    Limits, Message, Raw, StartLine, StatusCode,
+};
+use crate::{
+    error::{within, Element, Error},
+    parse_utils::{hcolon, token, ParseResult, CRLF, SIP_VERSION, SP},
+};
+
+// A message framed into slices of the buffer it came in. Header values are
+// not parsed until asked for, so nothing is copied or allocated per token.
+#[derive(Debug)]
+pub struct MessageRef<'a> {
+    pub start_line: StartLineRef<'a>,
+    pub headers: Vec<HeaderRef<'a>>,
+    pub body: &'a [u8],
+}
+
+#[derive(Debug)]
+pub enum StartLineRef<'a> {
+    Request {
+        method: &'a str,
+        uri: &'a str,
+    },
+    Status {
+        status_code: StatusCode,
+        reason_phrase: &'a [u8],
+    },
+}
+
+#[derive(Debug)]
+pub struct HeaderRef<'a> {
+    pub name: &'a str,
+    // Everything between HCOLON and the terminating CRLF, folding included
+    pub value: &'a [u8],
+}
+
+impl<'a> MessageRef<'a> {
+    pub fn parse(src: &'a [u8]) -> Result<(&'a [u8], Self), Error> {
+        Self::parse_with_limits(src, &Limits::DEFAULT)
+    }
+
+    pub fn parse_with_limits(src: &'a [u8], limits: &Limits) -> Result<(&'a [u8], Self), Error> {
+        limits.check_head(src)?;
+        let (rest, mut message) = Self::parse_head(src)?;
+        let content_length = message
+            .content_length()
+            .map_err(|e| e.locate(src))?
+            .unwrap_or(0);
+        limits
+            .check_body(content_length, rest)
+            .map_err(|e| e.locate(src))?;
+        if rest.len() < content_length {
+            return Err(Error::new(Element::Body, b"", nom::error::ErrorKind::Eof).locate(src));
+        }
+        message.body = &rest[..content_length];
+        Ok((&rest[content_length..], message))
+    }
+
+    // Start line and headers up to the empty line, leaving the body empty
+    pub fn parse_head(src: &'a [u8]) -> Result<(&'a [u8], Self), Error> {
+        let (rest, (start_line, headers)) = tuple((
+            within(Element::StartLine, StartLineRef::parse),
+            terminated(nom::multi::many0(HeaderRef::parse), tag(CRLF)),
+        ))(src)
+        .map_err(|e| Error::from(e).locate(src))?;
+        Ok((
+            rest,
+            Self {
+                start_line,
+                headers,
+                body: &[],
+            },
+        ))
+    }
+
+    pub fn content_length(&self) -> Result<Option<usize>, Error> {
+        let mut headers = self.headers_named(WellKnown::ContentLength.as_str());
+        let Some(header) = headers.next() else {
+            return Ok(None);
+        };
+        if headers.next().is_some() {
+            return Err(Error::new(
+                Element::Header("Content-Length".to_owned()),
+                b"",
+                nom::error::ErrorKind::Count,
+            ));
+        }
+        // The same 1*DIGIT as the owned message, so both frame alike
+        match Value::parse_field(WellKnown::ContentLength.as_str(), header.value)? {
+            Value::ContentLength(length) => Ok(Some(length)),
+            _ => unreachable!("Content-Length always parses to its own value"),
+        }
+    }
+
+    pub fn header(&self, known: WellKnown) -> Option<&HeaderRef<'a>> {
+        self.headers.iter().find(|header| header.is(known))
+    }
+
+    // Every header with the name, compact forms included, in wire order
+    pub fn headers_named<'s>(&'s self, name: &'s str) -> impl Iterator<Item = &'s HeaderRef<'a>> {
+        let known = WellKnown::from_name(name);
+        self.headers.iter().filter(move |header| match known {
+            Some(known) => header.is(known),
+            None => header.name.eq_ignore_ascii_case(name),
+        })
+    }
+
+    pub fn call_id(&self) -> Option<&'a [u8]> {
+        self.header(WellKnown::CallId).map(|header| header.value)
+    }
+}
+
+impl<'a> StartLineRef<'a> {
+    pub fn parse(src: &'a [u8]) -> ParseResult<'a, Self> {
+        // Status-Line  =  SIP-Version SP Status-Code SP Reason-Phrase CRLF
+        // Request-Line  =  Method SP Request-URI SP SIP-Version CRLF
+        if src.starts_with(b"SIP/") {
+            let (rest, (_, _, status_code, _, reason_phrase, _)) = tuple((
+                within(Element::SipVersion, tag(SIP_VERSION)),
+                tag(SP),
+                within(Element::StatusCode, StatusCode::parse),
+                tag(SP),
+                take_until(CRLF),
+                tag(CRLF),
+            ))(src)?;
+            Ok((
+                rest,
+                Self::Status {
+                    status_code,
+                    reason_phrase,
+                },
+            ))
+        } else {
+            let (rest, (method, _, uri, _, _, _)) = tuple((
+                within(Element::Method, token),
+                tag(SP),
+                within(Element::Uri, take_till1(|c: u8| c == b' ' || c == b'\r')),
+                tag(SP),
+                within(Element::SipVersion, tag(SIP_VERSION)),
+                tag(CRLF),
+            ))(src)?;
+            Ok((
+                rest,
+                Self::Request {
+                    method: ascii(method),
+                    uri: std::str::from_utf8(uri).map_err(|_| {
+                        nom::Err::Error(Error::new(Element::Uri, uri, nom::error::ErrorKind::Char))
+                    })?,
+                },
+            ))
+        }
+    }
+}
+
+impl<'a> HeaderRef<'a> {
+    pub fn parse(src: &'a [u8]) -> ParseResult<'a, Self> {
+        // message-header  =  field-name HCOLON field-value CRLF
+        // A CRLF followed by whitespace folds the value onto the next line
+        let (rest, (name, _)) = tuple((within(Element::HeaderName, token), hcolon))(src)?;
+        let mut end = 0;
+        loop {
+            match rest[end..].windows(2).position(|w| w == CRLF) {
+                Some(at) => {
+                    end += at;
+                    match rest.get(end + 2) {
+                        Some(b' ' | b'\t') => end += 2,
+                        _ => break,
+                    }
+                }
+                None => {
+                    return Err(nom::Err::Error(Error::new(
+                        Element::Header(ascii(name).to_owned()),
+                        &rest[rest.len()..],
+                        nom::error::ErrorKind::CrLf,
+                    )))
+                }
+            }
+        }
+        let value = rest[..end].trim_ascii_end();
+        Ok((
+            &rest[end + 2..],
+            Self {
+                name: ascii(name),
+                value,
+            },
+        ))
+    }
+
+    pub fn is(&self, known: WellKnown) -> bool {
+        WellKnown::from_name(self.name) == Some(known)
+    }
+
+    pub fn value_str(&self) -> Option<&'a str> {
+        std::str::from_utf8(self.value).ok()
+    }
+
+    // Parses the value into the typed representation of the owned message
+    pub fn parse_value(&self) -> Result<Value, Error> {
+        Value::parse_field(self.name, self.value)
+    }
+}
+
+// Where a slice of the buffer starts in it
+fn offset_in(buffer: &[u8], part: &[u8]) -> usize {
+    part.as_ptr() as usize - buffer.as_ptr() as usize
+}
+
+// A token is always ASCII
+fn ascii(token: &[u8]) -> &str {
+    std::str::from_utf8(token).unwrap_or_default()
+}
+
+// Owns the received buffer together with the message borrowing from it, so
+// the pair can be moved around, e.g. into a spawned task
+#[self_referencing]
+pub struct BorrowedMessage {
+    raw: Raw,
+    #[borrows(raw)]
+    #[covariant]
+    message: MessageRef<'this>,
+}
+
+impl BorrowedMessage {
+    pub fn parse(raw: Raw) -> Result<Self, Error> {
+        BorrowedMessageTryBuilder {
+            raw,
+            message_builder: |raw: &Raw| MessageRef::parse(&raw.data).map(|(_, message)| message),
+        }
+        .try_build()
+    }
+
+    pub fn message(&self) -> &MessageRef<'_> {
+        self.borrow_message()
+    }
+
+    pub fn raw(&self) -> &Raw {
+        self.borrow_raw()
+    }
+
+    // The owned message, made from the parts already split off. Header
+    // values stay unparsed until asked for.
+    pub fn to_owned(&self) -> Result<Message, Error> {
+        let raw = &self.borrow_raw().data;
+        let message = self.borrow_message();
+        let start_line = match message.start_line {
+            StartLineRef::Request { method, uri } => RequestLine::from_parts(method, uri)
+                .map(StartLine::Request)
+                .map_err(|e| {
+                    e.within(Element::StartLine)
+                        .shift(offset_in(raw, uri.as_bytes()))
+                })?,
+            StartLineRef::Status {
+                status_code,
+                reason_phrase,
+            } => StartLine::Status(StatusLine {
+                status_code,
+                reason_phrase: reason_phrase.into(),
+            }),
+        };
+        Ok(Message {
+            start_line,
+            headers: message
+                .headers
+                .iter()
+                .map(|header| Header::from(header).located_at(offset_in(raw, header.value)))
+                .collect(),
+            body: message.body.into(),
+            warnings: vec![],
+        })
+    }
+}
+
+impl std::fmt::Debug for BorrowedMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.borrow_message())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INVITE: &[u8] = b"INVITE sip:bob@biloxi.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nMax-Forwards: 70\r\nTo: Bob <sip:bob@biloxi.com>\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\ni: a84b4c76e66710@pc33.atlanta.com\r\nCSeq: 314159 INVITE\r\nSubject: lunch\r\n  at noon\r\nl: 4\r\n\r\nbody";
+
+    #[test]
+    fn it_works() {
+        let (rest, message) = MessageRef::parse(INVITE).unwrap();
+        assert!(rest.is_empty());
+        let StartLineRef::Request { method, uri } = message.start_line else {
+            unreachable!()
+        };
+        assert_eq!("INVITE", method);
+        assert_eq!("sip:bob@biloxi.com", uri);
+        assert_eq!(8, message.headers.len());
+        assert_eq!(
+            Some(b"a84b4c76e66710@pc33.atlanta.com".as_slice()),
+            message.call_id()
+        );
+        assert_eq!(
+            Some("lunch\r\n  at noon"),
+            message
+                .headers_named("subject")
+                .next()
+                .and_then(HeaderRef::value_str)
+        );
+        assert_eq!(b"body", message.body);
+        // Slices point into the buffer, nothing was copied
+        assert!(INVITE.as_ptr_range().contains(&message.body.as_ptr()));
+    }
+
+    #[test]
+    fn status_works() {
+        let (_, message) = MessageRef::parse(b"SIP/2.0 180 Ringing\r\nCall-ID: x\r\n\r\n").unwrap();
+        let StartLineRef::Status {
+            status_code,
+            reason_phrase,
+        } = message.start_line
+        else {
+            unreachable!()
+        };
+        assert_eq!(180, u16::from(status_code));
+        assert_eq!(b"Ringing", reason_phrase);
+        assert!(message.body.is_empty());
+    }
+
+    #[test]
+    fn values_parse_on_demand() {
+        let (_, message) = MessageRef::parse(INVITE).unwrap();
+        let cseq = message.headers_named("CSeq").next().unwrap();
+        assert_eq!("314159 INVITE", cseq.parse_value().unwrap().to_string());
+
+        let (_, message) =
+            MessageRef::parse(b"OPTIONS sip:x.com SIP/2.0\r\nMax-Forwards: many\r\n\r\n").unwrap();
+        let e = message.headers[0].parse_value().unwrap_err();
+        assert_eq!(
+            Some(&Element::Header("Max-Forwards".to_owned())),
+            e.element()
+        );
+    }
+
+    #[test]
+    fn owned_works() {
+        let message = BorrowedMessage::parse(Raw::new(INVITE)).unwrap();
+        assert_eq!(b"body", message.message().body);
+        let owned = message.to_owned().unwrap();
+        assert_eq!(Some(4), owned.headers.content_length());
+        assert_eq!(INVITE, message.raw().data.as_ref());
+        assert_eq!(b"body", owned.body.as_ref());
+        assert_eq!(8, owned.headers.len());
+        // Same headers in the same order, folding and compact forms kept
+        assert_eq!(INVITE, owned.to_bytes().as_slice());
+
+        // Values parsed later point into the message
+        let raw = Raw::new(b"OPTIONS sip:x.com SIP/2.0\r\nMax-Forwards: 7x\r\n\r\n");
+        let owned = BorrowedMessage::parse(raw).unwrap().to_owned().unwrap();
+        let e = owned.headers.max_forwards().unwrap().value().unwrap_err();
+        assert_eq!(Some(42), e.offset());
+
+        let raw = Raw::new(b"OPTIONS sip:x.com?subject=hi SIP/2.0\r\n\r\n");
+        let message = BorrowedMessage::parse(raw).unwrap();
+        let e = message.to_owned().unwrap_err();
+        assert_eq!(&[Element::StartLine, Element::Uri], e.elements());
+    }
+
+    #[test]
+    fn content_length_is_digits() {
+        let e = MessageRef::parse(b"BYE sip:x.com SIP/2.0\r\nl: +4\r\n\r\nbody").unwrap_err();
+        assert_eq!(
+            Some(&Element::Header("Content-Length".to_owned())),
+            e.element()
+        );
+        assert!(Message::parse(b"BYE sip:x.com SIP/2.0\r\nl: +4\r\n\r\nbody").is_err());
+        let (_, message) =
+            MessageRef::parse(b"BYE sip:x.com SIP/2.0\r\nl: 4 \r\n\r\nbody").unwrap();
+        assert_eq!(Some(4), message.content_length().unwrap());
+    }
+
+    #[test]
+    fn framing_errors_are_reported() {
+        let e = MessageRef::parse(b"SIP/2.0 200 OK\r\nCall-ID: x\r\n").unwrap_err();
+        assert_eq!(Some(28), e.offset());
+        let e = MessageRef::parse(b"BYE sip:x.com SIP/2.0\r\nl: 10\r\n\r\nshort").unwrap_err();
+        assert_eq!(Some(&Element::Body), e.element());
+        let e = MessageRef::parse(b"BYE sip:x.com SIP/3.0\r\n\r\n").unwrap_err();
+        assert_eq!(&[Element::StartLine, Element::SipVersion], e.elements());
+        assert!(BorrowedMessage::parse(Raw::new(b"garbage")).is_err());
+    }
+}
@@ -0,0 +1,430 @@
+use std::{
+    hash::{BuildHasher, RandomState},
+    sync::OnceLock,
+};
+
+use rand::{distributions::Alphanumeric, Rng};
+
+use super::{
+    header::{
+        Address, Contact, ContactAddress, DisplayName, Header, Map, MediaType, Spec, TagParam,
+        Transport, Value, Via, WellKnown,
+    },
+    start_line::{RequestLine, StatusLine},
+    Host, Message, Method, StartLine, StatusCode, Uri,
+};
+
+// Marks a branch as unique per RFC 3261 (8.1.1.7)
+pub const BRANCH_MAGIC_COOKIE: &str = "z9hG4bK";
+
+// Entry points for building messages, both of which produce a Message
+pub struct Request;
+pub struct Response;
+
+pub struct RequestBuilder {
+    method: Method,
+    uri: Uri,
+    via: Option<(Transport, Host, Option<u16>)>,
+    from: Option<Spec>,
+    to: Option<Spec>,
+    call_id: Option<String>,
+    cseq: u32,
+    max_forwards: usize,
+    contact: Option<Spec>,
+    headers: Vec<Header>,
+    content_type: Option<MediaType>,
+    body: Box<[u8]>,
+}
+
+impl Request {
+    pub fn builder(method: Method, uri: Uri) -> RequestBuilder {
+        RequestBuilder {
+            method,
+            uri,
+            via: None,
+            from: None,
+            to: None,
+            call_id: None,
+            cseq: 1,
+            max_forwards: 70,
+            contact: None,
+            headers: vec![],
+            content_type: None,
+            body: Box::default(),
+        }
+    }
+}
+
+impl RequestBuilder {
+    // Where responses are to be sent, the branch is always generated
+    pub fn via(mut self, transport: Transport, host: Host, port: Option<u16>) -> Self {
+        self.via = Some((transport, host, port));
+        self
+    }
+
+    // Defaults to the anonymous From of RFC 3261 8.1.1.3, with a new tag
+    // either way
+    pub fn from(mut self, from: impl Into<Spec>) -> Self {
+        self.from = Some(from.into());
+        self
+    }
+
+    // Defaults to the Request-URI
+    pub fn to(mut self, to: impl Into<Spec>) -> Self {
+        self.to = Some(to.into());
+        self
+    }
+
+    // Defaults to a random one
+    pub fn call_id(mut self, call_id: impl Into<String>) -> Self {
+        self.call_id = Some(call_id.into());
+        self
+    }
+
+    pub fn cseq(mut self, num: u32) -> Self {
+        self.cseq = num;
+        self
+    }
+
+    pub fn max_forwards(mut self, max_forwards: usize) -> Self {
+        self.max_forwards = max_forwards;
+        self
+    }
+
+    pub fn contact(mut self, contact: impl Into<Spec>) -> Self {
+        self.contact = Some(contact.into());
+        self
+    }
+
+    // Any other header, added after the generated ones
+    pub fn header(mut self, header: Header) -> Self {
+        self.headers.push(header);
+        self
+    }
+
+    pub fn body(mut self, content_type: MediaType, body: impl Into<Box<[u8]>>) -> Self {
+        self.content_type = Some(content_type);
+        self.body = body.into();
+        self
+    }
+
+    pub fn build(self) -> Result<Message, anyhow::Error> {
+        let (transport, host, port) = self
+            .via
+            .ok_or_else(|| anyhow::anyhow!("a request needs a Via sent-by"))?;
+        let from = match self.from {
+            Some(from) => from,
+            None => Spec::NameAddr {
+                display_name: DisplayName::Quoted("Anonymous".to_owned()),
+                addr_spec: "sip:anonymous@anonymous.invalid".parse()?,
+            },
+        };
+        let to = self.to.unwrap_or_else(|| self.uri.clone().into());
+
+        let mut headers = Map::new();
+        let mut add =
+            |name: WellKnown, value: Value| headers.insert_bottom(Header::new(name, value));
+        add(
+            WellKnown::Via,
+            Value::Via(Via::new(transport, host, port, new_branch())),
+        );
+        add(
+            WellKnown::MaxForwards,
+            Value::MaxForwards(self.max_forwards),
+        );
+        add(
+            WellKnown::To,
+            Value::To {
+                address: address(to),
+                params: vec![],
+            },
+        );
+        add(
+            WellKnown::From,
+            Value::From {
+                address: address(from),
+                params: vec![TagParam::Tag(new_tag())],
+            },
+        );
+        let call_id = self.call_id.unwrap_or_else(new_call_id);
+        add(
+            WellKnown::CallId,
+            Value::CallId(call_id.into_bytes().into()),
+        );
+        add(
+            WellKnown::CSeq,
+            Value::CSeq {
+                num: self.cseq,
+                method: self.method.clone(),
+            },
+        );
+        if let Some(contact) = self.contact {
+            add(
+                WellKnown::Contact,
+                Value::Contact(Contact::Addresses(vec![ContactAddress {
+                    spec: contact,
+                    params: vec![],
+                }])),
+            );
+        }
+        for header in self.headers {
+            headers.insert_bottom(header);
+        }
+        if let Some(content_type) = self.content_type {
+            headers.insert_bottom(Header::new(
+                WellKnown::ContentType,
+                Value::ContentType(content_type),
+            ));
+        }
+        headers.insert_bottom(Header::new(
+            WellKnown::ContentLength,
+            Value::ContentLength(self.body.len()),
+        ));
+
+        Ok(Message {
+            start_line: StartLine::Request(RequestLine {
+                method: self.method,
+                uri: self.uri,
+            }),
+            headers,
+            body: self.body,
+            warnings: vec![],
+        })
+    }
+}
+
+impl Response {
+    // A response as a UAS builds it (RFC 3261 8.2.6), with the canonical
+    // reason phrase and no body
+    pub fn from_request(req: &Message, status: StatusCode) -> Result<Message, anyhow::Error> {
+        if !req.start_line.is_request() {
+            anyhow::bail!("responses are built from requests only");
+        }
+        let mut headers = Map::new();
+        for via in req.headers.get_all("via") {
+            headers.insert_bottom(via.clone());
+        }
+        if let Some(from) = req.headers.from() {
+            headers.insert_bottom(from.clone());
+        }
+        if let Some(to) = req.headers.to() {
+            let mut to = to.clone();
+            // Only 100 Trying may go without a tag. One that does not parse
+            // goes back as it came, so the 400 for it can still be sent
+            if status != StatusCode::TRYING {
+                match to.value_mut() {
+                    Ok(value @ Value::To { .. }) if value.tag().is_some() => {}
+                    Ok(Value::To { params, .. }) => params.push(TagParam::Tag(to_tag(req))),
+                    Ok(_) => unreachable!("a To header holds a To value"),
+                    Err(_) => {}
+                }
+            }
+            headers.insert_bottom(to);
+        }
+        for name in ["call-id", "cseq"] {
+            if let Some(header) = req.headers.get(name) {
+                headers.insert_bottom(header.clone());
+            }
+        }
+        headers.insert_bottom(Header::new(
+            WellKnown::ContentLength,
+            Value::ContentLength(0),
+        ));
+        Ok(Message {
+            start_line: StartLine::Status(StatusLine::new(status)),
+            headers,
+            body: Box::default(),
+            warnings: vec![],
+        })
+    }
+}
+
+fn address(spec: Spec) -> Address {
+    Address {
+        spec,
+        params: vec![],
+    }
+}
+
+fn random_token(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+pub fn new_branch() -> String {
+    format!("{}{}", BRANCH_MAGIC_COOKIE, random_token(16))
+}
+
+// Tags need at least 32 random bits (RFC 3261 19.3)
+pub fn new_tag() -> String {
+    random_token(10)
+}
+
+pub fn new_call_id() -> String {
+    random_token(24)
+}
+
+// Derived from the request with a per-process key, so that all responses
+// to the same request carry the same tag
+fn to_tag(req: &Message) -> String {
+    static KEY: OnceLock<RandomState> = OnceLock::new();
+    let field = |name: &str| {
+        req.headers
+            .get(name)
+            .map(ToString::to_string)
+            .unwrap_or_default()
+    };
+    let hash = KEY.get_or_init(RandomState::new).hash_one((
+        field("call-id"),
+        field("from"),
+        field("cseq"),
+        field("via"),
+    ));
+    format!("{:x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invite() -> Message {
+        Request::builder(Method::Invite, "sip:bob@biloxi.com".parse().unwrap())
+            .via(
+                Transport::Udp,
+                Host::Domain("pc33.atlanta.com".to_owned()),
+                None,
+            )
+            .from(Spec::NameAddr {
+                display_name: DisplayName::Plain("Alice".to_owned()),
+                addr_spec: "sip:alice@atlanta.com".parse().unwrap(),
+            })
+            .contact("sip:alice@pc33.atlanta.com".parse::<Uri>().unwrap())
+            .body(MediaType::new("application", "sdp"), b"v=0\r\n".as_slice())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn request_works() {
+        let req = invite();
+        let bytes = req.to_bytes();
+        let (rest, parsed) = Message::parse(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert!(parsed.headers.sip_sweet_six().is_some());
+        assert_eq!(b"v=0\r\n".as_slice(), &*parsed.body);
+
+        let Ok(Value::Via(via)) = parsed.headers.via().unwrap().value() else {
+            unreachable!()
+        };
+        assert!(via.branch().unwrap().starts_with(BRANCH_MAGIC_COOKIE));
+        assert!(via
+            .to_string()
+            .starts_with("SIP/2.0/UDP pc33.atlanta.com;branch="));
+        assert!(matches!(
+            parsed.headers.get("max-forwards").unwrap().value(),
+            Ok(Value::MaxForwards(70))
+        ));
+        assert_eq!(
+            "<sip:bob@biloxi.com>",
+            parsed.headers.to().unwrap().value().unwrap().to_string()
+        );
+        let from = parsed.headers.from().unwrap().value().unwrap();
+        assert!(from
+            .to_string()
+            .starts_with("Alice <sip:alice@atlanta.com>;tag="));
+        assert!(from.tag().unwrap().len() >= 8);
+        assert!(matches!(
+            parsed.headers.cseq().unwrap().value(),
+            Ok(Value::CSeq {
+                num: 1,
+                method: Method::Invite
+            })
+        ));
+        assert_eq!(Some(5), parsed.headers.content_length());
+        assert!(parsed.content_type().unwrap().is("application", "sdp"));
+
+        // Every request is a new transaction and a new call
+        let other = invite();
+        assert_ne!(
+            req.headers.call_id().unwrap().to_string(),
+            other.headers.call_id().unwrap().to_string()
+        );
+        assert_ne!(
+            req.headers.via().unwrap().to_string(),
+            other.headers.via().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn via_is_required() {
+        let builder = Request::builder(Method::Options, "sip:biloxi.com".parse().unwrap());
+        assert!(builder.build().is_err());
+        let req = Request::builder(Method::Options, "sip:biloxi.com".parse().unwrap())
+            .via(
+                Transport::Tcp,
+                Host::Ipv4([192, 0, 2, 1].into()),
+                Some(5060),
+            )
+            .build()
+            .unwrap();
+        assert!(req
+            .headers
+            .from()
+            .unwrap()
+            .to_string()
+            .contains("anonymous.invalid"));
+    }
+
+    #[test]
+    fn response_works() {
+        let src = b"INVITE sip:bob@biloxi.com SIP/2.0\r\nVia: SIP/2.0/UDP p1.example.com;branch=z9hG4bK1\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK2\r\nMax-Forwards: 69\r\nTo: Bob <sip:bob@biloxi.com>\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\nCall-ID: a84b4c76e66710\r\nCSeq: 314159 INVITE\r\nContent-Length: 0\r\n\r\n";
+        let (_, req) = Message::parse(src).unwrap();
+
+        let trying = Response::from_request(&req, StatusCode::TRYING).unwrap();
+        assert_eq!(
+            "SIP/2.0 100 Trying\r\nVia: SIP/2.0/UDP p1.example.com;branch=z9hG4bK1\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK2\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\nTo: Bob <sip:bob@biloxi.com>\r\nCall-ID: a84b4c76e66710\r\nCSeq: 314159 INVITE\r\nContent-Length: 0\r\n\r\n",
+            String::from_utf8(trying.to_bytes()).unwrap()
+        );
+
+        let ringing = Response::from_request(&req, StatusCode::RINGING).unwrap();
+        let ok = Response::from_request(&req, StatusCode::OK).unwrap();
+        let tag = |res: &Message| {
+            res.headers
+                .to()
+                .unwrap()
+                .value()
+                .unwrap()
+                .tag()
+                .map(str::to_owned)
+        };
+        assert!(tag(&ringing).is_some());
+        assert_eq!(tag(&ringing), tag(&ok));
+        assert_eq!("SIP/2.0 200 OK", ok.start_line.to_string());
+        let bytes = ok.to_bytes();
+        let (rest, res) = Message::parse(&bytes).unwrap();
+        assert!(rest.is_empty());
+
+        // An existing tag is kept
+        let mut req = req;
+        req.headers.replace(res.headers.to().unwrap().clone());
+        let bye = Response::from_request(&req, StatusCode::OK).unwrap();
+        assert_eq!(tag(&ok), tag(&bye));
+
+        assert!(Response::from_request(&ok, StatusCode::OK).is_err());
+
+        // A broken To can still be answered
+        let src = String::from_utf8(src.to_vec()).unwrap().replace(
+            "To: Bob <sip:bob@biloxi.com>",
+            "To: Bob <sip:bob@biloxi.com",
+        );
+        let (_, req) = Message::parse(src.as_bytes()).unwrap();
+        let bad_request = Response::from_request(&req, StatusCode::BAD_REQUEST).unwrap();
+        assert_eq!(
+            "To: Bob <sip:bob@biloxi.com",
+            bad_request.headers.to().unwrap().to_string()
+        );
+    }
+}
@@ -1,31 +1,184 @@
-use nom::IResult;
+use nom::error::ErrorKind;
 
-use crate::parse_utils::{equal, token, ParseResult};
+use crate::{
+    error::{Element, Error},
+    parse_utils::{equal, parse_host, parse_quoted_string, quote, token, ParseResult, CRLF},
+};
 
-use super::{header, start_line::StartLine};
+use super::{header, lenient::Head, start_line::StartLine, Limits, Mode, ParseOptions, Warning};
 
 #[derive(Debug)]
 pub struct Message {
     pub start_line: StartLine,
     pub headers: header::Map,
     pub body: Box<[u8]>,
+    // What lenient parsing let through, empty otherwise
+    pub warnings: Vec<Warning>,
+}
+
+// Where the message ends: at its Content-Length on a stream, with the
+// datagram otherwise
+#[derive(Clone, Copy, PartialEq)]
+enum Framing {
+    Stream,
+    Datagram,
 }
 
 impl Message {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
-        let (rest, (start_line, headers)) =
-            nom::sequence::tuple((StartLine::parse, header::Map::parse))(src)?;
-        let content_length = headers.content_length().unwrap_or(0);
-        let body = rest[..content_length].to_vec().into_boxed_slice();
+    pub fn parse(src: &[u8]) -> Result<(&[u8], Self), Error> {
+        Self::parse_with_limits(src, &Limits::DEFAULT)
+    }
+
+    pub fn parse_with_limits<'a>(
+        src: &'a [u8],
+        limits: &Limits,
+    ) -> Result<(&'a [u8], Self), Error> {
+        let options = ParseOptions {
+            mode: Mode::Strict,
+            limits: *limits,
+        };
+        Self::parse_framed(src, &options, Framing::Stream)
+    }
+
+    pub fn parse_with_options<'a>(
+        src: &'a [u8],
+        options: &ParseOptions,
+    ) -> Result<(&'a [u8], Self), Error> {
+        Self::parse_framed(src, options, Framing::Stream)
+    }
+
+    // The whole of a UDP datagram or WebSocket message. The body is what
+    // follows the headers, cut to the Content-Length, and a shorter one is
+    // left for `validate` to turn into a 400 (RFC 3261 18.3)
+    pub fn parse_datagram(src: &[u8], options: &ParseOptions) -> Result<Self, Error> {
+        Self::parse_framed(src, options, Framing::Datagram).map(|(_, message)| message)
+    }
+
+    fn parse_framed<'a>(
+        src: &'a [u8],
+        options: &ParseOptions,
+        framing: Framing,
+    ) -> Result<(&'a [u8], Self), Error> {
+        if options.mode == Mode::Strict {
+            options.limits.check_head(src)?;
+            let (rest, start_line, headers) = Self::head(src)?;
+            return Self::frame(src, rest, start_line, headers, &options.limits, framing);
+        }
+        // Errors in the head are located in the rewritten head, which only
+        // differs from the input where a warning was recorded
+        let head = Head::normalize(src);
+        options.limits.check_head(&head.bytes)?;
+        let (_, start_line, headers) = Self::head(&head.bytes)?;
+        let (rest, mut message) = Self::frame(
+            src,
+            &src[head.consumed..],
+            start_line,
+            headers,
+            &options.limits,
+            framing,
+        )?;
+        message.warnings = head.warnings;
+        Ok((rest, message))
+    }
+
+    // Start line and headers, values located in the message for when they
+    // are parsed
+    fn head(src: &[u8]) -> Result<(&[u8], StartLine, header::Map), Error> {
+        let (after_line, start_line) =
+            StartLine::parse(src).map_err(|e| Error::from(e).locate(src))?;
+        let (rest, mut headers) =
+            header::Map::parse(after_line).map_err(|e| Error::from(e).locate(src))?;
+        headers.shift(src.len() - after_line.len());
+        Ok((rest, start_line, headers))
+    }
+
+    // The start line and headers of a message on a stream, up to and with
+    // the empty line, and the length of the body still to come. Limits are
+    // up to the caller, who checks them as the head arrives
+    pub(crate) fn parse_head(src: &[u8]) -> Result<(Self, Option<usize>), Error> {
+        let (_, start_line, headers) = Self::head(src)?;
+        let content_length = Self::content_length(&headers)?;
+        let message = Self {
+            start_line,
+            headers,
+            body: Box::default(),
+            warnings: vec![],
+        };
+        Ok((message, content_length))
+    }
+
+    fn content_length(headers: &header::Map) -> Result<Option<usize>, Error> {
+        // Framing needs the one header value parsed up front, and more than
+        // one leaves the body length ambiguous (RFC 4475 3.3.9)
+        match headers.get_all("content-length").as_slice() {
+            [] => Ok(None),
+            [header] => match header.value() {
+                Ok(header::Value::ContentLength(length)) => Ok(Some(*length)),
+                Ok(_) => unreachable!("Content-Length always parses to its own value"),
+                Err(e) => Err(e.clone()),
+            },
+            _ => Err(Error::new(
+                Element::Header("Content-Length".to_owned()),
+                b"",
+                ErrorKind::Count,
+            )),
+        }
+    }
+
+    // Takes the body off what follows the headers
+    fn frame<'a>(
+        src: &'a [u8],
+        rest: &'a [u8],
+        start_line: StartLine,
+        headers: header::Map,
+        limits: &Limits,
+        framing: Framing,
+    ) -> Result<(&'a [u8], Self), Error> {
+        let content_length = match Self::content_length(&headers)? {
+            Some(length) => length,
+            None if framing == Framing::Datagram => rest.len(),
+            None => 0,
+        };
+        limits
+            .check_body(content_length, rest)
+            .map_err(|e| e.locate(src))?;
+        let (body, rest) = match framing {
+            Framing::Stream if rest.len() < content_length => {
+                return Err(Error::new(Element::Body, b"", ErrorKind::Eof).locate(src))
+            }
+            Framing::Stream => rest.split_at(content_length),
+            Framing::Datagram => (&rest[..content_length.min(rest.len())], &b""[..]),
+        };
         Ok((
-            &rest[content_length..],
+            rest,
             Self {
                 start_line,
                 headers,
-                body,
+                body: body.into(),
+                warnings: vec![],
             },
         ))
     }
+
+    pub fn content_type(&self) -> Option<&header::MediaType> {
+        self.headers.content_type()
+    }
+
+    pub fn write_to(&self, w: &mut impl std::io::Write) -> std::io::Result<()> {
+        // Content-Length is always recomputed from the body being written
+        write!(w, "{}", self.start_line)?;
+        w.write_all(CRLF)?;
+        self.headers.write_with_content_length(w, self.body.len())?;
+        w.write_all(CRLF)?;
+        w.write_all(&self.body)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.body.len() + 512);
+        self.write_to(&mut bytes)
+            .expect("writing to a Vec never fails");
+        bytes
+    }
 }
 
 #[derive(Debug)]
@@ -35,9 +188,9 @@ pub struct GenericParam {
 }
 
 impl GenericParam {
-    pub fn parse(src: &[u8]) -> ParseResult<Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         let (remainder, name) =
-            nom::combinator::map(token, |name| String::from_utf8(name.to_vec()).unwrap())(src)?;
+            nom::combinator::map(token, |name| String::from_utf8_lossy(name).into_owned())(src)?;
         let (rest, maybe_value) =
             nom::multi::many_m_n(0, 1, nom::sequence::tuple((equal, GenValue::parse)))(remainder)?;
         Ok((
@@ -50,16 +203,23 @@ impl GenericParam {
     }
 }
 
-impl ToString for GenericParam {
-    fn to_string(&self) -> String {
-        format!(
-            "{}{}",
-            self.name,
-            self.value
-                .as_ref()
-                .map(|v| format!("={}", v.to_string()))
-                .unwrap_or_default()
-        )
+impl GenericParam {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> Option<&GenValue> {
+        self.value.as_ref()
+    }
+}
+
+impl std::fmt::Display for GenericParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(ref value) = self.value {
+            write!(f, "={}", value)?;
+        }
+        Ok(())
     }
 }
 
@@ -71,19 +231,54 @@ pub enum GenValue {
 }
 
 impl GenValue {
-    pub fn parse(src: &[u8]) -> ParseResult<Self> {
-        nom::combinator::map(token, |x| {
-            Self::Token(String::from_utf8(x.to_vec()).unwrap())
-        })(src)
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // gen-value  =  token / host / quoted-string
+        // Domain names and IPv4 addresses are also tokens, so only IPv6
+        // references end up as hosts
+        nom::branch::alt((
+            nom::combinator::map(token, |x| {
+                Self::Token(String::from_utf8_lossy(x).into_owned())
+            }),
+            nom::combinator::map(parse_host, |host| Self::Host(host.to_string())),
+            nom::combinator::map(parse_quoted_string, Self::Quoted),
+        ))(src)
     }
 }
 
-impl ToString for GenValue {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for GenValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            GenValue::Token(token) => token.to_string(),
-            GenValue::Host(host) => host.to_string(),
-            GenValue::Quoted(quoted) => format!("\"{}\"", quoted),
+            GenValue::Token(token) => write!(f, "{}", token),
+            GenValue::Host(host) => write!(f, "{}", host),
+            GenValue::Quoted(quoted) => write!(f, "{}", quote(quoted)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gen_value_works() {
+        let (rest, param) = GenericParam::parse(b"received=[2001:DB8::9:255];x").unwrap();
+        assert_eq!(b";x", rest);
+        assert!(matches!(param.value(), Some(GenValue::Host(_))));
+        assert_eq!("received=[2001:db8::9:255]", param.to_string());
+
+        let (_, param) = GenericParam::parse(b"maddr=192.0.2.1").unwrap();
+        assert!(matches!(param.value(), Some(GenValue::Token(_))));
+
+        let (rest, param) = GenericParam::parse(b"note = \"a \\\"b\\\" ; c\" ;x").unwrap();
+        assert_eq!(b" ;x", rest);
+        assert_eq!("note", param.name());
+        let Some(GenValue::Quoted(note)) = param.value() else {
+            unreachable!()
+        };
+        assert_eq!("a \"b\" ; c", note);
+        assert_eq!("note=\"a \\\"b\\\" ; c\"", param.to_string());
+
+        let (_, param) = GenericParam::parse(b"lr").unwrap();
+        assert!(param.value().is_none());
+    }
+}
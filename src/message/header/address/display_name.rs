@@ -1,4 +1,4 @@
-use crate::parse_utils::{lws, parse_quoted_string, token, ParseResult};
+use crate::parse_utils::{parse_quoted_string, quote, sws, token, ParseResult};
 
 #[derive(Debug)]
 pub enum DisplayName {
@@ -7,18 +7,36 @@ pub enum DisplayName {
 }
 
 impl DisplayName {
-    pub fn parse(src: &[u8]) -> ParseResult<Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         nom::branch::alt((parse_quoted, parse_plain))(src)
     }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            DisplayName::Plain(name) => name.is_empty(),
+            DisplayName::Quoted(_) => false,
+        }
+    }
+}
+
+impl std::fmt::Display for DisplayName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisplayName::Plain(name) => write!(f, "{}", name),
+            DisplayName::Quoted(name) => write!(f, "{}", quote(name)),
+        }
+    }
 }
 
-fn parse_plain(src: &[u8]) -> ParseResult<DisplayName> {
+fn parse_plain(src: &[u8]) -> ParseResult<'_, DisplayName> {
+    // display-name  =  *(token LWS) / quoted-string
+    // The LWS before LAQUOT may be missing (RFC 4475 3.1.1.6)
     nom::combinator::map(
-        nom::multi::many0(nom::sequence::tuple((token, lws))),
+        nom::multi::many0(nom::sequence::tuple((token, sws))),
         |tokens| {
             let s = tokens
                 .into_iter()
-                .map(|(token, _)| String::from_utf8(token.to_vec()).unwrap())
+                .map(|(token, _)| String::from_utf8_lossy(token).into_owned())
                 .collect::<Vec<_>>()
                 .join(" ");
             DisplayName::Plain(s)
@@ -26,8 +44,8 @@ fn parse_plain(src: &[u8]) -> ParseResult<DisplayName> {
     )(src)
 }
 
-fn parse_quoted(src: &[u8]) -> ParseResult<DisplayName> {
-    nom::combinator::map(parse_quoted_string, |s| DisplayName::Quoted(s))(src)
+fn parse_quoted(src: &[u8]) -> ParseResult<'_, DisplayName> {
+    nom::combinator::map(parse_quoted_string, DisplayName::Quoted)(src)
 }
 
 #[cfg(test)]
@@ -58,6 +76,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn escapes_are_kept() {
+        let raw = b"\"J Rosenberg \\\\\\\"\" <sip:";
+        let (rest, display_name) = DisplayName::parse(raw).unwrap();
+        assert_eq!(b" <sip:", rest);
+        let DisplayName::Quoted(ref s) = display_name else {
+            unreachable!()
+        };
+        assert_eq!("J Rosenberg \\\"", s);
+        assert_eq!(&raw[..raw.len() - 6], display_name.to_string().as_bytes());
+    }
+
     #[test]
     fn empty_works() {
         let raw = b"<URI>";
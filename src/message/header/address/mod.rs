@@ -2,7 +2,8 @@ mod display_name;
 mod param;
 mod spec;
 
-use self::{param::Param, spec::Spec};
+use self::param::Param;
+pub use self::{display_name::DisplayName, spec::Spec};
 use crate::parse_utils::ParseResult;
 
 #[derive(Debug)]
@@ -22,26 +23,22 @@ impl TryFrom<Box<[u8]>> for Address {
 }
 
 impl Address {
-    pub fn parse(src: &[u8]) -> ParseResult<Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         // spec =  (name-addr / addr-spec ) *( SEMI param )
         nom::combinator::map(
-            nom::sequence::tuple((Spec::parse, nom::multi::many0(Param::parse))),
+            nom::sequence::tuple((Spec::parse_before_params, nom::multi::many0(Param::parse))),
             |(spec, params)| Self { spec, params },
         )(src)
     }
 }
 
-impl ToString for Address {
-    fn to_string(&self) -> String {
-        format!(
-            "{};{}",
-            self.spec.to_string(),
-            self.params
-                .iter()
-                .map(|p| p.to_string())
-                .collect::<Vec<_>>()
-                .join(";")
-        )
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.spec)?;
+        for param in self.params.iter() {
+            write!(f, ";{}", param)?;
+        }
+        Ok(())
     }
 }
 
@@ -55,5 +52,16 @@ mod tests {
         let (rest, addr) = Address::parse(raw).unwrap();
         println!("addr={:?}", addr);
         assert!(rest.is_empty());
+        assert_eq!("Some One John <sip:john@some.one>", addr.to_string());
+    }
+
+    #[test]
+    fn addr_spec_to_string() {
+        let (_, addr) = Address::parse(b"sip:john@some.one").unwrap();
+        assert_eq!("sip:john@some.one", addr.to_string());
+        let (_, addr) = Address::parse(b"<sip:127.0.0.1:5060>").unwrap();
+        assert_eq!("<sip:127.0.0.1:5060>", addr.to_string());
+        let (_, addr) = Address::parse(b"\"John Doe\" <sip:john@some.one>").unwrap();
+        assert_eq!("\"John Doe\" <sip:john@some.one>", addr.to_string());
     }
 }
@@ -12,24 +12,27 @@ pub enum Param {
 }
 
 impl Param {
-    pub fn parse(src: &[u8]) -> ParseResult<Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         nom::branch::alt((parse_tag, parse_generic))(src)
     }
 }
 
-impl ToString for Param {
-    fn to_string(&self) -> String {
-        todo!()
+impl std::fmt::Display for Param {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Param::Tag(tag) => write!(f, "tag={}", tag),
+            Param::Generic(param) => write!(f, "{}", param),
+        }
     }
 }
 
-fn parse_tag(src: &[u8]) -> ParseResult<Param> {
+fn parse_tag(src: &[u8]) -> ParseResult<'_, Param> {
     nom::combinator::map(
         tuple((nom::bytes::complete::tag(b"tag"), equal, token)),
-        |(_, _, tag)| Param::Tag(String::from_utf8(tag.to_vec()).unwrap()),
+        |(_, _, tag)| Param::Tag(String::from_utf8_lossy(tag).into_owned()),
     )(src)
 }
 
-fn parse_generic(src: &[u8]) -> ParseResult<Param> {
-    nom::combinator::map(GenericParam::parse, |param| Param::Generic(param))(src)
+fn parse_generic(src: &[u8]) -> ParseResult<'_, Param> {
+    nom::combinator::map(GenericParam::parse, Param::Generic)(src)
 }
@@ -14,18 +14,52 @@ pub enum Spec {
 }
 
 impl Spec {
-    pub fn parse(src: &[u8]) -> ParseResult<Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         nom::branch::alt((parse_name, parse_spec))(src)
     }
+
+    pub fn parse_before_params(src: &[u8]) -> ParseResult<'_, Self> {
+        // RFC 3261 20: an addr-spec outside of angle brackets cannot contain
+        // a comma, semicolon or question mark, so parameters following it
+        // belong to the header field and not to the URI
+        nom::branch::alt((parse_name, parse_bare_spec))(src)
+    }
+
+    pub fn uri(&self) -> &Uri {
+        match self {
+            Spec::NameAddr { addr_spec, .. } => addr_spec,
+            Spec::AddrSpec(uri) => uri,
+        }
+    }
+}
+
+// A name-addr without a display name, which is always safe to write
+impl From<Uri> for Spec {
+    fn from(uri: Uri) -> Self {
+        Spec::NameAddr {
+            display_name: DisplayName::Plain(String::new()),
+            addr_spec: uri,
+        }
+    }
 }
 
-impl ToString for Spec {
-    fn to_string(&self) -> String {
-        todo!()
+impl std::fmt::Display for Spec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Spec::NameAddr {
+                display_name,
+                addr_spec,
+            } if display_name.is_empty() => write!(f, "<{}>", addr_spec),
+            Spec::NameAddr {
+                display_name,
+                addr_spec,
+            } => write!(f, "{} <{}>", display_name, addr_spec),
+            Spec::AddrSpec(uri) => write!(f, "{}", uri),
+        }
     }
 }
 
-fn parse_name(src: &[u8]) -> ParseResult<Spec> {
+fn parse_name(src: &[u8]) -> ParseResult<'_, Spec> {
     // name-addr      =  [ display-name ] LAQUOT addr-spec RAQUO
     nom::combinator::map(
         nom::sequence::tuple((DisplayName::parse, laquot, Uri::parse, raquot)),
@@ -36,8 +70,22 @@ fn parse_name(src: &[u8]) -> ParseResult<Spec> {
     )(src)
 }
 
-fn parse_spec(src: &[u8]) -> ParseResult<Spec> {
-    nom::combinator::map(Uri::parse, |uri| Spec::AddrSpec(uri))(src)
+fn parse_spec(src: &[u8]) -> ParseResult<'_, Spec> {
+    nom::combinator::map(Uri::parse, Spec::AddrSpec)(src)
+}
+
+fn parse_bare_spec(src: &[u8]) -> ParseResult<'_, Spec> {
+    let end = src
+        .iter()
+        .position(|c| b",;? \t\r\n".contains(c))
+        .unwrap_or(src.len());
+    match Uri::parse(&src[..end]) {
+        Ok((b"", uri)) => Ok((&src[end..], Spec::AddrSpec(uri))),
+        _ => Err(nom::Err::Error(nom::error::make_error(
+            src,
+            nom::error::ErrorKind::Verify,
+        ))),
+    }
 }
 
 #[cfg(test)]
@@ -51,4 +99,24 @@ mod tests {
         println!("spec={:?}", spec);
         assert!(rest.is_empty());
     }
+
+    #[test]
+    fn tel_name_addr_works() {
+        let raw = b"<tel:+15551234>";
+        let (rest, spec) = Spec::parse(raw).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!("<tel:+15551234>", spec.to_string());
+    }
+
+    #[test]
+    fn bare_spec_leaves_params() {
+        let (rest, spec) = Spec::parse_before_params(b"sip:bob@biloxi.com;expires=60").unwrap();
+        assert_eq!(b";expires=60", rest);
+        assert_eq!("sip:bob@biloxi.com", spec.uri().to_string());
+
+        let (rest, spec) =
+            Spec::parse_before_params(b"<sip:bob@biloxi.com;transport=tcp>;expires=60").unwrap();
+        assert_eq!(b";expires=60", rest);
+        assert_eq!("sip:bob@biloxi.com;transport=tcp", spec.uri().to_string());
+    }
 }
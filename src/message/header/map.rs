@@ -1,42 +1,211 @@
-use nom::IResult;
 use std::collections::HashMap;
 
-use super::{Header, Value};
+use super::{AcceptRange, ContentDisposition, Header, Key, MediaType, Route, Value, WellKnown};
+use crate::message::Method;
+use crate::parse_utils::{ParseResult, CRLF};
 
 #[derive(Debug)]
 pub struct Map {
-    indice: HashMap<String, Vec<usize>>,
+    // Where the well-known headers are, others are looked for in the entries
+    indice: HashMap<WellKnown, Vec<usize>>,
     entries: Vec<Header>,
 }
 
 impl Map {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
-        let mut parsed_map = Map {
+    pub fn new() -> Self {
+        Self {
             indice: HashMap::new(),
             entries: vec![],
-        };
+        }
+    }
+
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        let mut parsed_map = Map::new();
         let mut rest = src;
         loop {
+            let consumed = src.len() - rest.len();
             let (remainder, header) = Header::parse(rest)?;
             rest = remainder;
-            let Some(header) = header else { break };
-            let name = header.name.to_string().to_lowercase();
-            let index = parsed_map.entries.len();
+            let Some(mut header) = header else { break };
+            header.offset = header.offset.map(|offset| offset + consumed);
             parsed_map.entries.push(header);
-            parsed_map
-                .indice
-                .entry(name)
-                .and_modify(|i| i.push(index))
-                .or_insert(vec![index]);
         }
+        parsed_map.reindex();
         Ok((rest, parsed_map))
     }
+
+    // For headers parsed after `by` bytes of the message, e.g. a start line
+    pub(crate) fn shift(&mut self, by: usize) {
+        for header in self.entries.iter_mut() {
+            header.offset = header.offset.map(|offset| offset + by);
+        }
+    }
+}
+
+impl Default for Map {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Lookups take any spelling of a header name: "Call-ID", "call-id" and "i"
+// all find the same headers
+impl Map {
+    pub fn get(&self, name: &str) -> Option<&Header> {
+        self.first(Key::of(name)).map(|i| &self.entries[i])
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Header> {
+        let i = self.first(Key::of(name))?;
+        self.entries.get_mut(i)
+    }
+
+    // Every header with the name, in wire order
+    pub fn get_all(&self, name: &str) -> Vec<&Header> {
+        let key = Key::of(name);
+        match key {
+            Key::Known(known) => self
+                .indice
+                .get(&known)
+                .map(|i| i.iter().map(|i| &self.entries[*i]).collect())
+                .unwrap_or_default(),
+            Key::Other(_) => self
+                .entries
+                .iter()
+                .filter(|entry| entry.name.key() == key)
+                .collect(),
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.first(Key::of(name)).is_some()
+    }
+
+    // All headers in wire order
+    pub fn iter(&self) -> std::slice::Iter<'_, Header> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Adds the header above the topmost one with the same name, or at the
+    // very top when there is none, e.g. a proxy adding its Via
+    pub fn insert_top(&mut self, header: Header) {
+        let at = self.first(header.name.key()).unwrap_or(0);
+        self.entries.insert(at, header);
+        self.reindex();
+    }
+
+    // Adds the header below the last one with the same name, or at the very
+    // bottom when there is none
+    pub fn insert_bottom(&mut self, header: Header) {
+        let at = self
+            .last(header.name.key())
+            .map_or(self.entries.len(), |i| i + 1);
+        self.entries.insert(at, header);
+        self.reindex();
+    }
+
+    // Puts the header in place of the first one with the same name and drops
+    // the others, returning everything that was replaced
+    pub fn replace(&mut self, header: Header) -> Vec<Header> {
+        let name = header.name.clone();
+        let mut header = Some(header);
+        let mut replaced = vec![];
+        let mut kept = Vec::with_capacity(self.entries.len());
+        for entry in std::mem::take(&mut self.entries) {
+            if entry.name.key() == name.key() {
+                replaced.push(entry);
+                kept.extend(header.take());
+            } else {
+                kept.push(entry);
+            }
+        }
+        // Nothing to replace: the header goes to the bottom
+        kept.extend(header);
+        self.entries = kept;
+        self.reindex();
+        replaced
+    }
+
+    pub fn remove(&mut self, name: &str) -> Vec<Header> {
+        let key = Key::of(name);
+        let (removed, kept) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition(|entry| entry.name.key() == key);
+        self.entries = kept;
+        self.reindex();
+        removed
+    }
+
+    // Removes the topmost header with the name, e.g. the Route a proxy
+    // consumes or the Via a response pops
+    pub fn remove_first(&mut self, name: &str) -> Option<Header> {
+        let i = self.first(Key::of(name))?;
+        let removed = self.entries.remove(i);
+        self.reindex();
+        Some(removed)
+    }
+
+    fn first(&self, key: Key) -> Option<usize> {
+        match key {
+            Key::Known(known) => self.indice.get(&known)?.first().copied(),
+            Key::Other(_) => self
+                .entries
+                .iter()
+                .position(|entry| entry.name.key() == key),
+        }
+    }
+
+    fn last(&self, key: Key) -> Option<usize> {
+        match key {
+            Key::Known(known) => self.indice.get(&known)?.last().copied(),
+            Key::Other(_) => self
+                .entries
+                .iter()
+                .rposition(|entry| entry.name.key() == key),
+        }
+    }
+
+    fn reindex(&mut self) {
+        self.indice.clear();
+        for (index, header) in self.entries.iter().enumerate() {
+            if let Some(known) = header.name.well_known() {
+                self.indice.entry(known).or_default().push(index);
+            }
+        }
+    }
+}
+
+// In the given order, as received
+impl FromIterator<Header> for Map {
+    fn from_iter<I: IntoIterator<Item = Header>>(iter: I) -> Self {
+        let mut map = Map::new();
+        map.entries.extend(iter);
+        map.reindex();
+        map
+    }
+}
+
+impl<'a> IntoIterator for &'a Map {
+    type Item = &'a Header;
+    type IntoIter = std::slice::Iter<'a, Header>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
 impl Map {
     pub fn content_length(&self) -> Option<usize> {
         self.get("content-length").and_then(|header| {
-            if let Value::ContentLength(n) = &header.value {
+            if let Ok(Value::ContentLength(n)) = header.value() {
                 Some(*n)
             } else {
                 None
@@ -49,11 +218,11 @@ impl Map {
     }
 
     pub fn to(&self) -> Option<&Header> {
-        self.get("to").or_else(|| self.get("t"))
+        self.get("to")
     }
 
     pub fn from(&self) -> Option<&Header> {
-        self.get("from").or_else(|| self.get("f"))
+        self.get("from")
     }
 
     pub fn cseq(&self) -> Option<&Header> {
@@ -64,10 +233,93 @@ impl Map {
         self.get("call-id")
     }
 
+    pub fn contact(&self) -> Option<&Header> {
+        self.get("contact")
+    }
+
     pub fn max_forwards(&self) -> Option<&Header> {
         self.get("max-forwards")
     }
 
+    pub fn content_type(&self) -> Option<&MediaType> {
+        match self.get("content-type").map(Header::value) {
+            Some(Ok(Value::ContentType(media_type))) => Some(media_type),
+            _ => None,
+        }
+    }
+
+    pub fn accept(&self) -> Vec<&AcceptRange> {
+        self.get_all("accept")
+            .into_iter()
+            .flat_map(|header| match header.value() {
+                Ok(Value::Accept(ranges)) => ranges.iter().collect(),
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    pub fn content_disposition(&self) -> Option<&ContentDisposition> {
+        match self.get("content-disposition").map(Header::value) {
+            Some(Ok(Value::ContentDisposition(disposition))) => Some(disposition),
+            _ => None,
+        }
+    }
+
+    pub fn allow(&self) -> Vec<&Method> {
+        self.get_all("allow")
+            .into_iter()
+            .flat_map(|header| match header.value() {
+                Ok(Value::Allow(methods)) => methods.iter().collect(),
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    pub fn supported(&self) -> Vec<&str> {
+        self.option_tags("supported")
+    }
+
+    pub fn supports(&self, option_tag: &str) -> bool {
+        self.supported().contains(&option_tag)
+    }
+
+    pub fn requires(&self) -> Vec<&str> {
+        self.option_tags("require")
+    }
+
+    pub fn proxy_requires(&self) -> Vec<&str> {
+        self.option_tags("proxy-require")
+    }
+
+    pub fn unsupported(&self) -> Vec<&str> {
+        self.option_tags("unsupported")
+    }
+
+    // Required extensions missing from `supported`: a UAS rejects the request
+    // with 420 (Bad Extension) listing these in an Unsupported header
+    pub fn unsupported_by(&self, supported: &[&str]) -> Vec<&str> {
+        self.requires()
+            .into_iter()
+            .filter(|tag| !supported.contains(tag))
+            .collect()
+    }
+
+    pub fn route(&self) -> Vec<&Route> {
+        self.route_list("route")
+    }
+
+    pub fn record_route(&self) -> Vec<&Route> {
+        self.route_list("record-route")
+    }
+
+    pub fn path(&self) -> Vec<&Route> {
+        self.route_list("path")
+    }
+
+    pub fn service_route(&self) -> Vec<&Route> {
+        self.route_list("service-route")
+    }
+
     // header fields: To, From, CSeq, Call-ID, Max-Forwards, and Via;
     // all of these are mandatory in all SIP requests
     pub fn sip_sweet_six(&self) -> Option<(&Header, &Header, &Header, &Header, &Header, &Header)> {
@@ -82,26 +334,87 @@ impl Map {
 }
 
 impl Map {
-    fn get(&self, name: &str) -> Option<&Header> {
-        self.indice
-            .get(name)
-            .and_then(|i| i.first())
-            .and_then(|i| self.entries.get(*i))
+    pub fn write_to(&self, w: &mut impl std::io::Write) -> std::io::Result<()> {
+        for header in self.entries.iter() {
+            header.write_to(w)?;
+            w.write_all(CRLF)?;
+        }
+        Ok(())
+    }
+
+    // Writes every header in order, but with a single Content-Length carrying
+    // the actual body length: the first one is rewritten, the rest are dropped
+    // and one is appended if the map has none.
+    pub(crate) fn write_with_content_length(
+        &self,
+        w: &mut impl std::io::Write,
+        content_length: usize,
+    ) -> std::io::Result<()> {
+        let mut length_written = false;
+        for header in self.entries.iter() {
+            if is_content_length(header) {
+                if !length_written {
+                    write!(w, "{}: {}", header.name, content_length)?;
+                    w.write_all(CRLF)?;
+                    length_written = true;
+                }
+                continue;
+            }
+            header.write_to(w)?;
+            w.write_all(CRLF)?;
+        }
+        if !length_written {
+            write!(w, "Content-Length: {}", content_length)?;
+            w.write_all(CRLF)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for header in self.entries.iter() {
+            write!(f, "{}\r\n", header)?;
+        }
+        Ok(())
     }
+}
 
-    fn get_many(&self, name: &str) -> Vec<&Header> {
-        self.indice
-            .get(name)
-            .map(|i| i.iter().filter_map(|i| self.entries.get(*i)).collect())
-            .unwrap_or_default()
+fn is_content_length(header: &Header) -> bool {
+    header.name.is(WellKnown::ContentLength)
+}
+
+impl Map {
+    fn option_tags(&self, name: &str) -> Vec<&str> {
+        self.get_all(name)
+            .into_iter()
+            .flat_map(|header| match header.value() {
+                Ok(
+                    Value::Supported(tags)
+                    | Value::Require(tags)
+                    | Value::ProxyRequire(tags)
+                    | Value::Unsupported(tags),
+                ) => tags.iter().map(String::as_str).collect(),
+                _ => vec![],
+            })
+            .collect()
     }
 
-    fn raw_header_value(&self, header: &str) -> Option<&Value> {
-        self.indice
-            .get(&header.to_lowercase())
-            .and_then(|i| i.first())
-            .and_then(|i| self.entries.get(*i))
-            .map(|e| &e.value)
+    // All entries of a route header in order, across repeated header lines
+    fn route_list(&self, name: &str) -> Vec<&Route> {
+        self.get_all(name)
+            .into_iter()
+            .flat_map(|header| match header.value() {
+                Ok(
+                    Value::Route(routes)
+                    | Value::RecordRoute(routes)
+                    | Value::Path(routes)
+                    | Value::ServiceRoute(routes),
+                ) => Some(routes.iter()),
+                _ => None,
+            })
+            .flatten()
+            .collect()
     }
 }
 
@@ -116,4 +429,139 @@ mod tests {
         assert_eq!(rest.len(), headers.content_length().unwrap());
         println!("{:#?}", headers);
     }
+
+    #[test]
+    fn write_with_content_length_works() {
+        let msg = b"Call-ID: abc\r\nContent-Length: 10\r\nl: 12\r\nSubject: lunch\r\n\r\n";
+        let (_, headers) = Map::parse(msg).unwrap();
+        let mut buf = vec![];
+        headers.write_with_content_length(&mut buf, 3).unwrap();
+        assert_eq!(
+            b"Call-ID: abc\r\nContent-Length: 3\r\nSubject: lunch\r\n".as_slice(),
+            buf
+        );
+
+        let (_, headers) = Map::parse(b"Call-ID: abc\r\n\r\n").unwrap();
+        let mut buf = vec![];
+        headers.write_with_content_length(&mut buf, 0).unwrap();
+        assert_eq!(b"Call-ID: abc\r\nContent-Length: 0\r\n".as_slice(), buf);
+    }
+
+    #[test]
+    fn route_set_spans_header_lines() {
+        let msg = b"Record-Route: <sip:p3.example.com;lr>\r\nRoute: <sip:p1.example.com;lr>, <sip:p2.example.com;lr>\r\nRoute: <sip:uas.example.com>\r\n\r\n";
+        let (_, headers) = Map::parse(msg).unwrap();
+        let route = headers.route();
+        assert_eq!(
+            vec![
+                "sip:p1.example.com;lr",
+                "sip:p2.example.com;lr",
+                "sip:uas.example.com"
+            ],
+            route
+                .iter()
+                .map(|r| r.uri().to_string())
+                .collect::<Vec<_>>()
+        );
+        assert!(!route[2].is_loose());
+        assert_eq!(1, headers.record_route().len());
+        assert!(headers.path().is_empty());
+        assert!(headers.service_route().is_empty());
+    }
+
+    #[test]
+    fn extensions_work() {
+        let msg = b"Supported: timer, 100rel\r\nAllow: INVITE, ACK\r\nk: path\r\nRequire: 100rel, foo\r\nAllow: BYE\r\n\r\n";
+        let (_, headers) = Map::parse(msg).unwrap();
+        assert_eq!(vec!["timer", "100rel", "path"], headers.supported());
+        assert!(headers.supports("100rel"));
+        assert!(!headers.supports("gruu"));
+        assert_eq!(vec!["100rel", "foo"], headers.requires());
+        assert_eq!(vec!["foo"], headers.unsupported_by(&["100rel", "timer"]));
+        assert_eq!(
+            vec![&Method::Invite, &Method::Ack, &Method::Bye],
+            headers.allow()
+        );
+        assert!(headers.proxy_requires().is_empty());
+    }
+
+    fn header(line: &str) -> Header {
+        let line = format!("{}\r\n", line);
+        Header::parse(line.as_bytes()).unwrap().1.unwrap()
+    }
+
+    #[test]
+    fn proxy_edits_work() {
+        let msg = b"Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nRoute: <sip:p1.example.com;lr>\r\nMax-Forwards: 70\r\ni: a84b4c76e66710\r\nRoute: <sip:p2.example.com;lr>\r\n\r\n";
+        let (_, mut headers) = Map::parse(msg).unwrap();
+
+        headers.insert_top(header(
+            "Via: SIP/2.0/UDP p1.example.com;branch=z9hG4bK2d4790.1",
+        ));
+        let popped = headers.remove_first("ROUTE").unwrap();
+        assert_eq!("Route: <sip:p1.example.com;lr>", popped.to_string());
+        if let Some(Ok(Value::MaxForwards(n))) =
+            headers.get_mut("max-forwards").map(Header::value_mut)
+        {
+            *n -= 1;
+        }
+        headers.insert_bottom(header("Record-Route: <sip:p1.example.com;lr>"));
+        headers.insert_bottom(header("v: SIP/2.0/TCP x.example.com"));
+
+        assert_eq!(
+            "Via: SIP/2.0/UDP p1.example.com;branch=z9hG4bK2d4790.1\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nv: SIP/2.0/TCP x.example.com\r\nMax-Forwards: 69\r\ni: a84b4c76e66710\r\nRoute: <sip:p2.example.com;lr>\r\nRecord-Route: <sip:p1.example.com;lr>\r\n",
+            headers.to_string()
+        );
+        assert_eq!(3, headers.get_all("Via").len());
+        assert!(headers.contains("Call-ID"));
+        assert_eq!(1, headers.route().len());
+        assert_eq!(
+            vec![
+                "Via",
+                "Via",
+                "v",
+                "Max-Forwards",
+                "i",
+                "Route",
+                "Record-Route"
+            ],
+            headers
+                .iter()
+                .map(|h| h.name().to_string())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn replace_and_remove_work() {
+        let msg = b"Subject: a\r\nCall-ID: x\r\ns: b\r\n\r\n";
+        let (_, mut headers) = Map::parse(msg).unwrap();
+        let replaced = headers.replace(header("Subject: c"));
+        assert_eq!(2, replaced.len());
+        assert_eq!("Subject: c\r\nCall-ID: x\r\n", headers.to_string());
+
+        assert!(headers.replace(header("Expires: 60")).is_empty());
+        assert_eq!(1, headers.remove("i").len());
+        assert!(headers.remove("call-id").is_empty());
+        assert_eq!("Subject: c\r\nExpires: 60\r\n", headers.to_string());
+        assert_eq!(2, headers.len());
+        assert!(headers.get("Call-ID").is_none());
+        assert_eq!("Expires: 60", headers.get("expires").unwrap().to_string());
+    }
+
+    #[test]
+    fn extension_headers_work() {
+        let msg = b"X-Trace: 1\r\nCall-ID: x\r\nx-trace: 2\r\n\r\n";
+        let (_, mut headers) = Map::parse(msg).unwrap();
+        assert_eq!(2, headers.get_all("X-TRACE").len());
+        assert_eq!("X-Trace: 1", headers.get("x-trace").unwrap().to_string());
+        headers.insert_bottom(header("X-Trace: 3"));
+        assert_eq!(
+            "X-Trace: 1\r\nCall-ID: x\r\nx-trace: 2\r\nX-Trace: 3\r\n",
+            headers.to_string()
+        );
+        assert_eq!(3, headers.remove("x-Trace").len());
+        assert!(!headers.contains("X-Trace"));
+        assert!(headers.contains("i"));
+    }
 }
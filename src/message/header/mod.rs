@@ -8,27 +8,132 @@ pub use map::Map;
 pub use name::*;
 pub use value::*;
 
-use crate::parse_utils::{hcolon, ParseResult, CRLF};
+use std::sync::OnceLock;
 
+use crate::{
+    error::Error,
+    message::HeaderRef,
+    parse_utils::{ParseResult, CRLF},
+};
+
+// Headers are only split off the message when parsed. The value is parsed on
+// first access and kept, so a malformed header nobody asks for costs nothing.
 #[derive(Debug)]
 pub struct Header {
     name: Name,
-    value: Value,
+    // The value as received, written back verbatim until it is modified
+    raw: Option<Box<[u8]>>,
+    // Where the received value starts in the message, so errors found when
+    // it is parsed point into the message
+    offset: Option<usize>,
+    value: OnceLock<Result<Value, Error>>,
 }
 
 impl Header {
-    pub fn parse(src: &[u8]) -> ParseResult<Option<Self>> {
-        let (remainder, name) =
-            Name::parse(src).inspect_err(|e| println!("Error parsing header name: {:?}", e))?;
-        let (rest, header) = if let Some(name) = name {
-            let (rest, _) = hcolon(remainder)?;
-            let (rest, value) = Value::parse_with_name(&name, rest)?;
-            (rest, Some(Self { name, value }))
-        } else {
-            (remainder, None)
-        };
-        let (rest, _) = nom::bytes::complete::tag(CRLF)(rest)?;
-        Ok((rest, header))
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Option<Self>> {
+        if let Some(rest) = src.strip_prefix(CRLF) {
+            return Ok((rest, None));
+        }
+        let (rest, header) = HeaderRef::parse(src)?;
+        let offset = header.value.as_ptr() as usize - src.as_ptr() as usize;
+        Ok((rest, Some(Self::from(&header).located_at(offset))))
+    }
+
+    pub fn write_to(&self, w: &mut impl std::io::Write) -> std::io::Result<()> {
+        write!(w, "{}: ", self.name)?;
+        match (&self.raw, self.value.get()) {
+            (Some(raw), _) => w.write_all(raw),
+            (None, Some(Ok(value))) => value.write_to(w),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Header {
+    pub fn new(name: impl Into<Name>, value: Value) -> Self {
+        Self {
+            name: name.into(),
+            raw: None,
+            offset: None,
+            value: OnceLock::from(Ok(value)),
+        }
+    }
+
+    pub fn name(&self) -> &Name {
+        &self.name
+    }
+
+    pub fn raw(&self) -> Option<&[u8]> {
+        self.raw.as_deref()
+    }
+
+    pub fn value(&self) -> Result<&Value, &Error> {
+        self.value
+            .get_or_init(|| {
+                Value::parse_field(&self.name, self.raw.as_deref().unwrap_or_default())
+                    .map_err(|e| e.shift(self.offset.unwrap_or_default()))
+            })
+            .as_ref()
+    }
+
+    // The received value starts `offset` bytes into the message
+    pub(crate) fn located_at(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    // Once modified, the header is written from the value instead of the
+    // received bytes
+    pub fn value_mut(&mut self) -> Result<&mut Value, Error> {
+        if let Err(e) = self.value() {
+            return Err(e.clone());
+        }
+        self.raw = None;
+        match self.value.get_mut() {
+            Some(Ok(value)) => Ok(value),
+            _ => unreachable!("the value was parsed above"),
+        }
+    }
+}
+
+// The copy carries the value as it would be written, parsed again on access
+impl Clone for Header {
+    fn clone(&self) -> Self {
+        let mut raw = vec![];
+        match (&self.raw, self.value.get()) {
+            (Some(bytes), _) => raw.extend_from_slice(bytes),
+            (None, Some(Ok(value))) => value
+                .write_to(&mut raw)
+                .expect("writing to a Vec never fails"),
+            _ => {}
+        }
+        Self {
+            name: self.name.clone(),
+            offset: self.raw.as_ref().and(self.offset),
+            raw: Some(raw.into()),
+            value: OnceLock::new(),
+        }
+    }
+}
+
+impl From<&HeaderRef<'_>> for Header {
+    fn from(header: &HeaderRef<'_>) -> Self {
+        Self {
+            name: Name::new(header.name),
+            raw: Some(header.value.into()),
+            offset: None,
+            value: OnceLock::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for Header {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.raw, self.value.get()) {
+            (Some(raw), _) => write!(f, "{}: {}", self.name, String::from_utf8_lossy(raw)),
+            (None, Some(Ok(value))) => write!(f, "{}: {}", self.name, value),
+            _ => write!(f, "{}: ", self.name),
+        }
     }
 }
 
@@ -42,7 +147,18 @@ mod tests {
         let (src, header) = Header::parse(line as &[u8]).unwrap();
         assert!(src.is_empty());
         let header = header.unwrap();
-        println!("{:?} -> {:?}", header.name, header.value);
+        assert_eq!(b"lunch".as_slice(), header.raw().unwrap());
+        println!("{:?} -> {:?}", header.name, header.value());
+    }
+
+    #[test]
+    fn to_string_works() {
+        let line = b"Call-ID  :   a84b4c76e66710@pc33.atlanta.com\r\n";
+        let (_, header) = Header::parse(line as &[u8]).unwrap();
+        assert_eq!(
+            "Call-ID: a84b4c76e66710@pc33.atlanta.com",
+            header.unwrap().to_string()
+        );
     }
 
     #[test]
@@ -59,10 +175,31 @@ mod tests {
         let (src, header) = Header::parse(line as &[u8]).unwrap();
         assert!(src.is_empty());
         let header = header.unwrap();
-        if let Value::ContentLength(length) = header.value {
+        if let Ok(Value::ContentLength(length)) = header.value() {
             println!("{:?} -> {}", header.name, length);
         } else {
             unreachable!()
         }
     }
+
+    #[test]
+    fn value_is_parsed_lazily() {
+        let line = b"Max-Forwards: lots\r\n";
+        let (_, header) = Header::parse(line).unwrap();
+        let mut header = header.unwrap();
+        assert_eq!("Max-Forwards: lots", header.to_string());
+        assert!(header.value().is_err());
+        assert!(header.value_mut().is_err());
+
+        let line = b"Allow: INVITE,ACK\r\n";
+        let (_, header) = Header::parse(line).unwrap();
+        let mut header = header.unwrap();
+        assert!(matches!(header.value(), Ok(Value::Allow(methods)) if methods.len() == 2));
+        // Untouched headers keep their spelling
+        assert_eq!("Allow: INVITE,ACK", header.to_string());
+        if let Ok(Value::Allow(methods)) = header.value_mut() {
+            methods.push(crate::message::Method::Bye);
+        }
+        assert_eq!("Allow: INVITE, ACK, BYE", header.to_string());
+    }
 }
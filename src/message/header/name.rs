@@ -1,23 +1,98 @@
-use nom::IResult;
-
-use crate::parse_utils::{token, CRLF};
+use crate::parse_utils::{token, ParseResult, CRLF};
 
+#[derive(Clone)]
 pub struct Name {
+    // Spelling as received, kept for re-emission
     inner: String,
+    known: Option<WellKnown>,
 }
 
 impl Name {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Option<Self>> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Option<Self>> {
         if let Ok((_, _)) = nom::bytes::complete::tag::<_, _, ()>(CRLF)(src) {
             Ok((src, None))
         } else {
-            nom::combinator::map(token, |x: &[u8]| {
-                std::str::from_utf8(x)
-                    .map(|s| Name {
-                        inner: s.to_owned(),
-                    })
-                    .ok()
-            })(src)
+            nom::combinator::map(token, |x: &[u8]| std::str::from_utf8(x).map(Name::new).ok())(src)
+        }
+    }
+}
+
+impl Name {
+    pub fn new(name: &str) -> Self {
+        Self {
+            inner: name.to_owned(),
+            known: WellKnown::from_name(name),
+        }
+    }
+
+    pub fn well_known(&self) -> Option<WellKnown> {
+        self.known
+    }
+
+    pub fn is(&self, known: WellKnown) -> bool {
+        self.known == Some(known)
+    }
+
+    pub fn is_compact(&self) -> bool {
+        self.known
+            .and_then(|known| known.compact())
+            .is_some_and(|compact| compact.eq_ignore_ascii_case(&self.inner))
+    }
+
+    // The full, conventionally capitalised name, e.g. "Call-ID" for "i"
+    pub fn canonical(&self) -> &str {
+        match self.known {
+            Some(known) => known.as_str(),
+            None => &self.inner,
+        }
+    }
+
+    pub(crate) fn key(&self) -> Key<'_> {
+        match self.known {
+            Some(known) => Key::Known(known),
+            None => Key::Other(&self.inner),
+        }
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<WellKnown> for Name {
+    fn from(known: WellKnown) -> Self {
+        Self {
+            inner: known.as_str().to_owned(),
+            known: Some(known),
+        }
+    }
+}
+
+// What headers are looked up by: compact forms are their full name, and
+// other names compare case-insensitively
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Key<'a> {
+    Known(WellKnown),
+    Other(&'a str),
+}
+
+impl<'a> Key<'a> {
+    pub fn of(name: &'a str) -> Self {
+        match WellKnown::from_name(name) {
+            Some(known) => Key::Known(known),
+            None => Key::Other(name),
+        }
+    }
+}
+
+impl PartialEq for Key<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Key::Known(a), Key::Known(b)) => a == b,
+            (Key::Other(a), Key::Other(b)) => a.eq_ignore_ascii_case(b),
+            _ => false,
         }
     }
 }
@@ -28,15 +103,308 @@ impl AsRef<str> for Name {
     }
 }
 
-impl ToString for Name {
-    fn to_string(&self) -> String {
-        self.inner.to_owned()
+impl std::fmt::Display for Name {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.inner)
     }
 }
 
 impl std::fmt::Debug for Name {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", std::str::from_utf8(self.inner.as_ref()).unwrap())
+        write!(f, "{}", self.inner)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WellKnown {
+    Accept,
+    AcceptContact,
+    AcceptEncoding,
+    AcceptLanguage,
+    AlertInfo,
+    Allow,
+    AllowEvents,
+    AuthenticationInfo,
+    Authorization,
+    CallId,
+    CallInfo,
+    Contact,
+    ContentDisposition,
+    ContentEncoding,
+    ContentLanguage,
+    ContentLength,
+    ContentType,
+    CSeq,
+    Date,
+    ErrorInfo,
+    Event,
+    Expires,
+    From,
+    Identity,
+    IdentityInfo,
+    InReplyTo,
+    MaxForwards,
+    MimeVersion,
+    MinExpires,
+    MinSe,
+    Organization,
+    Path,
+    Priority,
+    ProxyAuthenticate,
+    ProxyAuthorization,
+    ProxyRequire,
+    RAck,
+    RecordRoute,
+    ReferTo,
+    ReferredBy,
+    RejectContact,
+    ReplyTo,
+    RequestDisposition,
+    Require,
+    RetryAfter,
+    Route,
+    RSeq,
+    Server,
+    ServiceRoute,
+    SessionExpires,
+    Subject,
+    SubscriptionState,
+    Supported,
+    Timestamp,
+    To,
+    Unsupported,
+    UserAgent,
+    Via,
+    Warning,
+    WwwAuthenticate,
+}
+
+impl WellKnown {
+    pub const ALL: [WellKnown; 60] = [
+        WellKnown::Accept,
+        WellKnown::AcceptContact,
+        WellKnown::AcceptEncoding,
+        WellKnown::AcceptLanguage,
+        WellKnown::AlertInfo,
+        WellKnown::Allow,
+        WellKnown::AllowEvents,
+        WellKnown::AuthenticationInfo,
+        WellKnown::Authorization,
+        WellKnown::CallId,
+        WellKnown::CallInfo,
+        WellKnown::Contact,
+        WellKnown::ContentDisposition,
+        WellKnown::ContentEncoding,
+        WellKnown::ContentLanguage,
+        WellKnown::ContentLength,
+        WellKnown::ContentType,
+        WellKnown::CSeq,
+        WellKnown::Date,
+        WellKnown::ErrorInfo,
+        WellKnown::Event,
+        WellKnown::Expires,
+        WellKnown::From,
+        WellKnown::Identity,
+        WellKnown::IdentityInfo,
+        WellKnown::InReplyTo,
+        WellKnown::MaxForwards,
+        WellKnown::MimeVersion,
+        WellKnown::MinExpires,
+        WellKnown::MinSe,
+        WellKnown::Organization,
+        WellKnown::Path,
+        WellKnown::Priority,
+        WellKnown::ProxyAuthenticate,
+        WellKnown::ProxyAuthorization,
+        WellKnown::ProxyRequire,
+        WellKnown::RAck,
+        WellKnown::RecordRoute,
+        WellKnown::ReferTo,
+        WellKnown::ReferredBy,
+        WellKnown::RejectContact,
+        WellKnown::ReplyTo,
+        WellKnown::RequestDisposition,
+        WellKnown::Require,
+        WellKnown::RetryAfter,
+        WellKnown::Route,
+        WellKnown::RSeq,
+        WellKnown::Server,
+        WellKnown::ServiceRoute,
+        WellKnown::SessionExpires,
+        WellKnown::Subject,
+        WellKnown::SubscriptionState,
+        WellKnown::Supported,
+        WellKnown::Timestamp,
+        WellKnown::To,
+        WellKnown::Unsupported,
+        WellKnown::UserAgent,
+        WellKnown::Via,
+        WellKnown::Warning,
+        WellKnown::WwwAuthenticate,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        // Header names are case-insensitive and may come in compact form. No
+        // well-known name is longer than the buffer
+        let mut buffer = [0; 24];
+        let lower = buffer.get_mut(..name.len())?;
+        lower.copy_from_slice(name.as_bytes());
+        lower.make_ascii_lowercase();
+        Some(match &*lower {
+            b"accept" => WellKnown::Accept,
+            b"a" | b"accept-contact" => WellKnown::AcceptContact,
+            b"accept-encoding" => WellKnown::AcceptEncoding,
+            b"accept-language" => WellKnown::AcceptLanguage,
+            b"alert-info" => WellKnown::AlertInfo,
+            b"allow" => WellKnown::Allow,
+            b"u" | b"allow-events" => WellKnown::AllowEvents,
+            b"authentication-info" => WellKnown::AuthenticationInfo,
+            b"authorization" => WellKnown::Authorization,
+            b"i" | b"call-id" => WellKnown::CallId,
+            b"call-info" => WellKnown::CallInfo,
+            b"m" | b"contact" => WellKnown::Contact,
+            b"content-disposition" => WellKnown::ContentDisposition,
+            b"e" | b"content-encoding" => WellKnown::ContentEncoding,
+            b"content-language" => WellKnown::ContentLanguage,
+            b"l" | b"content-length" => WellKnown::ContentLength,
+            b"c" | b"content-type" => WellKnown::ContentType,
+            b"cseq" => WellKnown::CSeq,
+            b"date" => WellKnown::Date,
+            b"error-info" => WellKnown::ErrorInfo,
+            b"o" | b"event" => WellKnown::Event,
+            b"expires" => WellKnown::Expires,
+            b"f" | b"from" => WellKnown::From,
+            b"y" | b"identity" => WellKnown::Identity,
+            b"n" | b"identity-info" => WellKnown::IdentityInfo,
+            b"in-reply-to" => WellKnown::InReplyTo,
+            b"max-forwards" => WellKnown::MaxForwards,
+            b"mime-version" => WellKnown::MimeVersion,
+            b"min-expires" => WellKnown::MinExpires,
+            b"min-se" => WellKnown::MinSe,
+            b"organization" => WellKnown::Organization,
+            b"path" => WellKnown::Path,
+            b"priority" => WellKnown::Priority,
+            b"proxy-authenticate" => WellKnown::ProxyAuthenticate,
+            b"proxy-authorization" => WellKnown::ProxyAuthorization,
+            b"proxy-require" => WellKnown::ProxyRequire,
+            b"rack" => WellKnown::RAck,
+            b"record-route" => WellKnown::RecordRoute,
+            b"r" | b"refer-to" => WellKnown::ReferTo,
+            b"b" | b"referred-by" => WellKnown::ReferredBy,
+            b"j" | b"reject-contact" => WellKnown::RejectContact,
+            b"reply-to" => WellKnown::ReplyTo,
+            b"d" | b"request-disposition" => WellKnown::RequestDisposition,
+            b"require" => WellKnown::Require,
+            b"retry-after" => WellKnown::RetryAfter,
+            b"route" => WellKnown::Route,
+            b"rseq" => WellKnown::RSeq,
+            b"server" => WellKnown::Server,
+            b"service-route" => WellKnown::ServiceRoute,
+            b"x" | b"session-expires" => WellKnown::SessionExpires,
+            b"s" | b"subject" => WellKnown::Subject,
+            b"subscription-state" => WellKnown::SubscriptionState,
+            b"k" | b"supported" => WellKnown::Supported,
+            b"timestamp" => WellKnown::Timestamp,
+            b"t" | b"to" => WellKnown::To,
+            b"unsupported" => WellKnown::Unsupported,
+            b"user-agent" => WellKnown::UserAgent,
+            b"v" | b"via" => WellKnown::Via,
+            b"warning" => WellKnown::Warning,
+            b"www-authenticate" => WellKnown::WwwAuthenticate,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WellKnown::Accept => "Accept",
+            WellKnown::AcceptContact => "Accept-Contact",
+            WellKnown::AcceptEncoding => "Accept-Encoding",
+            WellKnown::AcceptLanguage => "Accept-Language",
+            WellKnown::AlertInfo => "Alert-Info",
+            WellKnown::Allow => "Allow",
+            WellKnown::AllowEvents => "Allow-Events",
+            WellKnown::AuthenticationInfo => "Authentication-Info",
+            WellKnown::Authorization => "Authorization",
+            WellKnown::CallId => "Call-ID",
+            WellKnown::CallInfo => "Call-Info",
+            WellKnown::Contact => "Contact",
+            WellKnown::ContentDisposition => "Content-Disposition",
+            WellKnown::ContentEncoding => "Content-Encoding",
+            WellKnown::ContentLanguage => "Content-Language",
+            WellKnown::ContentLength => "Content-Length",
+            WellKnown::ContentType => "Content-Type",
+            WellKnown::CSeq => "CSeq",
+            WellKnown::Date => "Date",
+            WellKnown::ErrorInfo => "Error-Info",
+            WellKnown::Event => "Event",
+            WellKnown::Expires => "Expires",
+            WellKnown::From => "From",
+            WellKnown::Identity => "Identity",
+            WellKnown::IdentityInfo => "Identity-Info",
+            WellKnown::InReplyTo => "In-Reply-To",
+            WellKnown::MaxForwards => "Max-Forwards",
+            WellKnown::MimeVersion => "MIME-Version",
+            WellKnown::MinExpires => "Min-Expires",
+            WellKnown::MinSe => "Min-SE",
+            WellKnown::Organization => "Organization",
+            WellKnown::Path => "Path",
+            WellKnown::Priority => "Priority",
+            WellKnown::ProxyAuthenticate => "Proxy-Authenticate",
+            WellKnown::ProxyAuthorization => "Proxy-Authorization",
+            WellKnown::ProxyRequire => "Proxy-Require",
+            WellKnown::RAck => "RAck",
+            WellKnown::RecordRoute => "Record-Route",
+            WellKnown::ReferTo => "Refer-To",
+            WellKnown::ReferredBy => "Referred-By",
+            WellKnown::RejectContact => "Reject-Contact",
+            WellKnown::ReplyTo => "Reply-To",
+            WellKnown::RequestDisposition => "Request-Disposition",
+            WellKnown::Require => "Require",
+            WellKnown::RetryAfter => "Retry-After",
+            WellKnown::Route => "Route",
+            WellKnown::RSeq => "RSeq",
+            WellKnown::Server => "Server",
+            WellKnown::ServiceRoute => "Service-Route",
+            WellKnown::SessionExpires => "Session-Expires",
+            WellKnown::Subject => "Subject",
+            WellKnown::SubscriptionState => "Subscription-State",
+            WellKnown::Supported => "Supported",
+            WellKnown::Timestamp => "Timestamp",
+            WellKnown::To => "To",
+            WellKnown::Unsupported => "Unsupported",
+            WellKnown::UserAgent => "User-Agent",
+            WellKnown::Via => "Via",
+            WellKnown::Warning => "Warning",
+            WellKnown::WwwAuthenticate => "WWW-Authenticate",
+        }
+    }
+
+    pub fn compact(&self) -> Option<&'static str> {
+        // RFC 3261 7.3.3, RFC 3265, RFC 3515, RFC 3841, RFC 3892, RFC 4028, RFC 4474
+        match self {
+            WellKnown::AcceptContact => Some("a"),
+            WellKnown::AllowEvents => Some("u"),
+            WellKnown::CallId => Some("i"),
+            WellKnown::Contact => Some("m"),
+            WellKnown::ContentEncoding => Some("e"),
+            WellKnown::ContentLength => Some("l"),
+            WellKnown::ContentType => Some("c"),
+            WellKnown::Event => Some("o"),
+            WellKnown::From => Some("f"),
+            WellKnown::Identity => Some("y"),
+            WellKnown::IdentityInfo => Some("n"),
+            WellKnown::ReferTo => Some("r"),
+            WellKnown::ReferredBy => Some("b"),
+            WellKnown::RejectContact => Some("j"),
+            WellKnown::RequestDisposition => Some("d"),
+            WellKnown::SessionExpires => Some("x"),
+            WellKnown::Subject => Some("s"),
+            WellKnown::Supported => Some("k"),
+            WellKnown::To => Some("t"),
+            WellKnown::Via => Some("v"),
+            _ => None,
+        }
     }
 }
 
@@ -51,4 +419,73 @@ mod tests {
         assert_eq!(CRLF, rest);
         assert!(none.is_none());
     }
+
+    #[test]
+    fn compact_forms_work() {
+        for (compact, full) in [
+            ("m", "Contact"),
+            ("l", "Content-Length"),
+            ("c", "Content-Type"),
+            ("e", "Content-Encoding"),
+            ("k", "Supported"),
+            ("s", "Subject"),
+            ("o", "Event"),
+            ("u", "Allow-Events"),
+            ("r", "Refer-To"),
+            ("b", "Referred-By"),
+            ("x", "Session-Expires"),
+            ("y", "Identity"),
+            ("j", "Reject-Contact"),
+            ("a", "Accept-Contact"),
+            ("d", "Request-Disposition"),
+            ("I", "Call-ID"),
+            ("f", "From"),
+            ("t", "To"),
+            ("v", "Via"),
+        ] {
+            let name = Name::new(compact);
+            assert!(name.is_compact(), "{}", compact);
+            assert_eq!(full, name.canonical());
+            assert_eq!(compact, name.to_string());
+            assert_eq!(name.well_known(), Name::new(full).well_known());
+        }
+    }
+
+    #[test]
+    fn every_name_is_known() {
+        for known in WellKnown::ALL {
+            assert_eq!(Some(known), WellKnown::from_name(known.as_str()));
+            let upper = known.as_str().to_ascii_uppercase();
+            assert_eq!(Some(known), WellKnown::from_name(&upper));
+            if let Some(compact) = known.compact() {
+                assert_eq!(Some(known), WellKnown::from_name(compact));
+            }
+        }
+        assert_eq!(
+            None,
+            WellKnown::from_name("X-Call-ID-Of-Some-Length-Beyond")
+        );
+        assert_eq!(None, WellKnown::from_name("q"));
+        assert_eq!(None, WellKnown::from_name(""));
+    }
+
+    #[test]
+    fn spelling_is_kept() {
+        let (_, name) = Name::parse(b"call-id: x").unwrap();
+        let name = name.unwrap();
+        assert!(name.is(WellKnown::CallId));
+        assert!(!name.is_compact());
+        assert_eq!("call-id", name.to_string());
+        assert_eq!("Call-ID", name.canonical());
+
+        let name = Name::new("X-Custom");
+        assert_eq!(None, name.well_known());
+        assert_eq!("X-Custom", name.canonical());
+        assert_eq!(Key::Other("x-custom"), name.key());
+        assert_eq!(Key::Known(WellKnown::CallId), Key::of("I"));
+        assert_eq!(
+            "WWW-Authenticate",
+            Name::from(WellKnown::WwwAuthenticate).to_string()
+        );
+    }
 }
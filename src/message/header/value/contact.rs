@@ -0,0 +1,299 @@
+use nom::{
+    bytes::complete::{tag, take_while, take_while1},
+    combinator::opt,
+    multi::{many0, separated_list1},
+    sequence::{preceded, tuple},
+};
+
+use crate::{
+    message::{header::Spec, GenericParam, Uri},
+    parse_utils::{comma, equal, semi, star, token, ParseResult},
+};
+
+#[derive(Debug)]
+pub enum Contact {
+    // Only valid in a REGISTER removing all bindings, with expires=0
+    Wildcard,
+    Addresses(Vec<ContactAddress>),
+}
+
+#[derive(Debug)]
+pub struct ContactAddress {
+    pub spec: Spec,
+    pub params: Vec<ContactParam>,
+}
+
+#[derive(Debug)]
+pub enum ContactParam {
+    Q(QValue),
+    Expires(u32),
+    Generic(GenericParam),
+}
+
+// A qvalue in thousandths, from 0 to 1000
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct QValue(u16);
+
+impl Contact {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // Contact        =  ("Contact" / "m" ) HCOLON
+        //                   ( STAR / (contact-param *(COMMA contact-param)))
+        if let Ok((rest, _)) = star(src) {
+            return Ok((rest, Self::Wildcard));
+        }
+        nom::combinator::map(
+            separated_list1(comma, ContactAddress::parse),
+            Self::Addresses,
+        )(src)
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        matches!(self, Self::Wildcard)
+    }
+
+    pub fn addresses(&self) -> &[ContactAddress] {
+        match self {
+            Contact::Wildcard => &[],
+            Contact::Addresses(addresses) => addresses,
+        }
+    }
+}
+
+impl std::fmt::Display for Contact {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Contact::Wildcard => write!(f, "*"),
+            Contact::Addresses(addresses) => {
+                for (i, address) in addresses.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", address)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl ContactAddress {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // contact-param  =  (name-addr / addr-spec) *(SEMI contact-params)
+        nom::combinator::map(
+            tuple((
+                Spec::parse_before_params,
+                many0(preceded(semi, ContactParam::parse)),
+            )),
+            |(spec, params)| Self { spec, params },
+        )(src)
+    }
+
+    pub fn uri(&self) -> &Uri {
+        self.spec.uri()
+    }
+
+    pub fn expires(&self) -> Option<u32> {
+        self.params.iter().find_map(|p| match p {
+            ContactParam::Expires(expires) => Some(*expires),
+            _ => None,
+        })
+    }
+
+    pub fn q(&self) -> Option<QValue> {
+        self.params.iter().find_map(|p| match p {
+            ContactParam::Q(q) => Some(*q),
+            _ => None,
+        })
+    }
+
+    pub fn generic_params(&self) -> impl Iterator<Item = &GenericParam> {
+        self.params.iter().filter_map(|p| match p {
+            ContactParam::Generic(param) => Some(param),
+            _ => None,
+        })
+    }
+}
+
+impl std::fmt::Display for ContactAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.spec)?;
+        for param in self.params.iter() {
+            write!(f, ";{}", param)?;
+        }
+        Ok(())
+    }
+}
+
+impl ContactParam {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // contact-params     =  c-p-q / c-p-expires / contact-extension
+        // c-p-q              =  "q" EQUAL qvalue
+        // c-p-expires        =  "expires" EQUAL delta-seconds
+        // contact-extension  =  generic-param
+        let (_, name) = token(src)?;
+        if name.eq_ignore_ascii_case(b"q") {
+            nom::combinator::map(preceded(tuple((token, equal)), QValue::parse), Self::Q)(src)
+        } else if name.eq_ignore_ascii_case(b"expires") {
+            nom::combinator::map(
+                preceded(tuple((token, equal)), parse_delta_seconds),
+                Self::Expires,
+            )(src)
+        } else {
+            nom::combinator::map(GenericParam::parse, Self::Generic)(src)
+        }
+    }
+}
+
+impl std::fmt::Display for ContactParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContactParam::Q(q) => write!(f, "q={}", q),
+            ContactParam::Expires(expires) => write!(f, "expires={}", expires),
+            ContactParam::Generic(param) => write!(f, "{}", param),
+        }
+    }
+}
+
+impl QValue {
+    pub const MAX: Self = Self(1000);
+
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // qvalue         =  ( "0" [ "." 0*3DIGIT ] )
+        //                   / ( "1" [ "." 0*3("0") ] )
+        let (rest, (integer, fraction)) = tuple((
+            take_while1(|x: u8| x == b'0' || x == b'1'),
+            opt(preceded(tag(b"."), take_while(|x: u8| x.is_ascii_digit()))),
+        ))(src)?;
+        let fraction = fraction.unwrap_or_default();
+        let thousandths = fraction
+            .iter()
+            .chain(b"000")
+            .take(3)
+            .fold(0, |acc, d| acc * 10 + u16::from(d - b'0'));
+        match integer {
+            b"0" if fraction.len() <= 3 => Ok((rest, Self(thousandths))),
+            b"1" if fraction.len() <= 3 && thousandths == 0 => Ok((rest, Self::MAX)),
+            _ => Err(nom::Err::Error(nom::error::make_error(
+                src,
+                nom::error::ErrorKind::Verify,
+            ))),
+        }
+    }
+
+    pub fn new(thousandths: u16) -> Option<Self> {
+        (thousandths <= 1000).then_some(Self(thousandths))
+    }
+
+    pub fn thousandths(&self) -> u16 {
+        self.0
+    }
+}
+
+impl From<QValue> for f32 {
+    fn from(q: QValue) -> Self {
+        f32::from(q.0) / 1000.0
+    }
+}
+
+impl std::fmt::Display for QValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            1000 => write!(f, "1"),
+            0 => write!(f, "0"),
+            n => {
+                let fraction = format!("{:03}", n);
+                write!(f, "0.{}", fraction.trim_end_matches('0'))
+            }
+        }
+    }
+}
+
+fn parse_delta_seconds(src: &[u8]) -> ParseResult<'_, u32> {
+    // delta-seconds  =  1*DIGIT
+    // Values beyond 2**32-1 are treated as 2**32-1 (RFC 3261 8.3)
+    nom::combinator::map(
+        take_while1(|x: u8| x.is_ascii_digit()),
+        |digits: &[u8]| {
+            digits.iter().fold(0u32, |acc, d| {
+                acc.saturating_mul(10).saturating_add(u32::from(d - b'0'))
+            })
+        },
+    )(src)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_works() {
+        let (rest, contact) = Contact::parse(b"*").unwrap();
+        assert!(rest.is_empty());
+        assert!(contact.is_wildcard());
+        assert!(contact.addresses().is_empty());
+        assert_eq!("*", contact.to_string());
+    }
+
+    #[test]
+    fn contact_list_works() {
+        let raw = b"\"Mr. Watson\" <sip:watson@worcester.bell-telephone.com>;q=0.7;expires=3600, \"Mr. Watson\" <mailto:watson@bell-telephone.com>;q=0.1";
+        let (rest, contact) = Contact::parse(raw).unwrap();
+        assert!(rest.is_empty());
+        let addresses = contact.addresses();
+        assert_eq!(2, addresses.len());
+        assert_eq!(Some(3600), addresses[0].expires());
+        assert_eq!(QValue::new(700), addresses[0].q());
+        assert_eq!(
+            "sip:watson@worcester.bell-telephone.com",
+            addresses[0].uri().to_string()
+        );
+        assert_eq!(None, addresses[1].expires());
+        assert_eq!(0.1, f32::from(addresses[1].q().unwrap()));
+        assert_eq!(std::str::from_utf8(raw).unwrap(), contact.to_string());
+    }
+
+    #[test]
+    fn addr_spec_params_belong_to_header() {
+        let (rest, contact) =
+            Contact::parse(b"sip:carol@chicago.com;expires=0;+sip.instance=x").unwrap();
+        assert!(rest.is_empty());
+        let address = &contact.addresses()[0];
+        assert_eq!("sip:carol@chicago.com", address.uri().to_string());
+        assert_eq!(Some(0), address.expires());
+        assert_eq!(
+            vec!["+sip.instance=x"],
+            address
+                .generic_params()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn qvalue_is_validated() {
+        for (raw, thousandths) in [
+            ("0", 0),
+            ("0.", 0),
+            ("0.05", 50),
+            ("1.000", 1000),
+            ("1", 1000),
+        ] {
+            let (rest, q) = QValue::parse(raw.as_bytes()).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(thousandths, q.thousandths());
+        }
+        for raw in ["1.5", "2", "0.1234", "01", ""] {
+            assert!(QValue::parse(raw.as_bytes()).is_err(), "{}", raw);
+        }
+        assert_eq!("0.05", QValue::new(50).unwrap().to_string());
+        assert!(QValue::new(1001).is_none());
+    }
+
+    #[test]
+    fn expires_saturates() {
+        let (_, contact) = Contact::parse(b"<sip:a@b.com>;expires=99999999999").unwrap();
+        assert_eq!(Some(u32::MAX), contact.addresses()[0].expires());
+        let (rest, _) = Contact::parse(b"<sip:a@b.com>;q=5").unwrap();
+        assert_eq!(b";q=5", rest);
+    }
+}
@@ -0,0 +1,160 @@
+use nom::{
+    multi::many0,
+    sequence::{preceded, tuple},
+};
+
+use crate::{
+    message::GenericParam,
+    parse_utils::{equal, semi, token, ParseResult},
+};
+
+#[derive(Debug)]
+pub struct ContentDisposition {
+    pub disp_type: DispositionType,
+    pub params: Vec<DispositionParam>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DispositionType {
+    Render,
+    Session,
+    Icon,
+    Alert,
+    Extension(String),
+}
+
+#[derive(Debug)]
+pub enum DispositionParam {
+    Handling(Handling),
+    Generic(GenericParam),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Handling {
+    Optional,
+    Required,
+    Other(String),
+}
+
+impl ContentDisposition {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // Content-Disposition   =  "Content-Disposition" HCOLON
+        //                          disp-type *( SEMI disp-param )
+        nom::combinator::map(
+            tuple((
+                DispositionType::parse,
+                many0(preceded(semi, DispositionParam::parse)),
+            )),
+            |(disp_type, params)| Self { disp_type, params },
+        )(src)
+    }
+
+    pub fn handling(&self) -> Handling {
+        // The default handling is required (RFC 3261 20.11)
+        self.params
+            .iter()
+            .find_map(|p| match p {
+                DispositionParam::Handling(handling) => Some(handling.clone()),
+                _ => None,
+            })
+            .unwrap_or(Handling::Required)
+    }
+}
+
+impl std::fmt::Display for ContentDisposition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.disp_type)?;
+        for param in self.params.iter() {
+            write!(f, ";{}", param)?;
+        }
+        Ok(())
+    }
+}
+
+impl DispositionType {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // disp-type  =  "render" / "session" / "icon" / "alert"
+        //               / disp-extension-token
+        nom::combinator::map(token, |name: &[u8]| {
+            match name.to_ascii_lowercase().as_slice() {
+                b"render" => Self::Render,
+                b"session" => Self::Session,
+                b"icon" => Self::Icon,
+                b"alert" => Self::Alert,
+                _ => Self::Extension(String::from_utf8_lossy(name).into_owned()),
+            }
+        })(src)
+    }
+}
+
+impl std::fmt::Display for DispositionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DispositionType::Render => write!(f, "render"),
+            DispositionType::Session => write!(f, "session"),
+            DispositionType::Icon => write!(f, "icon"),
+            DispositionType::Alert => write!(f, "alert"),
+            DispositionType::Extension(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl DispositionParam {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // disp-param      =  handling-param / generic-param
+        // handling-param  =  "handling" EQUAL
+        //                    ( "optional" / "required" / other-handling )
+        // other-handling  =  token
+        let (_, name) = token(src)?;
+        if name.eq_ignore_ascii_case(b"handling") {
+            nom::combinator::map(
+                preceded(tuple((token, equal)), token),
+                |handling: &[u8]| {
+                    Self::Handling(match handling.to_ascii_lowercase().as_slice() {
+                        b"optional" => Handling::Optional,
+                        b"required" => Handling::Required,
+                        _ => Handling::Other(String::from_utf8_lossy(handling).into_owned()),
+                    })
+                },
+            )(src)
+        } else {
+            nom::combinator::map(GenericParam::parse, Self::Generic)(src)
+        }
+    }
+}
+
+impl std::fmt::Display for DispositionParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DispositionParam::Handling(Handling::Optional) => write!(f, "handling=optional"),
+            DispositionParam::Handling(Handling::Required) => write!(f, "handling=required"),
+            DispositionParam::Handling(Handling::Other(other)) => write!(f, "handling={}", other),
+            DispositionParam::Generic(param) => write!(f, "{}", param),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let (rest, disposition) =
+            ContentDisposition::parse(b"Session;handling=optional;foo=bar").unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(DispositionType::Session, disposition.disp_type);
+        assert_eq!(Handling::Optional, disposition.handling());
+        assert_eq!("session;handling=optional;foo=bar", disposition.to_string());
+    }
+
+    #[test]
+    fn handling_defaults_to_required() {
+        let (_, disposition) = ContentDisposition::parse(b"x-custom").unwrap();
+        assert_eq!(
+            DispositionType::Extension("x-custom".to_owned()),
+            disposition.disp_type
+        );
+        assert_eq!(Handling::Required, disposition.handling());
+    }
+}
@@ -0,0 +1,262 @@
+use nom::{
+    multi::{many0, separated_list0},
+    sequence::{preceded, tuple},
+};
+
+use super::QValue;
+use crate::{
+    message::GenValue,
+    parse_utils::{comma, equal, parse_quoted_string, semi, slash, token, ParseResult},
+};
+
+#[derive(Debug)]
+pub struct MediaType {
+    pub m_type: String,
+    pub m_subtype: String,
+    pub params: Vec<MediaParam>,
+}
+
+#[derive(Debug)]
+pub struct MediaParam {
+    pub name: String,
+    pub value: GenValue,
+}
+
+#[derive(Debug)]
+pub struct AcceptRange {
+    pub media_range: MediaType,
+    pub q: Option<QValue>,
+    pub params: Vec<MediaParam>,
+}
+
+impl MediaType {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // media-type     =  m-type SLASH m-subtype *(SEMI m-parameter)
+        nom::combinator::map(
+            tuple((
+                token,
+                slash,
+                token,
+                many0(preceded(semi, MediaParam::parse)),
+            )),
+            |(m_type, _, m_subtype, params)| Self {
+                m_type: lossy(m_type),
+                m_subtype: lossy(m_subtype),
+                params,
+            },
+        )(src)
+    }
+
+    pub fn new(m_type: &str, m_subtype: &str) -> Self {
+        Self {
+            m_type: m_type.to_owned(),
+            m_subtype: m_subtype.to_owned(),
+            params: vec![],
+        }
+    }
+
+    // Type and subtype only, ignoring case and parameters
+    pub fn is(&self, m_type: &str, m_subtype: &str) -> bool {
+        self.m_type.eq_ignore_ascii_case(m_type) && self.m_subtype.eq_ignore_ascii_case(m_subtype)
+    }
+
+    // Whether a media range from Accept, possibly with wildcards, covers other
+    pub fn matches(&self, other: &MediaType) -> bool {
+        match (self.m_type.as_str(), self.m_subtype.as_str()) {
+            ("*", "*") => true,
+            (m_type, "*") => m_type.eq_ignore_ascii_case(&other.m_type),
+            (m_type, m_subtype) => other.is(m_type, m_subtype),
+        }
+    }
+
+    pub fn param(&self, name: &str) -> Option<&GenValue> {
+        self.params
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+            .map(|p| &p.value)
+    }
+}
+
+impl PartialEq for MediaType {
+    fn eq(&self, other: &Self) -> bool {
+        // Type, subtype and parameter names are case-insensitive (RFC 3261 7.3.1)
+        self.is(&other.m_type, &other.m_subtype)
+            && self.params.len() == other.params.len()
+            && self
+                .params
+                .iter()
+                .all(|p| other.param(&p.name).is_some_and(|v| p.value_eq(v)))
+    }
+}
+
+impl PartialEq<&str> for MediaType {
+    fn eq(&self, other: &&str) -> bool {
+        match MediaType::parse(other.as_bytes()) {
+            Ok((b"", media_type)) => *self == media_type,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for MediaType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.m_type, self.m_subtype)?;
+        for param in self.params.iter() {
+            write!(f, ";{}", param)?;
+        }
+        Ok(())
+    }
+}
+
+impl MediaParam {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // m-parameter    =  m-attribute EQUAL m-value
+        // m-attribute    =  token
+        // m-value        =  token / quoted-string
+        nom::combinator::map(
+            tuple((
+                token,
+                equal,
+                nom::branch::alt((
+                    nom::combinator::map(parse_quoted_string, GenValue::Quoted),
+                    nom::combinator::map(token, |value| GenValue::Token(lossy(value))),
+                )),
+            )),
+            |(name, _, value)| Self {
+                name: lossy(name),
+                value,
+            },
+        )(src)
+    }
+
+    fn value_eq(&self, other: &GenValue) -> bool {
+        // Quoted values are compared as is, tokens ignoring case
+        match (&self.value, other) {
+            (GenValue::Quoted(a), GenValue::Quoted(b)) => a == b,
+            (a, b) => a.to_string().eq_ignore_ascii_case(&b.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for MediaParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.name, self.value)
+    }
+}
+
+impl AcceptRange {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // accept-range   =  media-range *(SEMI accept-param)
+        // media-range    =  ( "*/*" / ( m-type SLASH "*" ) / ( m-type SLASH m-subtype ) )
+        //                   *( SEMI m-parameter )
+        // accept-param   =  ("q" EQUAL qvalue) / generic-param
+        // Parameters before q belong to the media range, the rest to the range
+        let (rest, mut media_range) = MediaType::parse(src)?;
+        let Some(at) = media_range
+            .params
+            .iter()
+            .position(|p| p.name.eq_ignore_ascii_case("q"))
+        else {
+            return Ok((
+                rest,
+                Self {
+                    media_range,
+                    q: None,
+                    params: vec![],
+                },
+            ));
+        };
+        let mut params = media_range.params.split_off(at);
+        let q = params.remove(0).value.to_string();
+        match QValue::parse(q.as_bytes()) {
+            Ok((b"", q)) => Ok((
+                rest,
+                Self {
+                    media_range,
+                    q: Some(q),
+                    params,
+                },
+            )),
+            _ => Err(nom::Err::Error(nom::error::make_error(
+                src,
+                nom::error::ErrorKind::Verify,
+            ))),
+        }
+    }
+
+    pub fn parse_list(src: &[u8]) -> ParseResult<'_, Vec<Self>> {
+        // Accept         =  "Accept" HCOLON [ accept-range *(COMMA accept-range) ]
+        separated_list0(comma, Self::parse)(src)
+    }
+}
+
+impl std::fmt::Display for AcceptRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.media_range)?;
+        if let Some(q) = self.q {
+            write!(f, ";q={}", q)?;
+        }
+        for param in self.params.iter() {
+            write!(f, ";{}", param)?;
+        }
+        Ok(())
+    }
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn media_type_works() {
+        let (rest, media_type) =
+            MediaType::parse(b"multipart/mixed ; boundary=\"unique boundary\"").unwrap();
+        assert!(rest.is_empty());
+        assert!(media_type.is("Multipart", "MIXED"));
+        assert_eq!(
+            Some("\"unique boundary\"".to_owned()),
+            media_type.param("Boundary").map(ToString::to_string)
+        );
+        assert_eq!(
+            "multipart/mixed;boundary=\"unique boundary\"",
+            media_type.to_string()
+        );
+    }
+
+    #[test]
+    fn comparison_ignores_case() {
+        let (_, media_type) = MediaType::parse(b"Application/SDP").unwrap();
+        assert_eq!(media_type, "application/sdp");
+        let (_, media_type) = MediaType::parse(b"text/plain;charset=UTF-8").unwrap();
+        assert_eq!(media_type, "TEXT/plain;Charset=utf-8");
+        assert!(media_type != "text/plain");
+        assert!(MediaType::parse(b"application").is_err());
+    }
+
+    #[test]
+    fn accept_works() {
+        let (rest, ranges) =
+            AcceptRange::parse_list(b"application/sdp;level=1;q=0.8;x=y, */*;q=0.1, text/*")
+                .unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(3, ranges.len());
+        assert_eq!(1, ranges[0].media_range.params.len());
+        assert_eq!(QValue::new(800), ranges[0].q);
+        assert_eq!(1, ranges[0].params.len());
+        assert_eq!("application/sdp;level=1;q=0.8;x=y", ranges[0].to_string());
+        let sdp = MediaType::new("application", "sdp");
+        assert!(ranges[1].media_range.matches(&sdp));
+        assert!(!ranges[2].media_range.matches(&sdp));
+        assert!(ranges[2]
+            .media_range
+            .matches(&MediaType::new("TEXT", "html")));
+
+        let (_, ranges) = AcceptRange::parse_list(b"").unwrap();
+        assert!(ranges.is_empty());
+        assert!(AcceptRange::parse(b"text/*;q=2").is_err());
+    }
+}
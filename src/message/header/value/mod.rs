@@ -1,18 +1,28 @@
+mod contact;
+mod content_disposition;
+mod media_type;
+mod route;
+mod server;
 mod tag_param;
 mod via;
 
-use super::Address;
+pub use contact::{Contact, ContactAddress, ContactParam, QValue};
+pub use content_disposition::{ContentDisposition, DispositionParam, DispositionType, Handling};
+pub use media_type::{AcceptRange, MediaParam, MediaType};
+pub use route::{Route, RouteList};
+pub use server::ServerVal;
+pub use tag_param::TagParam;
+pub use via::{Transport, Via, ViaParm};
+
+use super::{Address, WellKnown};
 use crate::{
+    error::{Element, Error},
     message::Method,
-    parse_utils::{lws, parse_usize, semi, text_utf8_byte, word},
-};
-use nom::{
-    bytes::complete::{tag, take_while1},
-    sequence::tuple,
-    IResult, ParseTo,
+    parse_utils::{
+        comma, digits, lws, parse_usize, semi, text_utf8_byte, token, word, ParseResult,
+    },
 };
-use tag_param::TagParam;
-use via::Via;
+use nom::{bytes::complete::tag, sequence::tuple};
 
 pub enum Value {
     Via(Via),
@@ -29,72 +39,178 @@ pub enum Value {
         method: Method,
     },
     CallId(Box<[u8]>),
+    Contact(Contact),
     MaxForwards(usize),
+    Allow(Vec<Method>),
+    Supported(Vec<String>),
+    Require(Vec<String>),
+    ProxyRequire(Vec<String>),
+    Unsupported(Vec<String>),
+    ContentType(MediaType),
+    Accept(Vec<AcceptRange>),
+    ContentDisposition(ContentDisposition),
+    Route(RouteList),
+    RecordRoute(RouteList),
+    Path(RouteList),
+    ServiceRoute(RouteList),
     ContentLength(usize),
+    Server(Vec<ServerVal>),
+    UserAgent(Vec<ServerVal>),
     Raw(Box<[u8]>),
 }
 
 impl Value {
-    pub fn parse_with_name(name: impl AsRef<str>, src: &[u8]) -> IResult<&[u8], Self> {
-        match name.as_ref().to_lowercase().as_str() {
-            "via" | "v" => {
+    pub fn parse_with_name(name: impl AsRef<str>, src: &[u8]) -> ParseResult<'_, Self> {
+        match WellKnown::from_name(name.as_ref()) {
+            Some(WellKnown::Via) => {
                 let (rest, via) = Via::parse(src)?;
                 Ok((rest, Self::Via(via)))
             }
-            to_or_from @ ("to" | "t" | "from" | "f") => nom::combinator::map(
+            Some(to_or_from @ (WellKnown::To | WellKnown::From)) => nom::combinator::map(
                 tuple((
                     Address::parse,
                     nom::multi::many0(tuple((semi, TagParam::parse))),
                 )),
                 |(address, params)| {
                     let params = params.into_iter().map(|(_, p)| p).collect();
-                    if to_or_from.starts_with("t") {
+                    if to_or_from == WellKnown::To {
                         Self::To { address, params }
                     } else {
                         Self::From { address, params }
                     }
                 },
             )(src),
-            "cseq" => Self::parse_cseq(src),
-            "call-id" | "i" => Self::parse_call_id(src),
-            "max-forwards" => Self::parse_max_forwards(src),
-            "content-length" => Self::parse_content_length(src),
+            Some(WellKnown::CSeq) => Self::parse_cseq(src),
+            Some(WellKnown::CallId) => Self::parse_call_id(src),
+            Some(WellKnown::Contact) => nom::combinator::map(Contact::parse, Self::Contact)(src),
+            Some(WellKnown::MaxForwards) => Self::parse_max_forwards(src),
+            Some(WellKnown::Allow) => Self::parse_allow(src),
+            Some(WellKnown::Supported) => nom::combinator::map(
+                nom::multi::separated_list0(comma, option_tag),
+                Self::Supported,
+            )(src),
+            Some(WellKnown::Require) => nom::combinator::map(option_tags, Self::Require)(src),
+            Some(WellKnown::ProxyRequire) => {
+                nom::combinator::map(option_tags, Self::ProxyRequire)(src)
+            }
+            Some(WellKnown::Unsupported) => {
+                nom::combinator::map(option_tags, Self::Unsupported)(src)
+            }
+            Some(WellKnown::ContentType) => {
+                nom::combinator::map(MediaType::parse, Self::ContentType)(src)
+            }
+            Some(WellKnown::Accept) => {
+                nom::combinator::map(AcceptRange::parse_list, Self::Accept)(src)
+            }
+            Some(WellKnown::ContentDisposition) => {
+                nom::combinator::map(ContentDisposition::parse, Self::ContentDisposition)(src)
+            }
+            Some(WellKnown::Route) => nom::combinator::map(RouteList::parse, Self::Route)(src),
+            Some(WellKnown::RecordRoute) => {
+                nom::combinator::map(RouteList::parse, Self::RecordRoute)(src)
+            }
+            Some(WellKnown::Path) => nom::combinator::map(RouteList::parse, Self::Path)(src),
+            Some(WellKnown::ServiceRoute) => {
+                nom::combinator::map(RouteList::parse, Self::ServiceRoute)(src)
+            }
+            Some(WellKnown::ContentLength) => Self::parse_content_length(src),
+            Some(WellKnown::Server) => {
+                nom::combinator::map(ServerVal::parse_list, Self::Server)(src)
+            }
+            Some(WellKnown::UserAgent) => {
+                nom::combinator::map(ServerVal::parse_list, Self::UserAgent)(src)
+            }
             _ => Self::parse_default(src),
         }
     }
 }
 
+impl Value {
+    // The tag of a To or From value
+    pub fn tag(&self) -> Option<&str> {
+        match self {
+            Value::To { params, .. } | Value::From { params, .. } => {
+                params.iter().find_map(|param| match param {
+                    TagParam::Tag(tag) => Some(tag.as_str()),
+                    TagParam::Generic(_) => None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    // Parses a whole field value, e.g. the raw value of a header
+    pub fn parse_field(name: impl AsRef<str>, src: &[u8]) -> Result<Self, Error> {
+        let element = || Element::Header(name.as_ref().to_owned());
+        match Self::parse_with_name(name.as_ref(), src) {
+            Ok((rest, value)) if rest.trim_ascii().is_empty() => Ok(value),
+            Ok((rest, _)) => {
+                Err(Error::new(element(), rest, nom::error::ErrorKind::Eof).locate(src))
+            }
+            Err(e) => Err(Error::from(e).within(element()).locate(src)),
+        }
+    }
+
+    pub fn write_to(&self, w: &mut impl std::io::Write) -> std::io::Result<()> {
+        match self {
+            // Opaque values go back on the wire byte for byte
+            Value::CallId(bytes) | Value::Raw(bytes) => w.write_all(bytes),
+            _ => write!(w, "{}", self),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Via(via) => write!(f, "{}", via),
+            Value::To { address, params } | Value::From { address, params } => {
+                write!(f, "{}", address)?;
+                for param in params.iter() {
+                    write!(f, ";{}", param)?;
+                }
+                Ok(())
+            }
+            Value::CSeq { num, method } => write!(f, "{} {}", num, method),
+            Value::CallId(id) => write!(f, "{}", String::from_utf8_lossy(id)),
+            Value::Contact(contact) => write!(f, "{}", contact),
+            Value::MaxForwards(n) | Value::ContentLength(n) => write!(f, "{}", n),
+            Value::Allow(methods) => {
+                let methods = methods.iter().map(Method::as_str).collect::<Vec<_>>();
+                write!(f, "{}", methods.join(", "))
+            }
+            Value::Supported(tags)
+            | Value::Require(tags)
+            | Value::ProxyRequire(tags)
+            | Value::Unsupported(tags) => write!(f, "{}", tags.join(", ")),
+            Value::ContentType(media_type) => write!(f, "{}", media_type),
+            Value::Accept(ranges) => {
+                let ranges = ranges.iter().map(ToString::to_string).collect::<Vec<_>>();
+                write!(f, "{}", ranges.join(", "))
+            }
+            Value::ContentDisposition(disposition) => write!(f, "{}", disposition),
+            Value::Route(routes)
+            | Value::RecordRoute(routes)
+            | Value::Path(routes)
+            | Value::ServiceRoute(routes) => write!(f, "{}", routes),
+            Value::Server(list) | Value::UserAgent(list) => {
+                let list = list.iter().map(ToString::to_string).collect::<Vec<_>>();
+                write!(f, "{}", list.join(" "))
+            }
+            Value::Raw(raw) => write!(f, "{}", String::from_utf8_lossy(raw)),
+        }
+    }
+}
+
 impl TryFrom<&Value> for String {
     type Error = ();
 
     fn try_from(value: &Value) -> Result<Self, Self::Error> {
         match value {
-            Value::Via(_via) => Err(()),
-            Value::To { address, params } | Value::From { address, params } => Ok(format!(
-                "{}{}",
-                address.to_string(),
-                if params.is_empty() {
-                    String::new()
-                } else {
-                    format!(
-                        ";{}",
-                        params
-                            .iter()
-                            .map(ToString::to_string)
-                            .collect::<Vec<_>>()
-                            .join(";")
-                    )
-                }
-            )),
-            Value::CSeq { num, method } => Ok(format!("{} {}", num, method.to_string())),
-            Value::CallId(id) => std::str::from_utf8(id)
-                .map(ToOwned::to_owned)
-                .map_err(|_| {}),
-            Value::MaxForwards(n) => Ok(format!("{}", n)),
-            Value::ContentLength(n) => Ok(format!("{}", n)),
-            Value::Raw(raw) => std::str::from_utf8(raw)
+            Value::CallId(bytes) | Value::Raw(bytes) => std::str::from_utf8(bytes)
                 .map(ToOwned::to_owned)
                 .map_err(|_| {}),
+            _ => Ok(value.to_string()),
         }
     }
 }
@@ -122,25 +238,35 @@ impl std::fmt::Debug for Value {
             }
             Self::CSeq { num, method } => write!(f, "{} {:?}", num, method),
             Self::CallId(id) => write!(f, "{}", std::str::from_utf8(id).unwrap_or("BAD ID")),
+            Self::Contact(contact) => write!(f, "{:?}", contact),
             Self::MaxForwards(n) | Self::ContentLength(n) => write!(f, "{}", n),
+            Self::Allow(methods) => write!(f, "{:?}", methods),
+            Self::Supported(tags)
+            | Self::Require(tags)
+            | Self::ProxyRequire(tags)
+            | Self::Unsupported(tags) => write!(f, "{:?}", tags),
+            Self::ContentType(media_type) => write!(f, "{:?}", media_type),
+            Self::Accept(ranges) => write!(f, "{:?}", ranges),
+            Self::ContentDisposition(disposition) => write!(f, "{:?}", disposition),
+            Self::Route(routes)
+            | Self::RecordRoute(routes)
+            | Self::Path(routes)
+            | Self::ServiceRoute(routes) => write!(f, "{:?}", routes),
+            Self::Server(list) | Self::UserAgent(list) => write!(f, "{:?}", list),
             Self::Raw(raw) => write!(f, "{:?}", std::str::from_utf8(raw)),
         }
     }
 }
 
 impl Value {
-    fn parse_cseq(src: &[u8]) -> IResult<&[u8], Self> {
+    fn parse_cseq(src: &[u8]) -> ParseResult<'_, Self> {
         // CSeq  =  "CSeq" HCOLON 1*DIGIT LWS Method
-        nom::combinator::map(
-            tuple((take_while1(|x: u8| x.is_ascii_digit()), lws, Method::parse)),
-            |(cseq, _, method)| Self::CSeq {
-                num: cseq.parse_to().unwrap(),
-                method,
-            },
-        )(src)
+        nom::combinator::map(tuple((digits, lws, Method::parse)), |(num, _, method)| {
+            Self::CSeq { num, method }
+        })(src)
     }
 
-    fn parse_call_id(src: &[u8]) -> IResult<&[u8], Self> {
+    fn parse_call_id(src: &[u8]) -> ParseResult<'_, Self> {
         // callid   =  word [ "@" word ]
         let mut id = Vec::new();
         let (rest, w1) = word(src)?;
@@ -153,25 +279,44 @@ impl Value {
         Ok((rest, Self::CallId(id.into_boxed_slice())))
     }
 
-    fn parse_max_forwards(src: &[u8]) -> IResult<&[u8], Self> {
+    fn parse_max_forwards(src: &[u8]) -> ParseResult<'_, Self> {
         // Max-Forwards  =  "Max-Forwards" HCOLON 1*DIGIT
         nom::combinator::map(parse_usize(), |max_forwards| {
             Self::MaxForwards(max_forwards)
         })(src)
     }
 
-    fn parse_content_length(src: &[u8]) -> IResult<&[u8], Self> {
+    fn parse_allow(src: &[u8]) -> ParseResult<'_, Self> {
+        // Allow  =  "Allow" HCOLON [Method *(COMMA Method)]
+        nom::combinator::map(
+            nom::multi::separated_list0(comma, Method::parse),
+            Self::Allow,
+        )(src)
+    }
+
+    fn parse_content_length(src: &[u8]) -> ParseResult<'_, Self> {
         // Content-Length  =  ( "Content-Length" / "l" ) HCOLON 1*DIGIT
-        nom::combinator::map(parse_usize(), |length| Self::ContentLength(length))(src)
+        nom::combinator::map(parse_usize(), Self::ContentLength)(src)
     }
 
-    fn parse_default(src: &[u8]) -> IResult<&[u8], Self> {
+    fn parse_default(src: &[u8]) -> ParseResult<'_, Self> {
         nom::combinator::map(
             nom::multi::many0(nom::branch::alt((text_utf8_byte, lws))),
             |x| Self::Raw(x.into_boxed_slice()),
         )(src)
     }
 }
+
+fn option_tags(src: &[u8]) -> ParseResult<'_, Vec<String>> {
+    // Require  =  "Require" HCOLON option-tag *(COMMA option-tag)
+    nom::multi::separated_list1(comma, option_tag)(src)
+}
+
+fn option_tag(src: &[u8]) -> ParseResult<'_, String> {
+    // option-tag  =  token
+    nom::combinator::map(token, |tag| String::from_utf8_lossy(tag).into_owned())(src)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -183,4 +328,58 @@ mod tests {
         assert!(rest.is_empty());
         assert_eq!("Ok(\"lunch  with \\tme мама\")", format!("{:?}", v));
     }
+
+    #[test]
+    fn cseq_keeps_extension_method() {
+        let (rest, v) = Value::parse_with_name("CSeq", b"4711 FOOBAR").unwrap();
+        assert!(rest.is_empty());
+        let Value::CSeq { num, method } = &v else {
+            unreachable!()
+        };
+        assert_eq!(4711, *num);
+        assert_eq!(Method::Extension("FOOBAR".to_owned()), *method);
+        assert_eq!(Ok("4711 FOOBAR".to_owned()), String::try_from(&v));
+    }
+
+    #[test]
+    fn numbers_out_of_range_fail() {
+        assert!(Value::parse_field("CSeq", b"4294967296 INVITE").is_err());
+        assert!(Value::parse_field("Max-Forwards", b"99999999999999999999999").is_err());
+        let e = Value::parse_field("Via", b"SIP/2.0/UDP pc33.atlanta.com;ttl=300").unwrap_err();
+        assert_eq!(
+            &[Element::Header("Via".to_owned()), Element::ViaParam],
+            e.elements()
+        );
+        assert!(Value::parse_field("Via", b"SIP/2.0/UDP pc33.atlanta.com;ttl=255").is_ok());
+    }
+
+    #[test]
+    fn to_from_to_string() {
+        let (_, v) =
+            Value::parse_with_name("From", b"\"Bob\" <sip:bob@biloxi.com>;tag=a6c85cf").unwrap();
+        assert_eq!("\"Bob\" <sip:bob@biloxi.com>;tag=a6c85cf", v.to_string());
+        let (_, v) = Value::parse_with_name("t", b"<sip:127.0.0.1:5060>").unwrap();
+        assert_eq!("<sip:127.0.0.1:5060>", v.to_string());
+        let (_, v) = Value::parse_with_name("From", b"<tel:+15551234>;tag=887s").unwrap();
+        assert_eq!("<tel:+15551234>;tag=887s", v.to_string());
+    }
+
+    #[test]
+    fn allow_and_option_tags_work() {
+        let (rest, v) = Value::parse_with_name("Allow", b"INVITE, ACK,BYE , FOO").unwrap();
+        assert!(rest.is_empty());
+        let Value::Allow(methods) = &v else {
+            unreachable!()
+        };
+        assert_eq!(Method::Extension("FOO".to_owned()), methods[3]);
+        assert_eq!("INVITE, ACK, BYE, FOO", v.to_string());
+
+        let (_, v) = Value::parse_with_name("Allow", b"").unwrap();
+        assert_eq!("", v.to_string());
+        let (_, v) = Value::parse_with_name("k", b"timer, 100rel").unwrap();
+        assert!(matches!(v, Value::Supported(ref tags) if tags == &["timer", "100rel"]));
+        assert!(Value::parse_with_name("Require", b"").is_err());
+        let (_, v) = Value::parse_with_name("Proxy-Require", b"foo").unwrap();
+        assert!(matches!(v, Value::ProxyRequire(_)));
+    }
 }
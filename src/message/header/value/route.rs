@@ -0,0 +1,126 @@
+use nom::{
+    multi::{many0, separated_list1},
+    sequence::{preceded, tuple},
+};
+
+use crate::{
+    message::{header::Spec, uriparameter::UriParameter, GenericParam, Uri},
+    parse_utils::{comma, semi, ParseResult},
+};
+
+// Route, Record-Route, Path (RFC 3327) and Service-Route (RFC 3608)
+// all share the same grammar
+#[derive(Debug)]
+pub struct RouteList {
+    inner: Vec<Route>,
+}
+
+#[derive(Debug)]
+pub struct Route {
+    pub spec: Spec,
+    pub params: Vec<GenericParam>,
+}
+
+impl RouteList {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // Route        =  "Route" HCOLON route-param *(COMMA route-param)
+        nom::combinator::map(separated_list1(comma, Route::parse), |inner| Self { inner })(src)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Route> {
+        self.inner.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+impl std::fmt::Display for RouteList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut routes = self.inner.iter();
+        if let Some(first) = routes.next() {
+            write!(f, "{}", first)?;
+        }
+        for route in routes {
+            write!(f, ", {}", route)?;
+        }
+        Ok(())
+    }
+}
+
+impl Route {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // route-param  =  name-addr *( SEMI rr-param )
+        // rr-param     =  generic-param
+        let (rest, (spec, params)) = tuple((
+            Spec::parse_before_params,
+            many0(preceded(semi, GenericParam::parse)),
+        ))(src)?;
+        if let Spec::AddrSpec(_) = spec {
+            return Err(nom::Err::Error(nom::error::make_error(
+                src,
+                nom::error::ErrorKind::Verify,
+            )));
+        }
+        Ok((rest, Self { spec, params }))
+    }
+
+    pub fn uri(&self) -> &Uri {
+        self.spec.uri()
+    }
+
+    pub fn is_loose(&self) -> bool {
+        // The lr parameter is carried by the URI, not by the header field
+        match self.uri() {
+            Uri::Sip(uri) | Uri::Sips(uri) => {
+                uri.parameters.iter().any(|p| matches!(p, UriParameter::Lr))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Route {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.spec)?;
+        for param in self.params.iter() {
+            write!(f, ";{}", param)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_list_works() {
+        let raw = b"<sip:p1.example.com;lr>, \"Proxy 2\" <sip:p2.domain.com>;foo=bar";
+        let (rest, routes) = RouteList::parse(raw).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(2, routes.len());
+        let routes = routes.iter().collect::<Vec<_>>();
+        assert!(routes[0].is_loose());
+        assert_eq!("sip:p1.example.com;lr", routes[0].uri().to_string());
+        assert!(!routes[1].is_loose());
+        assert_eq!(1, routes[1].params.len());
+        assert_eq!(
+            "<sip:p1.example.com;lr>, \"Proxy 2\" <sip:p2.domain.com>;foo=bar",
+            RouteList::parse(raw).unwrap().1.to_string()
+        );
+        let (_, routes) = RouteList::parse(b"<sip:p1.example.com;lr=on>").unwrap();
+        assert!(routes.iter().next().unwrap().is_loose());
+    }
+
+    #[test]
+    fn addr_spec_must_fail() {
+        assert!(RouteList::parse(b"sip:p1.example.com;lr").is_err());
+        assert!(RouteList::parse(b"").is_err());
+    }
+}
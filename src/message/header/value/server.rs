@@ -0,0 +1,99 @@
+use nom::{
+    combinator::{map, opt},
+    multi::many0,
+    sequence::{preceded, tuple},
+};
+
+use crate::parse_utils::{comment, slash, sws, token, ParseResult};
+
+// Server and User-Agent share the same grammar
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerVal {
+    Product {
+        name: String,
+        version: Option<String>,
+    },
+    // Kept as written between the outer parentheses, escapes included
+    Comment(String),
+}
+
+impl ServerVal {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // server-val       =  product / comment
+        // product          =  token [SLASH product-version]
+        // product-version  =  token
+        nom::branch::alt((
+            map(comment, |comment| {
+                Self::Comment(String::from_utf8_lossy(comment).into_owned())
+            }),
+            map(
+                tuple((token, opt(preceded(slash, token)))),
+                |(name, version)| Self::Product {
+                    name: lossy(name),
+                    version: version.map(lossy),
+                },
+            ),
+        ))(src)
+    }
+
+    pub fn parse_list(src: &[u8]) -> ParseResult<'_, Vec<Self>> {
+        // Server      =  "Server" HCOLON server-val *(LWS server-val)
+        // User-Agent  =  "User-Agent" HCOLON server-val *(LWS server-val)
+        // A comment carries its own SWS, so LWS may be missing around it
+        let (rest, (first, others)) = tuple((Self::parse, many0(preceded(sws, Self::parse))))(src)?;
+        let mut list = vec![first];
+        list.extend(others);
+        Ok((rest, list))
+    }
+}
+
+impl std::fmt::Display for ServerVal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerVal::Product { name, version } => {
+                write!(f, "{}", name)?;
+                if let Some(version) = version {
+                    write!(f, "/{}", version)?;
+                }
+                Ok(())
+            }
+            ServerVal::Comment(comment) => write!(f, "({})", comment),
+        }
+    }
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let (rest, list) =
+            ServerVal::parse_list(b"Udith/0.1 (Linux; \\(x86\\) (64 bit)) libnom / 7").unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            vec![
+                ServerVal::Product {
+                    name: "Udith".to_owned(),
+                    version: Some("0.1".to_owned())
+                },
+                ServerVal::Comment("Linux; \\(x86\\) (64 bit)".to_owned()),
+                ServerVal::Product {
+                    name: "libnom".to_owned(),
+                    version: Some("7".to_owned())
+                },
+            ],
+            list
+        );
+        let (_, list) = ServerVal::parse_list(b"HomeServer v2(beta)").unwrap();
+        assert_eq!(3, list.len());
+        assert_eq!("(beta)", list[2].to_string());
+
+        let (rest, _) = ServerVal::parse_list(b"Broken (unbalanced").unwrap();
+        assert_eq!(b" (unbalanced", rest);
+    }
+}
@@ -10,27 +10,27 @@ pub enum TagParam {
 }
 
 impl TagParam {
-    pub fn parse(src: &[u8]) -> ParseResult<Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         nom::branch::alt((parse_tag, parse_generic))(src)
     }
 }
 
-impl ToString for TagParam {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for TagParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            TagParam::Tag(tag) => format!("tag={}", tag.to_string()),
-            TagParam::Generic(param) => param.to_string(),
+            TagParam::Tag(tag) => write!(f, "tag={}", tag),
+            TagParam::Generic(param) => write!(f, "{}", param),
         }
     }
 }
 
-fn parse_tag(src: &[u8]) -> ParseResult<TagParam> {
+fn parse_tag(src: &[u8]) -> ParseResult<'_, TagParam> {
     nom::combinator::map(
         nom::sequence::tuple((nom::bytes::complete::tag(b"tag"), equal, token)),
-        |(_, _, token)| TagParam::Tag(String::from_utf8(token.to_vec()).unwrap()),
+        |(_, _, token)| TagParam::Tag(String::from_utf8_lossy(token).into_owned()),
     )(src)
 }
 
-fn parse_generic(src: &[u8]) -> ParseResult<TagParam> {
-    nom::combinator::map(GenericParam::parse, |p| TagParam::Generic(p))(src)
+fn parse_generic(src: &[u8]) -> ParseResult<'_, TagParam> {
+    nom::combinator::map(GenericParam::parse, TagParam::Generic)(src)
 }
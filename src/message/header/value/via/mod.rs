@@ -4,8 +4,13 @@ mod transport;
 mod via_param;
 mod via_parm;
 
-use crate::parse_utils::{comma, ParseResult};
-use via_parm::ViaParm;
+pub use transport::Transport;
+pub use via_parm::ViaParm;
+
+use crate::{
+    message::Host,
+    parse_utils::{comma, ParseResult},
+};
 
 #[derive(Debug)]
 pub struct Via {
@@ -13,21 +18,50 @@ pub struct Via {
 }
 
 impl Via {
-    pub fn parse(src: &[u8]) -> ParseResult<Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         nom::combinator::map(
             nom::multi::separated_list1(comma, ViaParm::parse),
             |inner| Self { inner },
         )(src)
     }
+
+    // A SIP/2.0 Via as a client adds it to a new request
+    pub fn new(
+        transport: Transport,
+        host: Host,
+        port: Option<u16>,
+        branch: impl Into<String>,
+    ) -> Self {
+        Self {
+            inner: vec![ViaParm::new(transport, host, port, branch.into())],
+        }
+    }
+
+    // The via-parm of the hop closest to the receiver
+    pub fn first(&self) -> Option<&ViaParm> {
+        self.inner.first()
+    }
+
+    pub fn first_mut(&mut self) -> Option<&mut ViaParm> {
+        self.inner.first_mut()
+    }
+
+    // Branch of the first via-parm in the header
+    pub fn branch(&self) -> Option<&str> {
+        self.inner.first().and_then(ViaParm::branch)
+    }
 }
 
-impl ToString for Via {
-    fn to_string(&self) -> String {
-        self.inner
-            .iter()
-            .map(|p| p.to_string())
-            .collect::<Vec<_>>()
-            .join(",")
+impl std::fmt::Display for Via {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parms = self.inner.iter();
+        if let Some(first) = parms.next() {
+            write!(f, "{}", first)?;
+        }
+        for parm in parms {
+            write!(f, ", {}", parm)?;
+        }
+        Ok(())
     }
 }
 
@@ -41,5 +75,66 @@ mod tests {
         let (rest, via) = Via::parse(src).unwrap();
         println!("Via={:?}", via);
         assert!(rest.is_empty());
+        assert_eq!(
+            "SIP/2.0/UDP 127.0.0.1:40675;rport;branch=z9hG4bK7rmHHX13H1N3e;x-param=Custom",
+            via.to_string()
+        );
+    }
+
+    #[test]
+    fn ipv6_works() {
+        let src = b"SIP/2.0/UDP [2001:db8::9:1]:5060;received=[2001:db8::9:255];maddr=[2001:db8::9:2];branch=z9hG4bKas3";
+        let (rest, via) = Via::parse(src).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            "SIP/2.0/UDP [2001:db8::9:1]:5060;received=2001:db8::9:255;maddr=[2001:db8::9:2];branch=z9hG4bKas3",
+            via.to_string()
+        );
+        let (rest, via) = Via::parse(b"SIP/2.0/UDP 192.0.2.1;received=2001:db8::9:255").unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            "SIP/2.0/UDP 192.0.2.1;received=2001:db8::9:255",
+            via.to_string()
+        );
+    }
+
+    #[test]
+    fn received_and_rport_work() {
+        let (_, mut via) =
+            Via::parse(b"SIP/2.0/WSS df7jal23ls0d.invalid;rport;branch=z9hG4bKa;alias").unwrap();
+        let parm = via.first_mut().unwrap();
+        assert_eq!(&Transport::Wss, parm.transport());
+        assert_eq!(None, parm.port());
+        assert!(parm.wants_rport());
+        assert!(parm.has_alias());
+        assert_eq!(None, parm.rport());
+        parm.set_received(Host::Ipv4("192.0.2.4".parse().unwrap()));
+        parm.set_rport(49152);
+        assert_eq!(Some(49152), parm.rport());
+        assert_eq!(
+            Some(&Host::Ipv4("192.0.2.4".parse().unwrap())),
+            parm.received()
+        );
+        assert_eq!(
+            "SIP/2.0/WSS df7jal23ls0d.invalid;rport=49152;branch=z9hG4bKa;alias;received=192.0.2.4",
+            via.to_string()
+        );
+
+        let (rest, via) = Via::parse(b"SIP/2.0/UDP a.com;rportx=1").unwrap();
+        assert!(rest.is_empty());
+        assert!(!via.first().unwrap().wants_rport());
+        assert!(Via::parse(b"SIP/2.0/UDP a.com;rport=x").is_err());
+    }
+
+    #[test]
+    fn many_parms_to_string() {
+        let src =
+            b"SIP/2.0/TCP proxy.example.com;branch=z9hG4bKa ,SIP/2.0/UDP 10.0.0.1:5070;ttl=16";
+        let (rest, via) = Via::parse(src).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            "SIP/2.0/TCP proxy.example.com;branch=z9hG4bKa, SIP/2.0/UDP 10.0.0.1:5070;ttl=16",
+            via.to_string()
+        );
     }
 }
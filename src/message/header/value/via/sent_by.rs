@@ -1,15 +1,19 @@
-use nom::{sequence::tuple, IResult};
+use nom::sequence::tuple;
 
-use crate::parse_utils::{colon, parse_host, parse_port};
+use crate::{
+    message::Host,
+    parse_utils::{colon, parse_host, parse_port, ParseResult},
+};
 
 #[derive(Debug)]
 pub struct SentBy {
-    host: String,
-    port: Option<u16>,
+    pub host: Host,
+    pub port: Option<u16>,
 }
 
 impl SentBy {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // sent-by  =  host [ COLON port ]
         nom::combinator::map(
             tuple((
                 parse_host,
@@ -23,12 +27,12 @@ impl SentBy {
     }
 }
 
-impl ToString for SentBy {
-    fn to_string(&self) -> String {
-        format!(
-            "{}{}",
-            self.host,
-            self.port.map(|p| format!(":{}", p)).unwrap_or_default()
-        )
+impl std::fmt::Display for SentBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.host)?;
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        Ok(())
     }
 }
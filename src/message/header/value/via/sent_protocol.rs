@@ -1,9 +1,8 @@
 use super::transport::Transport;
-use crate::parse_utils::token;
-use nom::{branch::alt, bytes::complete::tag, IResult};
+use crate::parse_utils::{slash, token, ParseResult};
+use nom::{branch::alt, bytes::complete::tag};
 
 const SIP: &[u8] = b"SIP";
-const SLASH: &[u8] = b"/";
 
 #[derive(Debug)]
 pub struct SentProtocol {
@@ -13,15 +12,11 @@ pub struct SentProtocol {
 }
 
 impl SentProtocol {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // sent-protocol  =  protocol-name SLASH protocol-version
+        //                   SLASH transport
         nom::combinator::map(
-            nom::sequence::tuple((
-                ProtocolName::parse,
-                tag(SLASH),
-                token,
-                tag(SLASH),
-                Transport::parse,
-            )),
+            nom::sequence::tuple((ProtocolName::parse, slash, token, slash, Transport::parse)),
             |(name, _, version, _, transport)| Self {
                 name,
                 version: version.to_vec().into_boxed_slice(),
@@ -31,13 +26,14 @@ impl SentProtocol {
     }
 }
 
-impl ToString for SentProtocol {
-    fn to_string(&self) -> String {
-        format!(
+impl std::fmt::Display for SentProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
             "{}/{}/{}",
-            self.name.to_string(),
-            String::from_utf8(self.version.to_vec()).unwrap(),
-            self.transport.to_string()
+            self.name,
+            String::from_utf8_lossy(&self.version),
+            self.transport
         )
     }
 }
@@ -49,22 +45,19 @@ pub enum ProtocolName {
 }
 
 impl ProtocolName {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         nom::combinator::map(alt((tag(SIP), token)), |name| match name {
             SIP => Self::Sip,
-            protocol => Self::Protocol(std::str::from_utf8(protocol).unwrap().to_owned()),
+            protocol => Self::Protocol(String::from_utf8_lossy(protocol).into_owned()),
         })(src)
     }
 }
 
-impl ToString for ProtocolName {
-    fn to_string(&self) -> String {
-        format!(
-            "{}",
-            match self {
-                ProtocolName::Sip => "SIP",
-                ProtocolName::Protocol(name) => name.as_str(),
-            }
-        )
+impl std::fmt::Display for ProtocolName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolName::Sip => write!(f, "SIP"),
+            ProtocolName::Protocol(name) => write!(f, "{}", name),
+        }
     }
 }
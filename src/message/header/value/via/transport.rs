@@ -1,46 +1,88 @@
-use crate::parse_utils::token;
-use nom::{bytes::complete::tag, IResult};
+use crate::parse_utils::{token, ParseResult};
 
 const UDP: &[u8] = b"UDP";
 const TCP: &[u8] = b"TCP";
 const TLS: &[u8] = b"TLS";
 const SCTP: &[u8] = b"SCTP";
+const WS: &[u8] = b"WS";
+const WSS: &[u8] = b"WSS";
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Transport {
     Udp,
     Tcp,
     Tls,
     Sctp,
+    // SIP over WebSocket (RFC 7118 5)
+    Ws,
+    Wss,
     Other(String),
 }
 
 impl Transport {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
-        nom::combinator::map(
-            nom::branch::alt((tag(UDP), tag(TCP), tag(TLS), tag(SCTP), token)),
-            |transport| match transport {
-                UDP => Self::Udp,
-                TCP => Self::Tcp,
-                TLS => Self::Tls,
-                SCTP => Self::Sctp,
-                other => Self::Other(String::from_utf8(other.to_vec()).unwrap()),
-            },
-        )(src)
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // transport  =  "UDP" / "TCP" / "TLS" / "SCTP" / "WS" / "WSS"
+        //               / other-transport
+        // The whole token is taken, so "WS" is not read out of "WSS"
+        nom::combinator::map(token, |transport| match transport {
+            UDP => Self::Udp,
+            TCP => Self::Tcp,
+            TLS => Self::Tls,
+            SCTP => Self::Sctp,
+            WS => Self::Ws,
+            WSS => Self::Wss,
+            other => Self::Other(String::from_utf8_lossy(other).into_owned()),
+        })(src)
+    }
+
+    pub fn is_secure(&self) -> bool {
+        matches!(self, Self::Tls | Self::Wss)
+    }
+
+    // Port of a sent-by without one (RFC 3261 18)
+    pub fn default_port(&self) -> u16 {
+        if self.is_secure() {
+            5061
+        } else {
+            5060
+        }
     }
 }
 
-impl ToString for Transport {
-    fn to_string(&self) -> String {
-        format!(
-            "{}",
-            match self {
-                Transport::Udp => "UDP",
-                Transport::Tcp => "TCP",
-                Transport::Tls => "TLS",
-                Transport::Sctp => "SCTP",
-                Transport::Other(transport) => transport.as_str(),
-            }
-        )
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transport::Udp => write!(f, "UDP"),
+            Transport::Tcp => write!(f, "TCP"),
+            Transport::Tls => write!(f, "TLS"),
+            Transport::Sctp => write!(f, "SCTP"),
+            Transport::Ws => write!(f, "WS"),
+            Transport::Wss => write!(f, "WSS"),
+            Transport::Other(transport) => write!(f, "{}", transport),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn websocket_works() {
+        assert_eq!(
+            (b"".as_slice(), Transport::Ws),
+            Transport::parse(b"WS").unwrap()
+        );
+        assert_eq!(
+            (b" x".as_slice(), Transport::Wss),
+            Transport::parse(b"WSS x").unwrap()
+        );
+        assert_eq!(
+            Transport::Other("WSX".to_owned()),
+            Transport::parse(b"WSX").unwrap().1
+        );
+        assert!(Transport::Wss.is_secure());
+        assert!(!Transport::Ws.is_secure());
+        assert_eq!("WSS", Transport::Wss.to_string());
     }
 }
@@ -1,74 +1,102 @@
 use nom::{bytes::complete::tag, sequence::tuple};
 
 use crate::{
-    message::GenericParam,
-    parse_utils::{equal, parse_host, parse_u8, semi, token, ParseResult},
+    error::{within, Element},
+    message::{GenericParam, Host},
+    parse_utils::{equal, parse_host, parse_port, parse_u8, semi, token, ParseResult},
 };
 
 #[derive(Debug)]
 pub enum ViaParam {
     // via-params        =  via-ttl / via-maddr / via-received / via-branch / via-extension
     Ttl(u8),
-    Maddr(String),
-    Received(String),
+    Maddr(Host),
+    Received(Host),
     Branch(String),
+    // response-port     =  "rport" [EQUAL 1*DIGIT] (RFC 3581)
+    Rport(Option<u16>),
     Extension(GenericParam),
 }
 
 impl ViaParam {
-    pub fn parse(src: &[u8]) -> ParseResult<Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         let (remainder, _) = semi(src)?;
-        nom::branch::alt((
-            parse_ttl,
-            parse_maddr,
-            parse_received,
-            parse_branch,
-            parse_extension,
+        // Once the separator is seen the parameter must be well formed
+        nom::combinator::cut(within(
+            Element::ViaParam,
+            nom::branch::alt((
+                parse_ttl,
+                parse_maddr,
+                parse_received,
+                parse_branch,
+                parse_rport,
+                parse_extension,
+            )),
         ))(remainder)
     }
 }
 
-impl ToString for ViaParam {
-    fn to_string(&self) -> String {
-        format!(
-            "{}",
-            match self {
-                ViaParam::Ttl(ttl) => format!("ttl={}", ttl),
-                ViaParam::Maddr(host) => format!("maddr={}", host),
-                ViaParam::Received(addr) => format!("received={}", addr),
-                ViaParam::Branch(token) => format!("branch={}", token),
-                ViaParam::Extension(param) => param.to_string(),
-            }
-        )
+impl std::fmt::Display for ViaParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ViaParam::Ttl(ttl) => write!(f, "ttl={}", ttl),
+            ViaParam::Maddr(host) => write!(f, "maddr={}", host),
+            // via-received carries a bare IPv6address, not an IPv6reference
+            ViaParam::Received(Host::Ipv6(addr)) => write!(f, "received={}", addr),
+            ViaParam::Received(addr) => write!(f, "received={}", addr),
+            ViaParam::Branch(token) => write!(f, "branch={}", token),
+            ViaParam::Rport(None) => write!(f, "rport"),
+            ViaParam::Rport(Some(port)) => write!(f, "rport={}", port),
+            ViaParam::Extension(param) => write!(f, "{}", param),
+        }
     }
 }
 
-fn parse_ttl(src: &[u8]) -> ParseResult<ViaParam> {
-    nom::combinator::map(tuple((tag(b"ttl"), equal, parse_u8())), |(_, _, ttl)| {
-        ViaParam::Ttl(ttl)
-    })(src)
+fn parse_ttl(src: &[u8]) -> ParseResult<'_, ViaParam> {
+    // ttl  =  1*3DIGIT ; 0 to 255, anything else after "ttl=" is an error
+    nom::combinator::map(
+        nom::sequence::preceded(
+            tuple((tag(b"ttl"), equal)),
+            nom::combinator::cut(parse_u8()),
+        ),
+        ViaParam::Ttl,
+    )(src)
 }
 
-fn parse_maddr(src: &[u8]) -> ParseResult<ViaParam> {
+fn parse_maddr(src: &[u8]) -> ParseResult<'_, ViaParam> {
     nom::combinator::map(tuple((tag(b"maddr"), equal, parse_host)), |(_, _, host)| {
         ViaParam::Maddr(host)
     })(src)
 }
 
-fn parse_received(src: &[u8]) -> ParseResult<ViaParam> {
+fn parse_received(src: &[u8]) -> ParseResult<'_, ViaParam> {
     nom::combinator::map(
-        tuple((tag(b"received"), equal, parse_host)),
+        tuple((tag(b"received"), equal, Host::parse_ip)),
         |(_, _, host)| ViaParam::Received(host),
     )(src)
 }
 
-fn parse_branch(src: &[u8]) -> ParseResult<ViaParam> {
+fn parse_branch(src: &[u8]) -> ParseResult<'_, ViaParam> {
     nom::combinator::map(tuple((tag(b"branch"), equal, token)), |(_, _, token)| {
-        ViaParam::Branch(String::from_utf8(token.to_vec()).unwrap())
+        ViaParam::Branch(String::from_utf8_lossy(token).into_owned())
     })(src)
 }
 
-fn parse_extension(src: &[u8]) -> ParseResult<ViaParam> {
+fn parse_rport(src: &[u8]) -> ParseResult<'_, ViaParam> {
+    // The whole name is taken so "rportx" stays an extension
+    nom::combinator::map(
+        nom::sequence::preceded(
+            nom::combinator::verify(token, |name: &[u8]| name == b"rport"),
+            nom::combinator::opt(nom::sequence::preceded(
+                equal,
+                nom::combinator::cut(parse_port()),
+            )),
+        ),
+        ViaParam::Rport,
+    )(src)
+}
+
+fn parse_extension(src: &[u8]) -> ParseResult<'_, ViaParam> {
     nom::combinator::map(GenericParam::parse, |parsed_param| {
         ViaParam::Extension(parsed_param)
     })(src)
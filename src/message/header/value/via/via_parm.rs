@@ -1,5 +1,13 @@
-use super::{sent_by::SentBy, sent_protocol::SentProtocol, via_param::ViaParam};
-use crate::parse_utils::{lws, ParseResult};
+use super::{
+    sent_by::SentBy,
+    sent_protocol::{ProtocolName, SentProtocol},
+    transport::Transport,
+    via_param::ViaParam,
+};
+use crate::{
+    message::Host,
+    parse_utils::{lws, ParseResult},
+};
 use nom::sequence::tuple;
 
 #[derive(Debug)]
@@ -10,7 +18,84 @@ pub struct ViaParm {
 }
 
 impl ViaParm {
-    pub fn parse(src: &[u8]) -> ParseResult<Self> {
+    pub fn new(transport: Transport, host: Host, port: Option<u16>, branch: String) -> Self {
+        Self {
+            sent_protocol: SentProtocol {
+                name: ProtocolName::Sip,
+                version: b"2.0".as_slice().into(),
+                transport,
+            },
+            sent_by: SentBy { host, port },
+            params: vec![ViaParam::Branch(branch)],
+        }
+    }
+
+    pub fn branch(&self) -> Option<&str> {
+        self.params.iter().find_map(|param| match param {
+            ViaParam::Branch(branch) => Some(branch.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn transport(&self) -> &Transport {
+        &self.sent_protocol.transport
+    }
+
+    pub fn host(&self) -> &Host {
+        &self.sent_by.host
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.sent_by.port
+    }
+
+    // Source address of the request as its receiver saw it
+    pub fn received(&self) -> Option<&Host> {
+        self.params.iter().find_map(|param| match param {
+            ViaParam::Received(host) => Some(host),
+            _ => None,
+        })
+    }
+
+    // Source port filled in by the receiver, once it has been
+    pub fn rport(&self) -> Option<u16> {
+        self.params.iter().find_map(|param| match param {
+            ViaParam::Rport(port) => *port,
+            _ => None,
+        })
+    }
+
+    pub fn wants_rport(&self) -> bool {
+        self.params
+            .iter()
+            .any(|param| matches!(param, ViaParam::Rport(_)))
+    }
+
+    // The sender accepts requests back over the connection (RFC 5923)
+    pub fn has_alias(&self) -> bool {
+        self.params.iter().any(|param| {
+            matches!(param, ViaParam::Extension(ext) if ext.name().eq_ignore_ascii_case("alias"))
+        })
+    }
+
+    pub fn set_received(&mut self, host: Host) {
+        self.params
+            .retain(|param| !matches!(param, ViaParam::Received(_)));
+        self.params.push(ViaParam::Received(host));
+    }
+
+    pub fn set_rport(&mut self, port: u16) {
+        match self
+            .params
+            .iter_mut()
+            .find(|param| matches!(param, ViaParam::Rport(_)))
+        {
+            Some(param) => *param = ViaParam::Rport(Some(port)),
+            None => self.params.push(ViaParam::Rport(Some(port))),
+        }
+    }
+
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         nom::combinator::map(
             tuple((
                 SentProtocol::parse,
@@ -27,17 +112,12 @@ impl ViaParm {
     }
 }
 
-impl ToString for ViaParm {
-    fn to_string(&self) -> String {
-        format!(
-            "{} {}; {}",
-            self.sent_protocol.to_string(),
-            self.sent_by.to_string(),
-            self.params
-                .iter()
-                .map(|p| p.to_string())
-                .collect::<Vec<_>>()
-                .join(";")
-        )
+impl std::fmt::Display for ViaParm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.sent_protocol, self.sent_by)?;
+        for param in self.params.iter() {
+            write!(f, ";{}", param)?;
+        }
+        Ok(())
     }
 }
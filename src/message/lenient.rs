@@ -0,0 +1,260 @@
+use crate::parse_utils::{CRLF, SIP_VERSION};
+
+// What lenient parsing takes from equipment in the field, and how it reads it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deviation {
+    // LF without CR ends a line
+    BareLf,
+    // SIP-Version in any case, e.g. "sip/2.0"
+    LowercaseVersion,
+    // Tabs, several spaces or leading spaces between the parts of the
+    // start line
+    StartLineWhitespace,
+    // Spaces or tabs at the end of the start line
+    TrailingWhitespace,
+    // The input ends before the empty line closing the headers
+    MissingFinalCrlf,
+}
+
+impl std::fmt::Display for Deviation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Deviation::BareLf => write!(f, "LF without CR"),
+            Deviation::LowercaseVersion => write!(f, "SIP version not in upper case"),
+            Deviation::StartLineWhitespace => write!(f, "whitespace other than SP in start line"),
+            Deviation::TrailingWhitespace => write!(f, "whitespace at end of start line"),
+            Deviation::MissingFinalCrlf => write!(f, "no empty line after the headers"),
+        }
+    }
+}
+
+// A deviation and the first line, counted from 1, it was found on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Warning {
+    pub deviation: Deviation,
+    pub line: usize,
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.deviation)
+    }
+}
+
+// The start line and headers rewritten the way the strict parser takes them.
+// The body is left alone, it starts `consumed` bytes into the input.
+pub(crate) struct Head {
+    pub bytes: Vec<u8>,
+    pub consumed: usize,
+    pub warnings: Vec<Warning>,
+}
+
+impl Head {
+    pub fn normalize(src: &[u8]) -> Self {
+        let mut head = Self {
+            bytes: Vec::with_capacity(src.len() + 4),
+            consumed: 0,
+            warnings: vec![],
+        };
+        let mut rest = src;
+        let mut number = 0;
+        loop {
+            number += 1;
+            let (line, next) = match rest.iter().position(|c| *c == b'\n') {
+                Some(at) => {
+                    let line = match rest[..at].strip_suffix(b"\r") {
+                        Some(line) => line,
+                        None => {
+                            head.warn(Deviation::BareLf, number);
+                            &rest[..at]
+                        }
+                    };
+                    (line, Some(&rest[at + 1..]))
+                }
+                None => (rest, None),
+            };
+            if number == 1 {
+                head.start_line(line);
+            } else {
+                head.bytes.extend_from_slice(line);
+            }
+            match next {
+                Some(next) => {
+                    head.bytes.extend_from_slice(CRLF);
+                    rest = next;
+                    if line.is_empty() && number > 1 {
+                        break;
+                    }
+                }
+                None => {
+                    if !line.is_empty() {
+                        head.bytes.extend_from_slice(CRLF);
+                    }
+                    head.bytes.extend_from_slice(CRLF);
+                    head.warn(Deviation::MissingFinalCrlf, number);
+                    rest = &rest[rest.len()..];
+                    break;
+                }
+            }
+        }
+        head.consumed = src.len() - rest.len();
+        head
+    }
+
+    // Each deviation is recorded once, on the line it is first seen
+    fn warn(&mut self, deviation: Deviation, line: usize) {
+        if !self.warnings.iter().any(|w| w.deviation == deviation) {
+            self.warnings.push(Warning { deviation, line });
+        }
+    }
+
+    fn start_line(&mut self, line: &[u8]) {
+        let is_ws = |c: &u8| *c == b' ' || *c == b'\t';
+        let is_version = |part: &[u8]| part.eq_ignore_ascii_case(SIP_VERSION);
+        let trimmed = line.trim_ascii_end();
+        let parts: Vec<&[u8]> = trimmed
+            .split(is_ws)
+            .filter(|part| !part.is_empty())
+            .collect();
+        match parts.as_slice() {
+            // Status-Line  =  SIP-Version SP Status-Code SP Reason-Phrase CRLF
+            // The reason phrase may hold whitespace of its own
+            [version, ..] if is_version(version) => {
+                let leading = line.iter().take_while(|c| is_ws(c)).count();
+                let rest = &line[leading + version.len()..];
+                let (sep, rest) = split_ws(rest);
+                let code_len = rest.iter().take_while(|c| !is_ws(c)).count();
+                let (code, rest) = rest.split_at(code_len);
+                let (reason_sep, reason) = split_ws(rest);
+                let trimmed_reason = reason.trim_ascii_end();
+                if leading > 0 || sep != b" " {
+                    self.warn(Deviation::StartLineWhitespace, 1);
+                }
+                // With no reason phrase, whatever follows the one SP trails
+                match (reason_sep, reason.is_empty()) {
+                    (b" ", _) => {}
+                    (b"", true) => self.warn(Deviation::StartLineWhitespace, 1),
+                    (_, true) => self.warn(Deviation::TrailingWhitespace, 1),
+                    (_, false) => self.warn(Deviation::StartLineWhitespace, 1),
+                }
+                if trimmed_reason.len() != reason.len() {
+                    self.warn(Deviation::TrailingWhitespace, 1);
+                }
+                self.version(version);
+                self.bytes.push(b' ');
+                self.bytes.extend_from_slice(code);
+                self.bytes.push(b' ');
+                self.bytes.extend_from_slice(trimmed_reason);
+            }
+            // Request-Line  =  Method SP Request-URI SP SIP-Version CRLF
+            [method, uri, version] if is_version(version) => {
+                if trimmed.len() != line.len() {
+                    self.warn(Deviation::TrailingWhitespace, 1);
+                }
+                if trimmed != [*method, *uri, *version].join(&b' ').as_slice() {
+                    self.warn(Deviation::StartLineWhitespace, 1);
+                }
+                self.bytes.extend_from_slice(method);
+                self.bytes.push(b' ');
+                self.bytes.extend_from_slice(uri);
+                self.bytes.push(b' ');
+                self.version(version);
+            }
+            // Not something leniency can help, the strict parser reports it
+            _ => self.bytes.extend_from_slice(line),
+        }
+    }
+
+    fn version(&mut self, version: &[u8]) {
+        if version != SIP_VERSION {
+            self.warn(Deviation::LowercaseVersion, 1);
+        }
+        self.bytes.extend_from_slice(SIP_VERSION);
+    }
+}
+
+fn split_ws(src: &[u8]) -> (&[u8], &[u8]) {
+    let at = src
+        .iter()
+        .take_while(|c| **c == b' ' || **c == b'\t')
+        .count();
+    src.split_at(at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(src: &[u8]) -> (String, Vec<Deviation>) {
+        let head = Head::normalize(src);
+        (
+            String::from_utf8(head.bytes).unwrap(),
+            head.warnings.iter().map(|w| w.deviation).collect(),
+        )
+    }
+
+    #[test]
+    fn strict_input_is_kept() {
+        let src = b"SIP/2.0 200 OK\r\nCall-ID: a\r\n\r\nbody";
+        let head = Head::normalize(src);
+        assert_eq!(&src[..30], head.bytes.as_slice());
+        assert_eq!(30, head.consumed);
+        assert!(head.warnings.is_empty());
+        let (_, warnings) = normalized(b"SIP/2.0 180 \r\n\r\n");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn request_line_works() {
+        assert_eq!(
+            (
+                "INVITE sip:a@b.com SIP/2.0\r\nTo: <sip:a@b.com>\r\n\r\n".to_owned(),
+                vec![
+                    Deviation::BareLf,
+                    Deviation::TrailingWhitespace,
+                    Deviation::StartLineWhitespace,
+                    Deviation::LowercaseVersion,
+                ]
+            ),
+            normalized(b"INVITE\tsip:a@b.com  sip/2.0 \nTo: <sip:a@b.com>\n\n")
+        );
+    }
+
+    #[test]
+    fn status_line_works() {
+        assert_eq!(
+            (
+                "SIP/2.0 200 OK  then\tsome\r\n\r\n".to_owned(),
+                vec![
+                    Deviation::StartLineWhitespace,
+                    Deviation::TrailingWhitespace,
+                    Deviation::LowercaseVersion,
+                ]
+            ),
+            normalized(b"Sip/2.0\t200 OK  then\tsome \t\r\n\r\n")
+        );
+        assert_eq!(
+            (
+                "SIP/2.0 100 \r\n\r\n".to_owned(),
+                vec![Deviation::TrailingWhitespace]
+            ),
+            normalized(b"SIP/2.0 100   \r\n\r\n")
+        );
+    }
+
+    #[test]
+    fn missing_final_crlf_works() {
+        let head = Head::normalize(b"BYE sip:b.com SIP/2.0\r\nCall-ID: a\r\n");
+        assert!(head.bytes.ends_with(b"Call-ID: a\r\n\r\n"));
+        assert_eq!(
+            vec![Warning {
+                deviation: Deviation::MissingFinalCrlf,
+                line: 3
+            }],
+            head.warnings
+        );
+        let head = Head::normalize(b"BYE sip:b.com SIP/2.0\r\nCall-ID: a");
+        assert!(head.bytes.ends_with(b"Call-ID: a\r\n\r\n"));
+        assert_eq!(1, head.warnings.len());
+    }
+}
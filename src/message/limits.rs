@@ -0,0 +1,136 @@
+use nom::error::ErrorKind;
+
+use crate::{
+    error::{Element, Error},
+    parse_utils::CRLF,
+};
+
+// Bounds on what a parser accepts from the network before giving up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    pub max_headers: usize,
+    pub max_line_length: usize,
+    pub max_body_size: usize,
+}
+
+impl Limits {
+    pub const DEFAULT: Self = Self {
+        max_headers: 256,
+        max_line_length: 8192,
+        max_body_size: 65535,
+    };
+
+    // No limits at all, for input that is already trusted
+    pub const NONE: Self = Self {
+        max_headers: usize::MAX,
+        max_line_length: usize::MAX,
+        max_body_size: usize::MAX,
+    };
+
+    // Checks the start line and headers ahead of parsing them, stopping at
+    // the empty line or the end of what has arrived so far
+    pub fn check_head(&self, src: &[u8]) -> Result<(), Error> {
+        let mut rest = src;
+        let mut headers = 0;
+        let mut start_line = true;
+        loop {
+            let (line, next) = match rest.windows(2).position(|w| w == CRLF) {
+                Some(at) => (&rest[..at], Some(&rest[at + 2..])),
+                None => (rest, None),
+            };
+            if line.is_empty() && next.is_some() {
+                return Ok(());
+            }
+            let too_large = || {
+                let element = if start_line {
+                    Element::StartLine
+                } else {
+                    Element::Header(header_name(line))
+                };
+                Err(Error::new(element, rest, ErrorKind::TooLarge).locate(src))
+            };
+            if line.len() > self.max_line_length {
+                return too_large();
+            }
+            // Folded lines continue the header above them
+            if !start_line && !line.starts_with(b" ") && !line.starts_with(b"\t") {
+                headers += 1;
+                if headers > self.max_headers {
+                    return too_large();
+                }
+            }
+            start_line = false;
+            match next {
+                Some(next) => rest = next,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    pub fn check_body(&self, content_length: usize, src: &[u8]) -> Result<(), Error> {
+        if content_length > self.max_body_size {
+            return Err(Error::new(Element::Body, src, ErrorKind::TooLarge));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+fn header_name(line: &[u8]) -> String {
+    let end = line
+        .iter()
+        .position(|c| *c == b':')
+        .unwrap_or(line.len())
+        .min(64);
+    String::from_utf8_lossy(line[..end].trim_ascii()).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        max_headers: 2,
+        max_line_length: 32,
+        max_body_size: 10,
+    };
+
+    #[test]
+    fn it_works() {
+        assert!(LIMITS
+            .check_head(b"BYE sip:x.com SIP/2.0\r\nCall-ID: x\r\nSubject: a\r\n b\r\n\r\n")
+            .is_ok());
+        // Whatever follows the empty line is the body
+        assert!(LIMITS
+            .check_head(b"BYE sip:x.com SIP/2.0\r\n\r\na\r\nb\r\nc\r\n")
+            .is_ok());
+        assert!(LIMITS.check_body(10, b"").is_ok());
+    }
+
+    #[test]
+    fn limits_are_enforced() {
+        let src = b"BYE sip:x.com SIP/2.0\r\nCall-ID: x\r\nCSeq: 1 BYE\r\nTo: <sip:x.com>\r\n\r\n";
+        let e = LIMITS.check_head(src).unwrap_err();
+        assert_eq!(ErrorKind::TooLarge, e.kind());
+        assert_eq!(Some(&Element::Header("To".to_owned())), e.element());
+        assert_eq!(Some(48), e.offset());
+
+        let e = LIMITS
+            .check_head(b"BYE sip:x.com SIP/2.0\r\nSubject: a long subject, too long\r\n")
+            .unwrap_err();
+        assert_eq!(Some(&Element::Header("Subject".to_owned())), e.element());
+        let e = LIMITS
+            .check_head(b"BYE sip:a-very-long-user@x.com SIP/2.0")
+            .unwrap_err();
+        assert_eq!(Some(&Element::StartLine), e.element());
+
+        let e = LIMITS.check_body(11, b"").unwrap_err();
+        assert_eq!(Some(&Element::Body), e.element());
+        assert!(Limits::NONE.check_body(usize::MAX, b"").is_ok());
+    }
+}
@@ -1,27 +1,63 @@
-use nom::IResult;
+use crate::parse_utils::{token, ParseResult};
 
-use crate::parse_utils::token;
-
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Method {
+    // RFC 3261
     Invite,
-    Unknown,
+    Ack,
+    Bye,
+    Cancel,
+    Register,
+    Options,
+    // RFC 3262
+    Prack,
+    // RFC 6665
+    Subscribe,
+    Notify,
+    // RFC 3903
+    Publish,
+    // RFC 6086
+    Info,
+    // RFC 3515
+    Refer,
+    // RFC 3428
+    Message,
+    // RFC 3311
+    Update,
+    // extension-method  =  token
+    Extension(String),
 }
 
 impl TryFrom<&[u8]> for Method {
     type Error = anyhow::Error;
 
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        // Method names are case-sensitive (RFC 3261 7.1)
         let s = std::str::from_utf8(value)?;
         match s {
             "INVITE" => Ok(Self::Invite),
-            _ => Ok(Self::Unknown),
+            "ACK" => Ok(Self::Ack),
+            "BYE" => Ok(Self::Bye),
+            "CANCEL" => Ok(Self::Cancel),
+            "REGISTER" => Ok(Self::Register),
+            "OPTIONS" => Ok(Self::Options),
+            "PRACK" => Ok(Self::Prack),
+            "SUBSCRIBE" => Ok(Self::Subscribe),
+            "NOTIFY" => Ok(Self::Notify),
+            "PUBLISH" => Ok(Self::Publish),
+            "INFO" => Ok(Self::Info),
+            "REFER" => Ok(Self::Refer),
+            "MESSAGE" => Ok(Self::Message),
+            "UPDATE" => Ok(Self::Update),
+            "" => Err(anyhow::anyhow!("empty method name")),
+            extension => Ok(Self::Extension(extension.to_owned())),
         }
     }
 }
 
 impl Method {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // Method  =  INVITEm / ACKm / OPTIONSm / BYEm / CANCELm / REGISTERm / extension-method
         let (rest, method_name) = token(src)?;
         match Self::try_from(method_name) {
             Ok(method) => Ok((rest, method)),
@@ -31,14 +67,85 @@ impl Method {
             ))),
         }
     }
-}
 
-impl ToString for Method {
-    fn to_string(&self) -> String {
+    pub fn as_str(&self) -> &str {
         match self {
             Method::Invite => "INVITE",
-            Method::Unknown => "Unknown",
+            Method::Ack => "ACK",
+            Method::Bye => "BYE",
+            Method::Cancel => "CANCEL",
+            Method::Register => "REGISTER",
+            Method::Options => "OPTIONS",
+            Method::Prack => "PRACK",
+            Method::Subscribe => "SUBSCRIBE",
+            Method::Notify => "NOTIFY",
+            Method::Publish => "PUBLISH",
+            Method::Info => "INFO",
+            Method::Refer => "REFER",
+            Method::Message => "MESSAGE",
+            Method::Update => "UPDATE",
+            Method::Extension(name) => name.as_str(),
+        }
+    }
+
+    pub fn is_extension(&self) -> bool {
+        matches!(self, Self::Extension(_))
+    }
+}
+
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_methods_work() {
+        for name in [
+            "INVITE",
+            "ACK",
+            "BYE",
+            "CANCEL",
+            "REGISTER",
+            "OPTIONS",
+            "PRACK",
+            "SUBSCRIBE",
+            "NOTIFY",
+            "PUBLISH",
+            "INFO",
+            "REFER",
+            "MESSAGE",
+            "UPDATE",
+        ] {
+            let (rest, method) = Method::parse(name.as_bytes()).unwrap();
+            assert!(rest.is_empty());
+            assert!(!method.is_extension());
+            assert_eq!(name, method.to_string());
         }
-        .to_owned()
+    }
+
+    #[test]
+    fn extension_round_trip() {
+        let (rest, method) = Method::parse(b"FOOBAR sip:x").unwrap();
+        assert_eq!(b" sip:x", rest);
+        assert_eq!(Method::Extension("FOOBAR".to_owned()), method);
+        assert_eq!("FOOBAR", method.to_string());
+    }
+
+    #[test]
+    fn methods_are_case_sensitive() {
+        let (_, method) = Method::parse(b"invite").unwrap();
+        assert_eq!(Method::Extension("invite".to_owned()), method);
+        assert_eq!("invite", method.to_string());
+    }
+
+    #[test]
+    fn empty_must_fail() {
+        assert!(Method::parse(b"").is_err());
+        assert!(Method::parse(b" INVITE").is_err());
     }
 }
@@ -1,17 +1,29 @@
+mod borrowed;
+mod builder;
 mod generic;
-mod header;
+pub mod header;
+mod lenient;
+mod limits;
 mod method;
+mod options;
 mod raw;
 pub mod start_line;
 mod status_code;
 mod uri;
+mod validate;
 
+pub use borrowed::*;
+pub use builder::*;
 pub use generic::*;
+pub use lenient::{Deviation, Warning};
+pub use limits::Limits;
 pub use method::*;
+pub use options::{Mode, ParseOptions};
 pub use raw::*;
 pub use start_line::StartLine;
 pub use status_code::*;
 pub use uri::*;
+pub use validate::Violation;
 
 #[cfg(test)]
 mod tests {
@@ -30,5 +42,172 @@ mod tests {
         };
         assert_eq!(Method::Invite, request_line.method);
         assert_eq!("sip:127.0.0.1:5060", request_line.uri.to_string());
+        assert!(request.content_type().unwrap().is("application", "sdp"));
+        assert_eq!(
+            Some(header::Handling::Required),
+            request.headers.content_disposition().map(|d| d.handling())
+        );
+    }
+
+    #[test]
+    fn message_round_trip() {
+        let data = b"SIP/2.0 180 Ringing\r\nVia: SIP/2.0/UDP server10.biloxi.com;branch=z9hG4bK4b43c2ff8.1;received=192.0.2.3, SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nTo: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\nCall-ID: a84b4c76e66710@pc33.atlanta.com\r\nCSeq: 314159 INVITE\r\nContent-Length: 4\r\n\r\nbody";
+        let (rest, response) = Message::parse(data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(data.as_slice(), response.to_bytes());
+    }
+
+    #[test]
+    fn content_length_follows_body() {
+        let data =
+            b"BYE sip:bob@192.0.2.4 SIP/2.0\r\nCall-ID: a84b4c76e66710\r\nCSeq: 231 BYE\r\n\r\n";
+        let (_, mut request) = Message::parse(data).unwrap();
+        request.body = b"hello".to_vec().into_boxed_slice();
+        assert_eq!(
+            b"BYE sip:bob@192.0.2.4 SIP/2.0\r\nCall-ID: a84b4c76e66710\r\nCSeq: 231 BYE\r\nContent-Length: 5\r\n\r\nhello".as_slice(),
+            request.to_bytes()
+        );
+    }
+
+    #[test]
+    fn datagrams_carry_their_body() {
+        let head = "BYE sip:bob@192.0.2.4 SIP/2.0\r\nCall-ID: a84b4c76e66710\r\nCSeq: 231 BYE\r\n";
+        let datagram = |length: &str| format!("{}{}\r\nhello", head, length);
+
+        // Extra bytes are dropped, missing ones left to `validate`
+        let request =
+            Message::parse_datagram(datagram("l: 3\r\n").as_bytes(), &ParseOptions::STRICT)
+                .unwrap();
+        assert_eq!(b"hel".as_slice(), &*request.body);
+        let short = datagram("Content-Length: 9\r\n");
+        assert!(Message::parse(short.as_bytes()).is_err());
+        let request = Message::parse_datagram(short.as_bytes(), &ParseOptions::LENIENT).unwrap();
+        assert_eq!(b"hello".as_slice(), &*request.body);
+        assert!(request
+            .validate()
+            .unwrap_err()
+            .iter()
+            .any(|violation| matches!(
+                violation,
+                Violation::ContentLengthMismatch { header: 9, body: 5 }
+            )));
+        // Without Content-Length the rest of the datagram is the body
+        let request =
+            Message::parse_datagram(datagram("").as_bytes(), &ParseOptions::STRICT).unwrap();
+        assert_eq!(b"hello".as_slice(), &*request.body);
+    }
+
+    #[test]
+    fn compact_form_message_works() {
+        let data = b"MESSAGE sip:user2@domain.com SIP/2.0\r\nv: SIP/2.0/TCP user1pc.domain.com;branch=z9hG4bK776sgdkse\r\nf: sip:user1@domain.com;tag=49583\r\nt: sip:user2@domain.com\r\ni: asd88asd77a@1.2.3.4\r\nCSeq: 1 MESSAGE\r\nc: text/plain\r\nl: 5\r\n\r\nHello";
+        let (rest, request) = Message::parse(data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(b"Hello".as_slice(), &*request.body);
+        assert!(request.headers.sip_sweet_six().is_none());
+        assert!(request.headers.call_id().is_some());
+        assert!(request.content_type().unwrap().is("text", "plain"));
+        assert_eq!(data.as_slice(), request.to_bytes());
+    }
+
+    #[test]
+    fn errors_point_at_element() {
+        use crate::error::Element;
+
+        let data = b"INVITE sip:bob@[::1 SIP/2.0\r\n\r\n";
+        let e = Message::parse(data).unwrap_err();
+        assert_eq!(&[Element::StartLine, Element::Uri], e.elements());
+        assert_eq!("Malformed start line", e.reason_phrase());
+
+        // Header values are parsed on access, with offsets into the message
+        let data = b"SIP/2.0 200 OK\r\nVia: SIP/2.0/UDP pc33.atlanta.com;ttl=1x\r\n\r\n";
+        let (_, response) = Message::parse(data).unwrap();
+        let e = response.headers.via().unwrap().value().unwrap_err();
+        assert_eq!(&[Element::Header("Via".to_owned())], e.elements());
+        assert_eq!(Some(55), e.offset());
+
+        let data = b"SIP/2.0 200 OK\r\nVia: SIP/2.0/UDP pc33.atlanta.com;=1\r\n\r\n";
+        let (_, response) = Message::parse(data).unwrap();
+        let e = response.headers.via().unwrap().value().unwrap_err();
+        assert_eq!(
+            &[Element::Header("Via".to_owned()), Element::ViaParam],
+            e.elements()
+        );
+        assert_eq!(Some(50), e.offset());
+        assert_eq!(
+            "malformed Via header, Via parameter at byte 50: value expected (RFC 3261 20.42)",
+            e.to_string()
+        );
+
+        let data = b"BYE sip:bob@192.0.2.4 SIP/2.0\r\nContent-Length: ten\r\n\r\n";
+        let e = Message::parse(data).unwrap_err();
+        assert_eq!(
+            Some(&Element::Header("Content-Length".to_owned())),
+            e.element()
+        );
+
+        let data = b"BYE sip:bob@192.0.2.4 SIP/2.0\r\nContent-Length: 10\r\n\r\nshort";
+        let e = Message::parse(data).unwrap_err();
+        assert_eq!(Some(&Element::Body), e.element());
+        assert_eq!(Some(data.len()), e.offset());
+    }
+
+    #[test]
+    fn lenient_mode_works() {
+        let data = b"REGISTER\tsip:registrar.biloxi.com sip/2.0 \nVia: SIP/2.0/UDP bobspc.biloxi.com:5060;branch=z9hG4bKnashds7\nMax-Forwards: 70\nTo: Bob <sip:bob@biloxi.com>\nFrom: Bob <sip:bob@biloxi.com>;tag=456248\nCall-ID: 843817637684230@998sdasdh09\nCSeq: 1826 REGISTER\nContent-Length: 5\n\nab\ncd";
+        assert!(Message::parse(data).is_err());
+
+        let (rest, request) = Message::parse_with_options(data, &ParseOptions::LENIENT).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            "REGISTER sip:registrar.biloxi.com SIP/2.0",
+            request.start_line.to_string()
+        );
+        assert_eq!(
+            Some(("Call-ID", "843817637684230@998sdasdh09".to_owned())),
+            request
+                .headers
+                .call_id()
+                .map(|h| (h.name().canonical(), h.value().unwrap().to_string()))
+        );
+        // The body is taken as it came
+        assert_eq!(b"ab\ncd", &*request.body);
+        assert_eq!(
+            vec![
+                Warning {
+                    deviation: Deviation::BareLf,
+                    line: 1
+                },
+                Warning {
+                    deviation: Deviation::TrailingWhitespace,
+                    line: 1
+                },
+                Warning {
+                    deviation: Deviation::StartLineWhitespace,
+                    line: 1
+                },
+                Warning {
+                    deviation: Deviation::LowercaseVersion,
+                    line: 1
+                },
+            ],
+            request.warnings
+        );
+        assert_eq!(Ok(()), request.validate());
+
+        let data = b"SIP/2.0 200 OK\r\nCall-ID: a\r\nContent-Length: 0\r\n";
+        assert!(Message::parse(data).is_err());
+        let (_, response) = Message::parse_with_options(data, &ParseOptions::LENIENT).unwrap();
+        assert_eq!(
+            "line 4: no empty line after the headers",
+            response.warnings[0].to_string()
+        );
+
+        // Strict input parses the same either way, without warnings
+        let data = b"SIP/2.0 180 Ringing\r\nCall-ID: a\r\nContent-Length: 0\r\n\r\n";
+        let (_, response) = Message::parse_with_options(data, &ParseOptions::LENIENT).unwrap();
+        assert!(response.warnings.is_empty());
+        assert_eq!(data.as_slice(), response.to_bytes());
+        let options = ParseOptions::default();
+        assert!(Message::parse_with_options(b"SIP/2.0 180 Ringing\n\n", &options).is_err());
     }
 }
@@ -0,0 +1,33 @@
+use super::Limits;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    // The ABNF of RFC 3261 and nothing else
+    Strict,
+    // Also takes the deviations in `Deviation`, recording a warning for each
+    Lenient,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParseOptions {
+    pub mode: Mode,
+    pub limits: Limits,
+}
+
+impl ParseOptions {
+    pub const STRICT: Self = Self {
+        mode: Mode::Strict,
+        limits: Limits::DEFAULT,
+    };
+
+    pub const LENIENT: Self = Self {
+        mode: Mode::Lenient,
+        limits: Limits::DEFAULT,
+    };
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self::STRICT
+    }
+}
@@ -4,7 +4,7 @@ mod status;
 pub use request::*;
 pub use status::*;
 
-use crate::parse_utils::ParseResult;
+use crate::{error::Element, parse_utils::ParseResult};
 
 #[derive(Debug)]
 pub enum StartLine {
@@ -13,14 +13,16 @@ pub enum StartLine {
 }
 
 impl StartLine {
-    pub fn parse(src: &[u8]) -> ParseResult<StartLine> {
-        match status::StatusLine::parse(src) {
-            Ok((rest, status_line)) => Ok((rest, StartLine::Status(status_line))),
-            Err(_) => {
-                let (rest, request_line) = RequestLine::parse(src)?;
-                Ok((rest, StartLine::Request(request_line)))
-            }
-        }
+    pub fn parse(src: &[u8]) -> ParseResult<'_, StartLine> {
+        // start-line  =  Request-Line / Status-Line
+        // Only a Status-Line begins with the SIP-Version, so report errors
+        // against the kind of line the input looks like
+        let line = if src.starts_with(b"SIP/") {
+            nom::combinator::map(StatusLine::parse, StartLine::Status)(src)
+        } else {
+            nom::combinator::map(RequestLine::parse, StartLine::Request)(src)
+        };
+        line.map_err(|e| e.map(|e| e.within(Element::StartLine)))
     }
 
     pub fn is_request(&self) -> bool {
@@ -34,14 +36,10 @@ impl StartLine {
 
 impl std::fmt::Display for StartLine {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                StartLine::Request(r) => r.to_string(),
-                StartLine::Status(s) => s.to_string(),
-            }
-        )
+        match self {
+            StartLine::Request(r) => write!(f, "{}", r),
+            StartLine::Status(s) => write!(f, "{}", s),
+        }
     }
 }
 
@@ -76,7 +74,7 @@ mod tests {
         let line = b"SIP/2.0 200 OK\r\n";
         let (rest, status_line) = StatusLine::parse(line).unwrap();
         assert!(rest.is_empty());
-        assert_eq!(200_u16, status_line.status_code.into());
+        assert_eq!(200_u16, u16::from(status_line.status_code));
         assert_eq!(b"OK", status_line.reason_phrase.as_ref());
     }
 
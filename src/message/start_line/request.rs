@@ -1,8 +1,9 @@
-use nom::{bytes::complete::tag, IResult};
+use nom::{bytes::complete::tag, error::ErrorKind};
 
 use crate::{
+    error::{within, Element, Error},
     message::{uri::Uri, Method},
-    parse_utils::{CRLF, SIP_VERSION, SP},
+    parse_utils::{ParseResult, CRLF, SIP_VERSION, SP},
 };
 
 #[derive(Debug)]
@@ -12,13 +13,20 @@ pub struct RequestLine {
 }
 
 impl RequestLine {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         // Request-Line  =  Method SP Request-URI SP SIP-Version CRLF
         nom::combinator::map(
             nom::sequence::tuple((
-                Method::parse,
-                nom::sequence::delimited(tag(SP), Uri::parse, tag(SP)),
-                tag(SIP_VERSION),
+                within(Element::Method, Method::parse),
+                nom::sequence::delimited(
+                    tag(SP),
+                    within(
+                        Element::Uri,
+                        nom::combinator::verify(Uri::parse, no_headers),
+                    ),
+                    tag(SP),
+                ),
+                within(Element::SipVersion, tag(SIP_VERSION)),
                 tag(CRLF),
             )),
             |(method, uri, _, _)| Self { method, uri },
@@ -26,14 +34,31 @@ impl RequestLine {
     }
 }
 
+impl RequestLine {
+    // From the method and Request-URI of a line already split, with errors
+    // located in the URI
+    pub fn from_parts(method: &str, uri: &str) -> Result<Self, Error> {
+        let method = Method::try_from(method.as_bytes())
+            .map_err(|_| Error::new(Element::Method, b"", ErrorKind::Tag))?;
+        let uri: Uri = uri.parse()?;
+        if !no_headers(&uri) {
+            return Err(Error::new(Element::Uri, b"", ErrorKind::Verify));
+        }
+        Ok(Self { method, uri })
+    }
+}
+
+fn no_headers(uri: &Uri) -> bool {
+    // Headers are not allowed in a Request-URI (RFC 3261 19.1.1, table 1)
+    match uri {
+        Uri::Sip(uri) | Uri::Sips(uri) => uri.headers.is_empty(),
+        _ => true,
+    }
+}
+
 impl std::fmt::Display for RequestLine {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} {} SIP/2.0",
-            self.method.to_string(),
-            self.uri.to_string()
-        )
+        write!(f, "{} {} SIP/2.0", self.method, self.uri)
     }
 }
 
@@ -49,4 +74,23 @@ mod tests {
         assert_eq!(Method::Invite, request_line.method);
         assert_eq!("sip:127.0.0.1:5060", request_line.uri.to_string());
     }
+
+    #[test]
+    fn extension_method_works() {
+        let line = b"FOOBAR sip:127.0.0.1:5060 SIP/2.0\r\n";
+        let (rest, request_line) = RequestLine::parse(line).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(Method::Extension("FOOBAR".to_owned()), request_line.method);
+        assert_eq!(
+            "FOOBAR sip:127.0.0.1:5060 SIP/2.0",
+            request_line.to_string()
+        );
+    }
+
+    #[test]
+    fn uri_headers_must_fail() {
+        let line = b"INVITE sip:user@example.com?Route=%3Csip:example.com%3E SIP/2.0\r\n";
+        let e = crate::error::Error::from(RequestLine::parse(line).unwrap_err());
+        assert_eq!(&[Element::Uri], e.elements());
+    }
 }
@@ -1,7 +1,8 @@
-use nom::{bytes::complete::tag, IResult};
+use nom::bytes::complete::tag;
 
+use crate::error::{within, Element};
 use crate::message::StatusCode;
-use crate::parse_utils::{CRLF, SIP_VERSION, SP};
+use crate::parse_utils::{ParseResult, CRLF, SIP_VERSION, SP};
 
 #[derive(Debug)]
 pub struct StatusLine {
@@ -9,11 +10,23 @@ pub struct StatusLine {
     pub reason_phrase: Box<[u8]>,
 }
 impl StatusLine {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
+    // With the canonical reason phrase for the code
+    pub fn new(status_code: StatusCode) -> Self {
+        Self {
+            status_code,
+            reason_phrase: status_code.canonical_reason().as_bytes().into(),
+        }
+    }
+
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         nom::combinator::map(
             nom::sequence::tuple((
-                tag(SIP_VERSION),
-                nom::sequence::delimited(tag(SP), StatusCode::parse, tag(SP)),
+                within(Element::SipVersion, tag(SIP_VERSION)),
+                nom::sequence::delimited(
+                    tag(SP),
+                    within(Element::StatusCode, StatusCode::parse),
+                    tag(SP),
+                ),
                 nom::bytes::complete::take_until(CRLF),
                 tag(CRLF),
             )),
@@ -27,12 +40,11 @@ impl StatusLine {
 
 impl std::fmt::Display for StatusLine {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let status_code: u16 = self.status_code.into();
         write!(
             f,
             "SIP/2.0 {} {}",
-            status_code,
-            std::str::from_utf8(&self.reason_phrase).unwrap()
+            self.status_code,
+            String::from_utf8_lossy(&self.reason_phrase)
         )
     }
 }
@@ -46,7 +58,14 @@ mod tests {
         let line = b"SIP/2.0 200 OK\r\n";
         let (rest, status_line) = StatusLine::parse(line).unwrap();
         assert!(rest.is_empty());
-        assert_eq!(200_u16, status_line.status_code.into());
+        assert_eq!(200_u16, u16::from(status_line.status_code));
         assert_eq!(b"OK", status_line.reason_phrase.as_ref());
     }
+
+    #[test]
+    fn new_works() {
+        let status_line = StatusLine::new(StatusCode::REQUEST_TERMINATED);
+        assert_eq!("SIP/2.0 487 Request Terminated", status_line.to_string());
+        assert!(StatusLine::parse(b"SIP/2.0 000 Zero\r\n").is_err());
+    }
 }
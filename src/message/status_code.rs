@@ -1,17 +1,251 @@
-use nom::IResult;
+use nom::bytes::complete::take_while_m_n;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::parse_utils::ParseResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct StatusCode {
     inner: u16,
 }
 
+// Codes registered with IANA in the Session Initiation Protocol (SIP)
+// Parameters registry, Response Codes
+impl StatusCode {
+    pub const TRYING: Self = Self { inner: 100 };
+    pub const RINGING: Self = Self { inner: 180 };
+    pub const CALL_IS_BEING_FORWARDED: Self = Self { inner: 181 };
+    pub const QUEUED: Self = Self { inner: 182 };
+    pub const SESSION_PROGRESS: Self = Self { inner: 183 };
+    pub const EARLY_DIALOG_TERMINATED: Self = Self { inner: 199 };
+    pub const OK: Self = Self { inner: 200 };
+    pub const ACCEPTED: Self = Self { inner: 202 };
+    pub const NO_NOTIFICATION: Self = Self { inner: 204 };
+    pub const MULTIPLE_CHOICES: Self = Self { inner: 300 };
+    pub const MOVED_PERMANENTLY: Self = Self { inner: 301 };
+    pub const MOVED_TEMPORARILY: Self = Self { inner: 302 };
+    pub const USE_PROXY: Self = Self { inner: 305 };
+    pub const ALTERNATIVE_SERVICE: Self = Self { inner: 380 };
+    pub const BAD_REQUEST: Self = Self { inner: 400 };
+    pub const UNAUTHORIZED: Self = Self { inner: 401 };
+    pub const PAYMENT_REQUIRED: Self = Self { inner: 402 };
+    pub const FORBIDDEN: Self = Self { inner: 403 };
+    pub const NOT_FOUND: Self = Self { inner: 404 };
+    pub const METHOD_NOT_ALLOWED: Self = Self { inner: 405 };
+    pub const NOT_ACCEPTABLE: Self = Self { inner: 406 };
+    pub const PROXY_AUTHENTICATION_REQUIRED: Self = Self { inner: 407 };
+    pub const REQUEST_TIMEOUT: Self = Self { inner: 408 };
+    pub const GONE: Self = Self { inner: 410 };
+    pub const CONDITIONAL_REQUEST_FAILED: Self = Self { inner: 412 };
+    pub const REQUEST_ENTITY_TOO_LARGE: Self = Self { inner: 413 };
+    pub const REQUEST_URI_TOO_LONG: Self = Self { inner: 414 };
+    pub const UNSUPPORTED_MEDIA_TYPE: Self = Self { inner: 415 };
+    pub const UNSUPPORTED_URI_SCHEME: Self = Self { inner: 416 };
+    pub const UNKNOWN_RESOURCE_PRIORITY: Self = Self { inner: 417 };
+    pub const BAD_EXTENSION: Self = Self { inner: 420 };
+    pub const EXTENSION_REQUIRED: Self = Self { inner: 421 };
+    pub const SESSION_INTERVAL_TOO_SMALL: Self = Self { inner: 422 };
+    pub const INTERVAL_TOO_BRIEF: Self = Self { inner: 423 };
+    pub const BAD_LOCATION_INFORMATION: Self = Self { inner: 424 };
+    pub const BAD_ALERT_MESSAGE: Self = Self { inner: 425 };
+    pub const USE_IDENTITY_HEADER: Self = Self { inner: 428 };
+    pub const PROVIDE_REFERRER_IDENTITY: Self = Self { inner: 429 };
+    pub const FLOW_FAILED: Self = Self { inner: 430 };
+    pub const ANONYMITY_DISALLOWED: Self = Self { inner: 433 };
+    pub const BAD_IDENTITY_INFO: Self = Self { inner: 436 };
+    pub const UNSUPPORTED_CREDENTIAL: Self = Self { inner: 437 };
+    pub const INVALID_IDENTITY_HEADER: Self = Self { inner: 438 };
+    pub const FIRST_HOP_LACKS_OUTBOUND_SUPPORT: Self = Self { inner: 439 };
+    pub const MAX_BREADTH_EXCEEDED: Self = Self { inner: 440 };
+    pub const BAD_INFO_PACKAGE: Self = Self { inner: 469 };
+    pub const CONSENT_NEEDED: Self = Self { inner: 470 };
+    pub const TEMPORARILY_UNAVAILABLE: Self = Self { inner: 480 };
+    pub const CALL_OR_TRANSACTION_DOES_NOT_EXIST: Self = Self { inner: 481 };
+    pub const LOOP_DETECTED: Self = Self { inner: 482 };
+    pub const TOO_MANY_HOPS: Self = Self { inner: 483 };
+    pub const ADDRESS_INCOMPLETE: Self = Self { inner: 484 };
+    pub const AMBIGUOUS: Self = Self { inner: 485 };
+    pub const BUSY_HERE: Self = Self { inner: 486 };
+    pub const REQUEST_TERMINATED: Self = Self { inner: 487 };
+    pub const NOT_ACCEPTABLE_HERE: Self = Self { inner: 488 };
+    pub const BAD_EVENT: Self = Self { inner: 489 };
+    pub const REQUEST_PENDING: Self = Self { inner: 491 };
+    pub const UNDECIPHERABLE: Self = Self { inner: 493 };
+    pub const SECURITY_AGREEMENT_REQUIRED: Self = Self { inner: 494 };
+    pub const SERVER_INTERNAL_ERROR: Self = Self { inner: 500 };
+    pub const NOT_IMPLEMENTED: Self = Self { inner: 501 };
+    pub const BAD_GATEWAY: Self = Self { inner: 502 };
+    pub const SERVICE_UNAVAILABLE: Self = Self { inner: 503 };
+    pub const SERVER_TIMEOUT: Self = Self { inner: 504 };
+    pub const VERSION_NOT_SUPPORTED: Self = Self { inner: 505 };
+    pub const MESSAGE_TOO_LARGE: Self = Self { inner: 513 };
+    pub const PUSH_NOTIFICATION_SERVICE_NOT_SUPPORTED: Self = Self { inner: 555 };
+    pub const PRECONDITION_FAILURE: Self = Self { inner: 580 };
+    pub const BUSY_EVERYWHERE: Self = Self { inner: 600 };
+    pub const DECLINE: Self = Self { inner: 603 };
+    pub const DOES_NOT_EXIST_ANYWHERE: Self = Self { inner: 604 };
+    pub const NOT_ACCEPTABLE_ANYWHERE: Self = Self { inner: 606 };
+    pub const UNWANTED: Self = Self { inner: 607 };
+    pub const REJECTED: Self = Self { inner: 608 };
+}
+
+impl StatusCode {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // Status-Code  =  3DIGIT, the first digit being the class
+        let (rest, digits) = take_while_m_n(3, 3, |t: u8| t.is_ascii_digit())(src)?;
+        match Self::try_from(digits) {
+            Ok(code) if !rest.first().is_some_and(u8::is_ascii_digit) => Ok((rest, code)),
+            _ => Err(nom::Err::Error(nom::error::make_error(
+                src,
+                nom::error::ErrorKind::Verify,
+            ))),
+        }
+    }
+
+    pub fn new(code: u16) -> Option<Self> {
+        (100..=699).contains(&code).then_some(Self { inner: code })
+    }
+
+    pub fn as_u16(&self) -> u16 {
+        self.inner
+    }
+
+    // 1xx
+    pub fn is_provisional(&self) -> bool {
+        self.inner < 200
+    }
+
+    // 2xx
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.inner)
+    }
+
+    // 3xx
+    pub fn is_redirect(&self) -> bool {
+        (300..400).contains(&self.inner)
+    }
+
+    // 4xx
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.inner)
+    }
+
+    // 5xx
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.inner)
+    }
+
+    // 6xx
+    pub fn is_global_failure(&self) -> bool {
+        self.inner >= 600
+    }
+
+    // Any final response
+    pub fn is_final(&self) -> bool {
+        !self.is_provisional()
+    }
+
+    // The phrase for a registered code, or for its class otherwise
+    // (RFC 3261 21: an unknown code is treated as x00 of its class)
+    pub fn canonical_reason(&self) -> &'static str {
+        match self.inner {
+            100 => "Trying",
+            180 => "Ringing",
+            181 => "Call Is Being Forwarded",
+            182 => "Queued",
+            183 => "Session Progress",
+            199 => "Early Dialog Terminated",
+            200 => "OK",
+            202 => "Accepted",
+            204 => "No Notification",
+            300 => "Multiple Choices",
+            301 => "Moved Permanently",
+            302 => "Moved Temporarily",
+            305 => "Use Proxy",
+            380 => "Alternative Service",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            402 => "Payment Required",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            407 => "Proxy Authentication Required",
+            408 => "Request Timeout",
+            410 => "Gone",
+            412 => "Conditional Request Failed",
+            413 => "Request Entity Too Large",
+            414 => "Request-URI Too Long",
+            415 => "Unsupported Media Type",
+            416 => "Unsupported URI Scheme",
+            417 => "Unknown Resource-Priority",
+            420 => "Bad Extension",
+            421 => "Extension Required",
+            422 => "Session Interval Too Small",
+            423 => "Interval Too Brief",
+            424 => "Bad Location Information",
+            425 => "Bad Alert Message",
+            428 => "Use Identity Header",
+            429 => "Provide Referrer Identity",
+            430 => "Flow Failed",
+            433 => "Anonymity Disallowed",
+            436 => "Bad Identity-Info",
+            437 => "Unsupported Credential",
+            438 => "Invalid Identity Header",
+            439 => "First Hop Lacks Outbound Support",
+            440 => "Max-Breadth Exceeded",
+            469 => "Bad Info Package",
+            470 => "Consent Needed",
+            480 => "Temporarily Unavailable",
+            481 => "Call/Transaction Does Not Exist",
+            482 => "Loop Detected",
+            483 => "Too Many Hops",
+            484 => "Address Incomplete",
+            485 => "Ambiguous",
+            486 => "Busy Here",
+            487 => "Request Terminated",
+            488 => "Not Acceptable Here",
+            489 => "Bad Event",
+            491 => "Request Pending",
+            493 => "Undecipherable",
+            494 => "Security Agreement Required",
+            500 => "Server Internal Error",
+            501 => "Not Implemented",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Server Time-out",
+            505 => "Version Not Supported",
+            513 => "Message Too Large",
+            555 => "Push Notification Service Not Supported",
+            580 => "Precondition Failure",
+            600 => "Busy Everywhere",
+            603 => "Decline",
+            604 => "Does Not Exist Anywhere",
+            606 => "Not Acceptable",
+            607 => "Unwanted",
+            608 => "Rejected",
+            _ => Self {
+                inner: self.inner / 100 * 100,
+            }
+            .canonical_reason(),
+        }
+    }
+}
+
 impl TryFrom<&[u8]> for StatusCode {
     type Error = anyhow::Error;
 
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
         let s = std::str::from_utf8(value)?;
-        let inner = s.parse()?;
-        Ok(Self { inner })
+        let code: u16 = s.parse()?;
+        Self::try_from(code)
+    }
+}
+
+impl TryFrom<u16> for StatusCode {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Self::new(value)
+            .ok_or_else(|| anyhow::anyhow!("status code {} is not within 100-699", value))
     }
 }
 
@@ -21,16 +255,9 @@ impl From<StatusCode> for u16 {
     }
 }
 
-impl StatusCode {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
-        let (rest, digits) = nom::bytes::complete::take_while(|t: u8| t.is_ascii_digit())(src)?;
-        match Self::try_from(digits) {
-            Ok(code) => Ok((rest, code)),
-            Err(_) => Err(nom::Err::Error(nom::error::make_error(
-                src,
-                nom::error::ErrorKind::Fail,
-            ))),
-        }
+impl std::fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.inner)
     }
 }
 
@@ -41,6 +268,75 @@ mod tests {
     #[test]
     fn it_works() {
         let code = StatusCode::try_from(b"200".as_slice()).unwrap();
-        assert_eq!(200_u16, code.into())
+        assert_eq!(200_u16, u16::from(code));
+        assert_eq!(StatusCode::OK, code);
+    }
+
+    #[test]
+    fn out_of_range_must_fail() {
+        for code in [0, 99, 700, 999] {
+            assert!(StatusCode::new(code).is_none());
+            assert!(StatusCode::try_from(code).is_err());
+        }
+        assert!(StatusCode::try_from(b"99999".as_slice()).is_err());
+        assert!(StatusCode::parse(b"099 Nope").is_err());
+        assert!(StatusCode::parse(b"4294967301 Nope").is_err());
+        assert!(StatusCode::parse(b"20 OK").is_err());
+        let (rest, code) = StatusCode::parse(b"699 Custom").unwrap();
+        assert_eq!(b" Custom", rest);
+        assert_eq!(699, code.as_u16());
+    }
+
+    #[test]
+    fn classes_work() {
+        assert!(StatusCode::TRYING.is_provisional());
+        assert!(!StatusCode::TRYING.is_final());
+        assert!(StatusCode::OK.is_success());
+        assert!(StatusCode::MOVED_TEMPORARILY.is_redirect());
+        assert!(StatusCode::TOO_MANY_HOPS.is_client_error());
+        assert!(StatusCode::SERVICE_UNAVAILABLE.is_server_error());
+        assert!(StatusCode::DECLINE.is_global_failure());
+        assert!(StatusCode::DECLINE.is_final());
+        let classes = |code: StatusCode| {
+            [
+                code.is_provisional(),
+                code.is_success(),
+                code.is_redirect(),
+                code.is_client_error(),
+                code.is_server_error(),
+                code.is_global_failure(),
+            ]
+            .iter()
+            .filter(|is| **is)
+            .count()
+        };
+        assert!((100..=699).all(|code| classes(StatusCode::new(code).unwrap()) == 1));
+    }
+
+    #[test]
+    fn canonical_reason_works() {
+        assert_eq!("OK", StatusCode::OK.canonical_reason());
+        assert_eq!(
+            "Call/Transaction Does Not Exist",
+            StatusCode::CALL_OR_TRANSACTION_DOES_NOT_EXIST.canonical_reason()
+        );
+        assert_eq!(
+            "Not Acceptable",
+            StatusCode::NOT_ACCEPTABLE_ANYWHERE.canonical_reason()
+        );
+        // Unknown codes fall back to their class
+        assert_eq!(
+            "Session Progress",
+            StatusCode::new(183).unwrap().canonical_reason()
+        );
+        assert_eq!("Trying", StatusCode::new(189).unwrap().canonical_reason());
+        assert_eq!(
+            "Bad Request",
+            StatusCode::new(499).unwrap().canonical_reason()
+        );
+        assert_eq!(
+            "Busy Everywhere",
+            StatusCode::new(699).unwrap().canonical_reason()
+        );
     }
 }
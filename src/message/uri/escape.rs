@@ -0,0 +1,122 @@
+// Percent-encoding of the userinfo, parameter and header parts of a SIP URI
+
+// reserved  =  ";" / "/" / "?" / ":" / "@" / "&" / "=" / "+" / "$" / ","
+const RESERVED: &[u8] = b";/?:@&=+$,";
+
+fn is_unreserved(x: u8) -> bool {
+    // unreserved  =  alphanum / mark
+    // mark        =  "-" / "_" / "." / "!" / "~" / "*" / "'" / "(" / ")"
+    x.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&x)
+}
+
+pub(crate) fn is_user_char(x: u8) -> bool {
+    // user-unreserved  =  "&" / "=" / "+" / "$" / "," / ";" / "?" / "/"
+    is_unreserved(x) || b"&=+$,;?/".contains(&x)
+}
+
+pub(crate) fn is_password_char(x: u8) -> bool {
+    // password  =  *( unreserved / escaped / "&" / "=" / "+" / "$" / "," )
+    is_unreserved(x) || b"&=+$,".contains(&x)
+}
+
+pub(crate) fn is_param_char(x: u8) -> bool {
+    // param-unreserved  =  "[" / "]" / "/" / ":" / "&" / "+" / "$"
+    is_unreserved(x) || b"[]/:&+$".contains(&x)
+}
+
+pub(crate) fn is_header_char(x: u8) -> bool {
+    // hnv-unreserved  =  "[" / "]" / "/" / "?" / ":" / "+" / "$"
+    is_unreserved(x) || b"[]/?:+$".contains(&x)
+}
+
+// Replaces every escape with the octet it stands for
+pub fn decode(s: &str) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(s.len());
+    let mut bytes = s.as_bytes();
+    while let Some((&c, rest)) = bytes.split_first() {
+        match escaped(bytes) {
+            Some(x) => {
+                decoded.push(x);
+                bytes = &bytes[3..];
+            }
+            None => {
+                decoded.push(c);
+                bytes = rest;
+            }
+        }
+    }
+    decoded
+}
+
+pub fn decode_lossy(s: &str) -> String {
+    String::from_utf8_lossy(&decode(s)).into_owned()
+}
+
+// Escapes only what the part cannot carry as is, with upper case hex
+// digits. An escaped reserved character is not the same as the character
+// itself (RFC 3261 19.1.4), so it stays escaped
+pub(crate) fn canonical(s: &str, allowed: fn(u8) -> bool) -> String {
+    let mut canonical = String::with_capacity(s.len());
+    let mut bytes = s.as_bytes();
+    while let Some((&c, rest)) = bytes.split_first() {
+        let (x, was_escaped) = match escaped(bytes) {
+            Some(x) => {
+                bytes = &bytes[3..];
+                (x, true)
+            }
+            None => {
+                bytes = rest;
+                (c, false)
+            }
+        };
+        if allowed(x) && !(was_escaped && RESERVED.contains(&x)) {
+            canonical.push(x as char);
+        } else {
+            canonical.push_str(&format!("%{:02X}", x));
+        }
+    }
+    canonical
+}
+
+fn escaped(bytes: &[u8]) -> Option<u8> {
+    // escaped  =  "%" HEXDIG HEXDIG
+    match bytes {
+        [b'%', hi, lo, ..] if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {
+            let hex = [*hi, *lo];
+            u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_works() {
+        assert_eq!(b"user".to_vec(), decode("%75se%72"));
+        assert_eq!(b"null-\0-null".to_vec(), decode("null-%00-null"));
+        assert_eq!(b"100%".to_vec(), decode("100%"));
+        assert_eq!(b"%4".to_vec(), decode("%4"));
+        assert_eq!("I have spaces", decode_lossy("I%20have%20spaces"));
+    }
+
+    #[test]
+    fn canonical_works() {
+        assert_eq!("user", canonical("%75se%72", is_user_char));
+        assert_eq!(
+            "sips%3Auser%40example.com",
+            canonical("sips%3auser%40example.com", is_user_char)
+        );
+        // Reserved characters keep whichever form they came in
+        assert_eq!("a;b%3Bc", canonical("a;b%3Bc", is_user_char));
+        assert_eq!(
+            "I%20have%20spaces",
+            canonical("I have spaces", is_user_char)
+        );
+        assert_eq!("value%2541", canonical("v%61lue%25%34%31", is_param_char));
+        assert_eq!("%D0%BC", canonical("м", is_header_char));
+        assert_eq!("100%25", canonical("100%", is_password_char));
+    }
+}
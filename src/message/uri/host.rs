@@ -0,0 +1,219 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::parse_utils::ParseResult;
+use nom::{
+    bytes::complete::{tag, take_while1},
+    sequence::delimited,
+};
+
+#[derive(Debug, Clone, Eq)]
+pub enum Host {
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr),
+    Domain(String),
+}
+
+impl Host {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // host             =  hostname / IPv4address / IPv6reference
+        match src.first() {
+            Some(b'[') => parse_ipv6_reference(src),
+            Some(_) => parse_ipv4_address(src).or_else(|_| parse_hostname(src)),
+            None => Err(nom::Err::Error(nom::error::make_error(
+                src,
+                nom::error::ErrorKind::Eof,
+            ))),
+        }
+    }
+
+    pub fn parse_ip(src: &[u8]) -> ParseResult<'_, Self> {
+        // via-received  =  "received" EQUAL (IPv4address / IPv6address)
+        // RFC 5118 4.5: accept the bracketed IPv6reference as well
+        match src.first() {
+            Some(b'[') => parse_ipv6_reference(src),
+            _ => parse_ipv4_address(src).or_else(|_| parse_ipv6_address(src)),
+        }
+    }
+
+    pub fn ip(&self) -> Option<IpAddr> {
+        match self {
+            Host::Ipv4(ip) => Some(IpAddr::V4(*ip)),
+            Host::Ipv6(ip) => Some(IpAddr::V6(*ip)),
+            Host::Domain(_) => None,
+        }
+    }
+
+    pub fn is_domain(&self) -> bool {
+        matches!(self, Self::Domain(_))
+    }
+}
+
+impl From<IpAddr> for Host {
+    fn from(ip: IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(ip) => Self::Ipv4(ip),
+            IpAddr::V6(ip) => Self::Ipv6(ip),
+        }
+    }
+}
+
+impl std::fmt::Display for Host {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Host::Ipv4(ip) => write!(f, "{}", ip),
+            Host::Ipv6(ip) => write!(f, "[{}]", ip),
+            Host::Domain(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl PartialEq for Host {
+    fn eq(&self, other: &Self) -> bool {
+        // Host names are case-insensitive (RFC 3261 19.1.4)
+        match (self, other) {
+            (Host::Ipv4(a), Host::Ipv4(b)) => a == b,
+            (Host::Ipv6(a), Host::Ipv6(b)) => a == b,
+            (Host::Domain(a), Host::Domain(b)) => a.eq_ignore_ascii_case(b),
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq<&str> for Host {
+    fn eq(&self, other: &&str) -> bool {
+        match Host::parse(other.as_bytes()) {
+            Ok((b"", host)) => *self == host,
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq<Host> for &str {
+    fn eq(&self, other: &Host) -> bool {
+        other == self
+    }
+}
+
+fn parse_ipv6_reference(src: &[u8]) -> ParseResult<'_, Host> {
+    // IPv6reference  =  "[" IPv6address "]"
+    delimited(tag(b"["), parse_ipv6_address, tag(b"]"))(src)
+}
+
+fn parse_ipv6_address(src: &[u8]) -> ParseResult<'_, Host> {
+    // IPv6address    =  hexpart [ ":" IPv4address ]
+    // hexpart        =  hexseq / hexseq "::" [ hexseq ] / "::" [ hexseq ]
+    let (rest, address) =
+        take_while1(|x: u8| x.is_ascii_hexdigit() || x == b':' || x == b'.')(src)?;
+    std::str::from_utf8(address)
+        .ok()
+        .and_then(|address| address.parse().ok())
+        .map(|ip| (rest, Host::Ipv6(ip)))
+        .ok_or_else(|| nom::Err::Error(nom::error::make_error(src, nom::error::ErrorKind::Verify)))
+}
+
+fn parse_ipv4_address(src: &[u8]) -> ParseResult<'_, Host> {
+    // IPv4address    =  1*3DIGIT "." 1*3DIGIT "." 1*3DIGIT "." 1*3DIGIT
+    let (rest, address) = take_while1(|x: u8| x.is_ascii_digit() || x == b'.')(src)?;
+    let followed_by_label = rest
+        .first()
+        .is_some_and(|c| c.is_ascii_alphanumeric() || *c == b'-');
+    std::str::from_utf8(address)
+        .ok()
+        .filter(|_| !followed_by_label)
+        .and_then(|address| address.parse().ok())
+        .map(|ip| (rest, Host::Ipv4(ip)))
+        .ok_or_else(|| nom::Err::Error(nom::error::make_error(src, nom::error::ErrorKind::Verify)))
+}
+
+fn parse_hostname(src: &[u8]) -> ParseResult<'_, Host> {
+    // hostname         =  *( domainlabel "." ) toplabel [ "." ]
+    // domainlabel      =  alphanum
+    //                     / alphanum *( alphanum / "-" ) alphanum
+    // toplabel         =  ALPHA / ALPHA *( alphanum / "-" ) alphanum
+    let (rest, name) =
+        take_while1(|x: u8| x.is_ascii_alphanumeric() || x == b'-' || x == b'.')(src)?;
+    let fail = || nom::Err::Error(nom::error::make_error(src, nom::error::ErrorKind::Verify));
+    let labels = name.strip_suffix(b".").unwrap_or(name);
+    let mut labels = labels.split(|c| *c == b'.').rev();
+    let toplabel = labels.next().ok_or_else(fail)?;
+    if !toplabel.first().is_some_and(u8::is_ascii_alphabetic) || !is_label(toplabel) {
+        return Err(fail());
+    }
+    if !labels.all(is_label) {
+        return Err(fail());
+    }
+    Ok((
+        rest,
+        Host::Domain(String::from_utf8_lossy(name).into_owned()),
+    ))
+}
+
+fn is_label(label: &[u8]) -> bool {
+    match (label.first(), label.last()) {
+        (Some(first), Some(last)) => {
+            first.is_ascii_alphanumeric()
+                && last.is_ascii_alphanumeric()
+                && label
+                    .iter()
+                    .all(|c| c.is_ascii_alphanumeric() || *c == b'-')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ipv4_works() {
+        let (rest, host) = Host::parse(b"192.0.2.4:5060").unwrap();
+        assert_eq!(b":5060", rest);
+        assert_eq!(Host::Ipv4(Ipv4Addr::new(192, 0, 2, 4)), host);
+        assert_eq!("192.0.2.4", host.to_string());
+    }
+
+    #[test]
+    fn ipv6_works() {
+        let (rest, host) = Host::parse(b"[2001:db8::1]:5060").unwrap();
+        assert_eq!(b":5060", rest);
+        assert_eq!(Host::Ipv6("2001:db8::1".parse().unwrap()), host);
+        assert_eq!("[2001:db8::1]", host.to_string());
+
+        let (rest, host) = Host::parse(b"[::ffff:192.0.2.1];transport=tcp").unwrap();
+        assert_eq!(b";transport=tcp", rest);
+        assert_eq!("[::ffff:192.0.2.1]", host.to_string());
+    }
+
+    #[test]
+    fn hostname_works() {
+        let (rest, host) = Host::parse(b"Atlanta.example.COM;lr").unwrap();
+        assert_eq!(b";lr", rest);
+        assert_eq!("atlanta.example.com", host);
+        assert_eq!("Atlanta.example.COM", host.to_string());
+
+        let (_, host) = Host::parse(b"1.2.3.4.example.com").unwrap();
+        assert!(host.is_domain());
+        let (_, host) = Host::parse(b"example.com.").unwrap();
+        assert!(host.is_domain());
+    }
+
+    #[test]
+    fn bad_hosts_must_fail() {
+        assert!(Host::parse(b"").is_err());
+        assert!(Host::parse(b"[2001:db8::1").is_err());
+        assert!(Host::parse(b"[example.com]").is_err());
+        assert!(Host::parse(b"example.123").is_err());
+        assert!(Host::parse(b"-example.com").is_err());
+        assert!(Host::parse(b"example..com").is_err());
+        assert!(Host::parse(b"256.0.0.1").is_err());
+    }
+
+    #[test]
+    fn bare_ipv6_received_works() {
+        let (rest, host) = Host::parse_ip(b"2001:db8::9:255;branch=z9hG4bK").unwrap();
+        assert_eq!(b";branch=z9hG4bK", rest);
+        assert_eq!(Host::Ipv6("2001:db8::9:255".parse().unwrap()), host);
+        assert!(Host::parse_ip(b"example.com").is_err());
+    }
+}
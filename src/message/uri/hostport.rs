@@ -3,51 +3,72 @@ use nom::{
     character::is_digit,
     combinator::map,
     sequence::tuple,
-    IResult, ParseTo,
+    ParseTo,
 };
 
-use crate::parse_utils::parse_host;
+use super::host::Host;
+use crate::parse_utils::{parse_host, ParseResult};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct HostPort {
-    pub hostname: String,
+    pub host: Host,
     pub port: Option<u16>,
 }
 
 impl HostPort {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
-        map(tuple((parse_host, parse_port)), |(hostname, port)| Self {
-            hostname,
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // hostport  =  host [ ":" port ]
+        map(tuple((parse_host, parse_port)), |(host, port)| Self {
+            host,
             port,
         })(src)
     }
 }
 
-impl ToString for HostPort {
-    fn to_string(&self) -> String {
-        format!(
-            "{}{}",
-            self.hostname,
-            if let Some(port) = self.port {
-                format!(":{}", port)
-            } else {
-                String::new()
-            }
-        )
+impl std::fmt::Display for HostPort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.host)?;
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        Ok(())
     }
 }
 
-fn parse_port(src: &[u8]) -> IResult<&[u8], Option<u16>> {
+fn parse_port(src: &[u8]) -> ParseResult<'_, Option<u16>> {
     let Ok((rest, _)) = tag::<_, _, ()>(b":")(src) else {
         return Ok((src, None));
     };
     let (rest, port) = take_while(is_digit)(rest)?;
-    if port.is_empty() {
-        Err(nom::Err::Error(nom::error::make_error(
+    match port.parse_to() {
+        Some(port) => Ok((rest, Some(port))),
+        None => Err(nom::Err::Error(nom::error::make_error(
             src,
             nom::error::ErrorKind::Fail,
-        )))
-    } else {
-        Ok((rest, port.parse_to()))
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let (rest, hostport) = HostPort::parse(b"[2001:db8::1]:5060;lr").unwrap();
+        assert_eq!(b";lr", rest);
+        assert_eq!(Some(5060), hostport.port);
+        assert_eq!("[2001:db8::1]:5060", hostport.to_string());
+
+        let (rest, hostport) = HostPort::parse(b"example.com>").unwrap();
+        assert_eq!(b">", rest);
+        assert_eq!(None, hostport.port);
+        assert_eq!("example.com", hostport.host);
+    }
+
+    #[test]
+    fn bad_port_must_fail() {
+        assert!(HostPort::parse(b"example.com:").is_err());
+        assert!(HostPort::parse(b"example.com:65536").is_err());
     }
 }
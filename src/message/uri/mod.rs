@@ -1,5 +1,9 @@
+pub mod escape;
+pub mod host;
+use crate::parse_utils::ParseResult;
 pub mod hostport;
 pub mod sipuri;
+pub mod teluri;
 pub mod transportparam;
 pub mod uriheader;
 pub mod uriparameter;
@@ -7,57 +11,113 @@ pub mod uripart;
 pub mod userinfo;
 pub mod userparam;
 
-use nom::{branch::alt, bytes::complete::tag, IResult};
+pub use host::Host;
+
 use sipuri::SipUri;
+use teluri::TelUri;
 use uripart::UriPart;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Uri {
     Sip(SipUri),
     Sips(SipUri),
+    Tel(TelUri),
     Absolute { scheme: String, uri: UriPart },
 }
 
-const SIP: &[u8] = b"sip:";
-const SIPS: &[u8] = b"sips:";
-
 impl Uri {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
-        let (rest, tag) = alt((tag(SIP), tag(SIPS), parse_scheme))(src)?;
-        match tag {
-            SIP => {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // Request-URI  =  SIP-URI / SIPS-URI / absoluteURI
+        // Schemes are case-insensitive (RFC 3986 3.1)
+        let (rest, scheme) = parse_scheme(src)?;
+        match scheme.to_ascii_lowercase().as_slice() {
+            b"sip" => {
                 let (rest, uri) = SipUri::parse(rest)?;
                 Ok((rest, Self::Sip(uri)))
             }
-            SIPS => {
+            b"sips" => {
                 let (rest, uri) = SipUri::parse(rest)?;
                 Ok((rest, Self::Sips(uri)))
             }
-            _ => todo!(),
+            b"tel" => {
+                let (rest, uri) = TelUri::parse(rest)?;
+                Ok((rest, Self::Tel(uri)))
+            }
+            _ => {
+                let (rest, uri) = UriPart::parse(rest)?;
+                let scheme = String::from_utf8_lossy(scheme).into_owned();
+                Ok((rest, Self::Absolute { scheme, uri }))
+            }
         }
     }
-}
 
-impl ToString for Uri {
-    fn to_string(&self) -> String {
-        let mut uri_string = String::new();
-        let uri = match self {
-            Uri::Sip(uri) => {
-                uri_string.push_str("sip:");
-                uri
+    // Reached over TLS on every hop (RFC 3261 26.2.2)
+    pub fn is_secure(&self) -> bool {
+        matches!(self, Uri::Sips(_))
+    }
+
+    pub fn scheme(&self) -> &str {
+        match self {
+            Uri::Sip(_) => "sip",
+            Uri::Sips(_) => "sips",
+            Uri::Tel(_) => "tel",
+            Uri::Absolute { scheme, .. } => scheme,
+        }
+    }
+
+    // URI equality (RFC 3261 19.1.4), a SIP and a SIPS URI never match. Not
+    // a PartialEq, as parameters on one side only make it intransitive
+    pub fn uri_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Uri::Sip(a), Uri::Sip(b)) | (Uri::Sips(a), Uri::Sips(b)) => a.uri_eq(b),
+            (Uri::Tel(a), Uri::Tel(b)) => a == b,
+            (
+                Uri::Absolute { scheme, uri },
+                Uri::Absolute {
+                    scheme: other_scheme,
+                    uri: other_uri,
+                },
+            ) => {
+                scheme.eq_ignore_ascii_case(other_scheme)
+                    && uri.to_string() == other_uri.to_string()
             }
-            Uri::Sips(uri) => {
-                uri_string.push_str("sips:");
-                uri
+            _ => false,
+        }
+    }
+}
+
+impl std::str::FromStr for Uri {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let element = crate::error::Element::Uri;
+        match Self::parse(s.as_bytes()) {
+            Ok((b"", uri)) => Ok(uri),
+            Ok((rest, _)) => {
+                Err(
+                    crate::error::Error::new(element, rest, nom::error::ErrorKind::Eof)
+                        .locate(s.as_bytes()),
+                )
             }
-            Uri::Absolute { scheme, uri } => todo!(),
-        };
-        uri_string.push_str(&uri.to_string());
-        uri_string
+            Err(e) => Err(crate::error::Error::from(e)
+                .within(element)
+                .locate(s.as_bytes())),
+        }
+    }
+}
+
+impl std::fmt::Display for Uri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Uri::Sip(uri) => write!(f, "sip:{}", uri),
+            Uri::Sips(uri) => write!(f, "sips:{}", uri),
+            Uri::Tel(uri) => write!(f, "tel:{}", uri),
+            Uri::Absolute { scheme, uri } => write!(f, "{}:{}", scheme, uri),
+        }
     }
 }
 
-fn parse_scheme(src: &[u8]) -> IResult<&[u8], &[u8]> {
+fn parse_scheme(src: &[u8]) -> ParseResult<'_, &[u8]> {
     // scheme = ALPHA *( ALPHA / DIGIT / "+" / "-" / "." )
     if src.first().map(|c| c.is_ascii_alphabetic()) != Some(true) {
         return Err(nom::Err::Error(nom::error::make_error(
@@ -91,6 +151,8 @@ fn parse_scheme(src: &[u8]) -> IResult<&[u8], &[u8]> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use nom::bytes::complete::tag;
+    use uriparameter::UriParameter;
 
     #[test]
     fn it_works() {
@@ -99,7 +161,7 @@ mod tests {
         assert!(rest.is_empty());
         if let Uri::Sip(uri) = uri {
             assert!(uri.userinfo.is_none());
-            assert_eq!("0.0.0.0", uri.hostport.hostname);
+            assert_eq!("0.0.0.0", uri.hostport.host);
             assert_eq!(Some(44572), uri.hostport.port);
             assert!(uri.parameters.is_empty());
             assert!(uri.headers.is_empty());
@@ -115,10 +177,10 @@ mod tests {
         assert!(rest.is_empty());
         if let Uri::Sips(uri) = uri {
             assert!(uri.userinfo.is_none());
-            assert_eq!("127.0.0.1", uri.hostport.hostname);
+            assert_eq!("127.0.0.1", uri.hostport.host);
             assert_eq!(None, uri.hostport.port);
             assert_eq!(
-                r#"[Transport(Udp), Maddr("sip.google.com"), Lr, Other { name: "opti", value: "someid" }]"#,
+                r#"[Transport(Udp), Maddr(Domain("sip.google.com")), Lr, Other { name: "opti", value: "someid" }]"#,
                 format!("{:?}", uri.parameters)
             );
             assert!(uri.headers.is_empty());
@@ -134,7 +196,7 @@ mod tests {
         assert!(rest.is_empty());
         if let Uri::Sip(uri) = uri {
             assert!(uri.userinfo.is_none());
-            assert_eq!("127.0.0.1", uri.hostport.hostname);
+            assert_eq!("127.0.0.1", uri.hostport.host);
             assert!(uri.hostport.port.is_none());
             assert_eq!(r#"[Transport(Udp)]"#, format!("{:?}", uri.parameters));
             assert_eq!(
@@ -160,6 +222,163 @@ mod tests {
         }
     }
 
+    #[test]
+    fn ipv6_works() {
+        let raw = b"sip:alice@[2001:db8::10]:5070;maddr=[2001:db8::20]>";
+        let (rest, uri) = Uri::parse(raw).unwrap();
+        assert_eq!(b">", rest);
+        let Uri::Sip(ref sip) = uri else {
+            unreachable!()
+        };
+        assert_eq!(
+            Host::Ipv6("2001:db8::10".parse().unwrap()),
+            sip.hostport.host
+        );
+        assert_eq!(Some(5070), sip.hostport.port);
+        assert_eq!(
+            "sip:alice@[2001:db8::10]:5070;maddr=[2001:db8::20]",
+            uri.to_string()
+        );
+        assert!(Uri::parse(b"sip:[2001:db8::10").is_err());
+    }
+
+    #[test]
+    fn tel_works() {
+        let raw = b"tel:+15551234>";
+        let (rest, uri) = Uri::parse(raw).unwrap();
+        assert_eq!(b">", rest);
+        let Uri::Tel(ref tel) = uri else {
+            unreachable!()
+        };
+        assert!(tel.is_global());
+        assert_eq!("tel:+15551234", uri.to_string());
+    }
+
+    #[test]
+    fn absolute_works() {
+        let raw = b"urn:service:sos>";
+        let (rest, uri) = Uri::parse(raw).unwrap();
+        assert_eq!(b">", rest);
+        assert_eq!("urn", uri.scheme());
+        assert_eq!("urn:service:sos", uri.to_string());
+
+        let raw = b"http://www.example.com/alice/photo.jpg SIP/2.0";
+        let (rest, uri) = Uri::parse(raw).unwrap();
+        assert_eq!(b" SIP/2.0", rest);
+        assert!(matches!(uri, Uri::Absolute { .. }));
+        assert_eq!("http://www.example.com/alice/photo.jpg", uri.to_string());
+    }
+
+    #[test]
+    fn scheme_is_case_insensitive() {
+        let (_, uri) = Uri::parse(b"SIP:alice@atlanta.com").unwrap();
+        assert!(matches!(uri, Uri::Sip(_)));
+        let (_, uri) = Uri::parse(b"Tel:+1234").unwrap();
+        assert!(matches!(uri, Uri::Tel(_)));
+    }
+
+    #[test]
+    fn bad_scheme_must_fail() {
+        assert!(Uri::parse(b"1sip:alice@atlanta.com").is_err());
+        assert!(Uri::parse(b"urn:").is_err());
+        assert!(Uri::parse(b"tel:alice").is_err());
+    }
+
+    fn uri(src: &str) -> Uri {
+        let (rest, uri) = Uri::parse(src.as_bytes()).unwrap();
+        assert!(rest.is_empty(), "{}", src);
+        uri
+    }
+
+    #[test]
+    fn rfc3261_equivalent_uris() {
+        // RFC 3261 19.1.4
+        for (a, b) in [
+            (
+                "sip:%61lice@atlanta.com;transport=TCP",
+                "sip:alice@AtLanTa.CoM;Transport=tcp",
+            ),
+            ("sip:carol@chicago.com", "sip:carol@chicago.com;newparam=5"),
+            ("sip:carol@chicago.com", "sip:carol@chicago.com;security=on"),
+            (
+                "sip:carol@chicago.com;newparam=5",
+                "sip:carol@chicago.com;security=on",
+            ),
+            (
+                "sip:biloxi.com;transport=tcp;method=REGISTER?to=sip:bob%40biloxi.com",
+                "sip:biloxi.com;method=REGISTER;transport=tcp?to=sip:bob%40biloxi.com",
+            ),
+            (
+                "sip:alice@atlanta.com?subject=project%20x&priority=urgent",
+                "sip:alice@atlanta.com?priority=urgent&subject=project%20x",
+            ),
+        ] {
+            assert!(uri(a).uri_eq(&uri(b)), "{} == {}", a, b);
+            assert!(uri(b).uri_eq(&uri(a)), "{} == {}", b, a);
+        }
+    }
+
+    #[test]
+    fn rfc3261_not_equivalent_uris() {
+        // RFC 3261 19.1.4
+        for (a, b) in [
+            (
+                "SIP:ALICE@AtLanTa.CoM;Transport=udp",
+                "sip:alice@AtLanTa.CoM;Transport=UDP",
+            ),
+            ("sip:bob@biloxi.com", "sip:bob@biloxi.com:5060"),
+            ("sip:bob@biloxi.com", "sip:bob@biloxi.com;transport=udp"),
+            (
+                "sip:bob@biloxi.com",
+                "sip:bob@biloxi.com:6000;transport=tcp",
+            ),
+            (
+                "sip:carol@chicago.com",
+                "sip:carol@chicago.com?Subject=next%20meeting",
+            ),
+            ("sip:bob@phone21.boxesbybob.com", "sip:bob@192.0.2.4"),
+            // Equality is not transitive
+            (
+                "sip:carol@chicago.com;security=on",
+                "sip:carol@chicago.com;security=off",
+            ),
+            // Neither are
+            ("sip:alice@atlanta.com", "sips:alice@atlanta.com"),
+            (
+                "sip:alice@atlanta.com",
+                "sip:alice@atlanta.com;maddr=atlanta.com",
+            ),
+            ("sip:alice@atlanta.com", "sip:alice@atlanta.com;user=ip"),
+            ("sip:a%3Bb@atlanta.com", "sip:a;b@atlanta.com"),
+            ("sip:alice:pw@atlanta.com", "sip:alice:PW@atlanta.com"),
+        ] {
+            assert!(!uri(a).uri_eq(&uri(b)), "{} != {}", a, b);
+            assert!(!uri(b).uri_eq(&uri(a)), "{} != {}", b, a);
+        }
+    }
+
+    #[test]
+    fn decoding_works() {
+        let uri = uri("sip:%61lice:p%40ss@atlanta.com;n%61me=a%20b;lr?Subject=next%20meeting");
+        let Uri::Sip(ref sip) = uri else {
+            unreachable!()
+        };
+        let userinfo = sip.userinfo.as_ref().unwrap();
+        assert_eq!("%61lice", userinfo.user);
+        assert_eq!("alice", userinfo.decoded_user());
+        assert_eq!(Some("p@ss".to_owned()), userinfo.decoded_password());
+        assert_eq!(
+            Some("a b".to_owned()),
+            sip.param("NAME").and_then(UriParameter::value)
+        );
+        assert!(sip.param("lr").unwrap().value().is_none());
+        assert_eq!(Some("next meeting".to_owned()), sip.header("subject"));
+        assert_eq!(
+            "sip:alice:p%40ss@atlanta.com;name=a%20b;lr?Subject=next%20meeting",
+            uri.to_string()
+        );
+    }
+
     #[test]
     fn test_request_line() {
         let raw = b"INVITE sip:127.0.0.1:5060 SIP/2.0\r\n";
@@ -179,7 +398,7 @@ mod tests {
         assert!(rest.is_empty());
         if let Uri::Sip(uri) = uri {
             assert!(uri.userinfo.is_none());
-            assert_eq!("127.0.0.1", uri.hostport.hostname);
+            assert_eq!("127.0.0.1", uri.hostport.host);
             assert_eq!(Some(5060), uri.hostport.port);
             assert!(uri.parameters.is_empty());
             assert!(uri.headers.is_empty());
@@ -5,12 +5,16 @@ use super::{
     userinfo::UserInfo,
 };
 
-use nom::{
-    multi::{many0, many_m_n},
-    IResult,
-};
+use crate::parse_utils::ParseResult;
+use nom::multi::{many0, many_m_n};
+use std::collections::BTreeMap;
+
+// Parameters that never match when present in only one of two URIs.
+// RFC 3261 19.1.4 lists user, ttl, method and maddr, its examples treat
+// transport the same way
+const MUST_MATCH: &[&str] = &["user", "ttl", "method", "maddr", "transport"];
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SipUri {
     pub userinfo: Option<UserInfo>,
     pub hostport: HostPort,
@@ -19,7 +23,7 @@ pub struct SipUri {
 }
 
 impl SipUri {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         nom::combinator::map(
             nom::sequence::tuple((
                 many_m_n(0, 1, UserInfo::parse),
@@ -35,27 +39,71 @@ impl SipUri {
             },
         )(src)
     }
+
+    // Looks a parameter up by name, ignoring case
+    pub fn param(&self, name: &str) -> Option<&UriParameter> {
+        self.parameters
+            .iter()
+            .find(|param| param.name().eq_ignore_ascii_case(name))
+    }
+
+    // Decoded value of a header component
+    pub fn header(&self, name: &str) -> Option<String> {
+        self.headers
+            .iter()
+            .find(|header| header.name().eq_ignore_ascii_case(name))
+            .map(UriHeader::value)
+    }
+
+    // URI equality (RFC 3261 19.1.4) less the scheme: userinfo is
+    // compared case-sensitively, everything else ignoring case, and an
+    // escaped character matches its unescaped form unless it is reserved
+    pub fn uri_eq(&self, other: &Self) -> bool {
+        let userinfo = |uri: &Self| uri.userinfo.as_ref().map(ToString::to_string);
+        userinfo(self) == userinfo(other)
+            && self.hostport == other.hostport
+            && params_eq(&self.parameters, &other.parameters)
+            && headers_eq(&self.headers, &other.headers)
+    }
+}
+
+fn params_eq(a: &[UriParameter], b: &[UriParameter]) -> bool {
+    // Parameters in both URIs must match, others are ignored
+    let a = a.iter().map(UriParameter::key).collect::<BTreeMap<_, _>>();
+    let b = b.iter().map(UriParameter::key).collect::<BTreeMap<_, _>>();
+    a.iter()
+        .all(|(name, value)| b.get(name).is_none_or(|other| other == value))
+        && MUST_MATCH
+            .iter()
+            .all(|name| a.contains_key(*name) == b.contains_key(*name))
+}
+
+fn headers_eq(a: &[UriHeader], b: &[UriHeader]) -> bool {
+    // Headers are never ignored, but their order does not matter
+    let keys = |headers: &[UriHeader]| {
+        let mut keys = headers.iter().map(UriHeader::key).collect::<Vec<_>>();
+        keys.sort();
+        keys
+    };
+    keys(a) == keys(b)
 }
 
-impl ToString for SipUri {
-    fn to_string(&self) -> String {
-        let mut uri_string = String::new();
+impl std::fmt::Display for SipUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if let Some(ref userinfo) = self.userinfo {
-            uri_string.push_str(&userinfo.to_string());
+            write!(f, "{}@", userinfo)?;
         }
-        uri_string.push_str(&self.hostport.to_string());
+        write!(f, "{}", self.hostport)?;
         for parameter in self.parameters.iter() {
-            uri_string.push_str(&parameter.to_string());
+            write!(f, "{}", parameter)?;
         }
         let mut header_iter = self.headers.iter();
         if let Some(first_header) = header_iter.next() {
-            uri_string.push('?');
-            uri_string.push_str(&first_header.to_string());
+            write!(f, "?{}", first_header)?;
             for header in header_iter {
-                uri_string.push('&');
-                uri_string.push_str(&header.to_string());
+                write!(f, "&{}", header)?;
             }
         }
-        uri_string
+        Ok(())
     }
 }
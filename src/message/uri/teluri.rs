@@ -0,0 +1,311 @@
+use nom::{
+    bytes::complete::{tag, take_while1},
+    combinator::opt,
+    multi::many0,
+    sequence::{preceded, tuple},
+};
+
+use super::{
+    hostport::HostPort, sipuri::SipUri, uriparameter::UriParameter, userinfo::UserInfo,
+    userparam::UserParam,
+};
+use crate::parse_utils::{escaped, ParseResult};
+
+// RFC 3966: The tel URI for Telephone Numbers
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelUri {
+    pub number: TelNumber,
+    pub params: Vec<TelParam>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TelNumber {
+    // global-number-digits  =  "+" *phonedigit DIGIT *phonedigit
+    Global(String),
+    // local-number-digits  =  *phonedigit-hex (HEXDIG / "*" / "#")*phonedigit-hex
+    Local(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TelParam {
+    Extension(String),
+    Isub(String),
+    PhoneContext(String),
+    Other { name: String, value: Option<String> },
+}
+
+impl TelUri {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // telephone-subscriber  =  global-number / local-number
+        // global-number         =  global-number-digits *par
+        // local-number          =  local-number-digits *par context *par
+        let (rest, (number, params)) = tuple((TelNumber::parse, many0(TelParam::parse)))(src)?;
+        let context = params
+            .iter()
+            .filter(|p| matches!(p, TelParam::PhoneContext(_)))
+            .count();
+        let valid = match number {
+            TelNumber::Global(_) => context == 0,
+            TelNumber::Local(_) => context == 1,
+        };
+        if valid {
+            Ok((rest, Self { number, params }))
+        } else {
+            Err(nom::Err::Error(nom::error::make_error(
+                src,
+                nom::error::ErrorKind::Verify,
+            )))
+        }
+    }
+
+    pub fn is_global(&self) -> bool {
+        matches!(self.number, TelNumber::Global(_))
+    }
+
+    pub fn phone_context(&self) -> Option<&str> {
+        self.params.iter().find_map(|p| match p {
+            TelParam::PhoneContext(context) => Some(context.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn extension(&self) -> Option<&str> {
+        self.params.iter().find_map(|p| match p {
+            TelParam::Extension(ext) => Some(ext.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn isub(&self) -> Option<&str> {
+        self.params.iter().find_map(|p| match p {
+            TelParam::Isub(isub) => Some(isub.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn to_sip_uri(&self, hostport: HostPort) -> SipUri {
+        // RFC 3261 19.1.6: the telephone-subscriber becomes the user part
+        SipUri {
+            userinfo: Some(UserInfo {
+                user: self.to_string(),
+                password: None,
+            }),
+            hostport,
+            parameters: vec![UriParameter::User(UserParam::Phone)],
+            headers: vec![],
+        }
+    }
+}
+
+impl TryFrom<&SipUri> for TelUri {
+    type Error = anyhow::Error;
+
+    fn try_from(uri: &SipUri) -> Result<Self, Self::Error> {
+        if !uri
+            .parameters
+            .iter()
+            .any(|p| matches!(p, UriParameter::User(UserParam::Phone)))
+        {
+            anyhow::bail!("SIP URI has no user=phone parameter");
+        }
+        let user = uri
+            .userinfo
+            .as_ref()
+            .map(|userinfo| userinfo.user.as_bytes())
+            .ok_or_else(|| anyhow::anyhow!("SIP URI has no user part"))?;
+        match TelUri::parse(user) {
+            Ok((b"", tel)) => Ok(tel),
+            _ => anyhow::bail!("user part is not a telephone-subscriber"),
+        }
+    }
+}
+
+impl std::fmt::Display for TelUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The "tel:" scheme belongs to the enclosing URI
+        write!(f, "{}", self.number)?;
+        for param in self.params.iter() {
+            write!(f, "{}", param)?;
+        }
+        Ok(())
+    }
+}
+
+impl TelNumber {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        if let Ok((rest, _)) = tag::<_, _, ()>(b"+")(src) {
+            let (rest, digits) = take_while1(is_phonedigit)(rest)?;
+            if !digits.iter().any(u8::is_ascii_digit) {
+                return Err(nom::Err::Error(nom::error::make_error(
+                    src,
+                    nom::error::ErrorKind::Digit,
+                )));
+            }
+            Ok((rest, Self::Global(format!("+{}", lossy(digits)))))
+        } else {
+            let (rest, digits) = take_while1(is_phonedigit_hex)(src)?;
+            if !digits
+                .iter()
+                .any(|c| c.is_ascii_hexdigit() || b"*#".contains(c))
+            {
+                return Err(nom::Err::Error(nom::error::make_error(
+                    src,
+                    nom::error::ErrorKind::HexDigit,
+                )));
+            }
+            Ok((rest, Self::Local(lossy(digits))))
+        }
+    }
+
+    pub fn digits(&self) -> &str {
+        match self {
+            TelNumber::Global(digits) | TelNumber::Local(digits) => digits,
+        }
+    }
+}
+
+impl std::fmt::Display for TelNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.digits())
+    }
+}
+
+impl TelParam {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // par            =  parameter / extension / isdn-subaddress
+        // extension      =  ";ext=" 1*phonedigit
+        // isdn-subaddress  =  ";isub=" 1*uric
+        // context        =  ";phone-context=" descriptor
+        // descriptor     =  domainname / global-number-digits
+        // parameter      =  ";" pname ["=" pvalue ]
+        let (rest, (_, name, value)) = tuple((
+            tag(b";"),
+            take_while1(|x: u8| x.is_ascii_alphanumeric() || x == b'-'),
+            opt(preceded(tag(b"="), pvalue)),
+        ))(src)?;
+        let name = lossy(name);
+        let param = match (name.to_ascii_lowercase().as_str(), value) {
+            ("ext", Some(ext)) if !ext.is_empty() && ext.bytes().all(is_phonedigit) => {
+                Self::Extension(ext)
+            }
+            ("isub", Some(isub)) => Self::Isub(isub),
+            ("phone-context", Some(context)) => Self::PhoneContext(context),
+            ("ext" | "isub" | "phone-context", _) => {
+                return Err(nom::Err::Error(nom::error::make_error(
+                    src,
+                    nom::error::ErrorKind::Verify,
+                )))
+            }
+            (_, value) => Self::Other { name, value },
+        };
+        Ok((rest, param))
+    }
+}
+
+impl std::fmt::Display for TelParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TelParam::Extension(ext) => write!(f, ";ext={}", ext),
+            TelParam::Isub(isub) => write!(f, ";isub={}", isub),
+            TelParam::PhoneContext(context) => write!(f, ";phone-context={}", context),
+            TelParam::Other { name, value: None } => write!(f, ";{}", name),
+            TelParam::Other {
+                name,
+                value: Some(value),
+            } => write!(f, ";{}={}", name, value),
+        }
+    }
+}
+
+pub(crate) fn pvalue(src: &[u8]) -> ParseResult<'_, String> {
+    // pvalue            =  1*paramchar
+    // paramchar         =  param-unreserved / unreserved / pct-encoded
+    // param-unreserved  =  "[" / "]" / "/" / ":" / "&" / "+" / "$"
+    nom::multi::fold_many1(
+        nom::branch::alt((
+            take_while1(|x: u8| x.is_ascii_alphanumeric() || b"-_.!~*'()[]/:&+$".contains(&x)),
+            escaped,
+        )),
+        String::new,
+        |mut acc, item: &[u8]| {
+            acc.push_str(&lossy(item));
+            acc
+        },
+    )(src)
+}
+
+fn is_phonedigit(c: u8) -> bool {
+    // phonedigit  =  DIGIT / [ visual-separator ]
+    // visual-separator  =  "-" / "." / "(" / ")"
+    c.is_ascii_digit() || b"-.()".contains(&c)
+}
+
+fn is_phonedigit_hex(c: u8) -> bool {
+    // phonedigit-hex  =  HEXDIG / "*" / "#" / [ visual-separator ]
+    c.is_ascii_hexdigit() || b"*#-.()".contains(&c)
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Host;
+
+    #[test]
+    fn global_number_works() {
+        let (rest, tel) = TelUri::parse(b"+1-201-555-0123;ext=1234>").unwrap();
+        assert_eq!(b">", rest);
+        assert!(tel.is_global());
+        assert_eq!("+1-201-555-0123", tel.number.digits());
+        assert_eq!(Some("1234"), tel.extension());
+        assert_eq!("+1-201-555-0123;ext=1234", tel.to_string());
+    }
+
+    #[test]
+    fn local_number_works() {
+        let (rest, tel) = TelUri::parse(b"7042;phone-context=example.com;isub=%2Fab;foo").unwrap();
+        assert!(rest.is_empty());
+        assert!(!tel.is_global());
+        assert_eq!(Some("example.com"), tel.phone_context());
+        assert_eq!(Some("%2Fab"), tel.isub());
+        assert_eq!(
+            TelParam::Other {
+                name: "foo".to_owned(),
+                value: None
+            },
+            tel.params[2]
+        );
+        assert_eq!(
+            "7042;phone-context=example.com;isub=%2Fab;foo",
+            tel.to_string()
+        );
+    }
+
+    #[test]
+    fn context_rules_are_enforced() {
+        assert!(TelUri::parse(b"7042").is_err());
+        assert!(TelUri::parse(b"+7042;phone-context=+1").is_err());
+        assert!(TelUri::parse(b"+").is_err());
+        assert!(!matches!(TelUri::parse(b"+1;ext=abc"), Ok((rest, _)) if rest.is_empty()));
+    }
+
+    #[test]
+    fn sip_conversion_works() {
+        let (_, tel) = TelUri::parse(b"+358-555-1234567;postd=pp22").unwrap();
+        let sip = tel.to_sip_uri(HostPort {
+            host: Host::Domain("foo.com".to_owned()),
+            port: None,
+        });
+        assert_eq!(
+            "+358-555-1234567;postd=pp22@foo.com;user=phone",
+            sip.to_string()
+        );
+        assert_eq!(tel, TelUri::try_from(&sip).unwrap());
+
+        let (_, sip) = SipUri::parse(b"alice@atlanta.com").unwrap();
+        assert!(TelUri::try_from(&sip).is_err());
+    }
+}
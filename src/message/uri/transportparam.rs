@@ -1,43 +1,46 @@
-use nom::{branch::alt, bytes::complete::tag, combinator::map, IResult};
+use nom::combinator::map;
 
-use crate::parse_utils::token;
+use crate::parse_utils::{token, ParseResult};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum TransportParam {
     Udp,
     Tcp,
     Sctp,
     Tls,
+    // RFC 7118 5
+    Ws,
+    Wss,
     Other(String),
 }
 
-const UDP: &[u8] = b"udp";
-const TCP: &[u8] = b"tcp";
-const SCTP: &[u8] = b"sctp";
-const TLS: &[u8] = b"tls";
 impl TransportParam {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
-        map(
-            alt((tag(UDP), tag(TCP), tag(SCTP), tag(TLS), token)),
-            |param| match param {
-                UDP => Self::Udp,
-                TCP => Self::Tcp,
-                SCTP => Self::Sctp,
-                TLS => Self::Tls,
-                _ => Self::Other(std::str::from_utf8(param).unwrap().to_owned()),
-            },
-        )(src)
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // transport-param  =  "transport="
+        //                     ( "udp" / "tcp" / "sctp" / "tls" / "ws" / "wss"
+        //                     / other-transport)
+        map(token, |param| match param.to_ascii_lowercase().as_slice() {
+            b"udp" => Self::Udp,
+            b"tcp" => Self::Tcp,
+            b"sctp" => Self::Sctp,
+            b"tls" => Self::Tls,
+            b"ws" => Self::Ws,
+            b"wss" => Self::Wss,
+            _ => Self::Other(String::from_utf8_lossy(param).into_owned()),
+        })(src)
     }
 }
 
-impl ToString for TransportParam {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for TransportParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            TransportParam::Udp => "udp".to_string(),
-            TransportParam::Tcp => "tcp".to_string(),
-            TransportParam::Sctp => "sctp".to_string(),
-            TransportParam::Tls => "tls".to_string(),
-            TransportParam::Other(other) => other.to_owned(),
+            TransportParam::Udp => write!(f, "udp"),
+            TransportParam::Tcp => write!(f, "tcp"),
+            TransportParam::Sctp => write!(f, "sctp"),
+            TransportParam::Tls => write!(f, "tls"),
+            TransportParam::Ws => write!(f, "ws"),
+            TransportParam::Wss => write!(f, "wss"),
+            TransportParam::Other(other) => write!(f, "{}", other),
         }
     }
 }
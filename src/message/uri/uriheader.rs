@@ -1,12 +1,13 @@
+use super::escape::{canonical, decode_lossy, is_header_char};
+use crate::parse_utils::ParseResult;
 use nom::{
     branch::alt,
     bytes::complete::{tag, take_while1},
     multi::{fold_many0, fold_many1},
     sequence::{separated_pair, tuple},
-    IResult,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct UriHeader {
     name: String,
     value: String,
@@ -19,21 +20,42 @@ impl PartialEq<(&str, &str)> for UriHeader {
 }
 
 impl UriHeader {
-    fn parse(src: &[u8]) -> IResult<&[u8], Self> {
+    fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         // header = hname "=" hvalue
         nom::combinator::map(separated_pair(hname, tag("="), hvalue), |(name, value)| {
             Self { name, value }
         })(src)
     }
+
+    pub fn name(&self) -> String {
+        decode_lossy(&self.name)
+    }
+
+    pub fn value(&self) -> String {
+        decode_lossy(&self.value)
+    }
+
+    // Header components compare by decoded name ignoring case and by value
+    pub(crate) fn key(&self) -> (String, String) {
+        (
+            canonical(&self.name, is_header_char).to_ascii_lowercase(),
+            canonical(&self.value, is_header_char),
+        )
+    }
 }
 
-impl ToString for UriHeader {
-    fn to_string(&self) -> String {
-        format!("{}={}", self.name, self.value)
+impl std::fmt::Display for UriHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}={}",
+            canonical(&self.name, is_header_char),
+            canonical(&self.value, is_header_char)
+        )
     }
 }
 
-pub fn parse_headers(src: &[u8]) -> IResult<&[u8], Vec<UriHeader>> {
+pub fn parse_headers(src: &[u8]) -> ParseResult<'_, Vec<UriHeader>> {
     // headers = "?" header *( "&" header )
     let Ok((rest, _)) = tag::<_, _, ()>(b"?")(src) else {
         return Ok((src, vec![]));
@@ -52,20 +74,20 @@ pub fn parse_headers(src: &[u8]) -> IResult<&[u8], Vec<UriHeader>> {
     Ok((rest, parsed_headers))
 }
 
-fn hname(src: &[u8]) -> IResult<&[u8], String> {
+fn hname(src: &[u8]) -> ParseResult<'_, String> {
     fold_many1(header_chars(), String::new, extender)(src)
 }
 
-fn hvalue(src: &[u8]) -> IResult<&[u8], String> {
+fn hvalue(src: &[u8]) -> ParseResult<'_, String> {
     fold_many0(header_chars(), String::new, extender)(src)
 }
 
 fn extender(mut acc: String, item: &[u8]) -> String {
-    acc.push_str(std::str::from_utf8(item).unwrap());
+    acc.push_str(&String::from_utf8_lossy(item));
     acc
 }
 
-fn header_chars<'a>() -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+fn header_chars<'a>() -> impl FnMut(&'a [u8]) -> ParseResult<'a, &'a [u8]> {
     alt((
         crate::parse_utils::unreserved1,
         crate::parse_utils::escaped,
@@ -73,7 +95,7 @@ fn header_chars<'a>() -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
     ))
 }
 
-fn hnv_unreserved(src: &[u8]) -> IResult<&[u8], &[u8]> {
+fn hnv_unreserved(src: &[u8]) -> ParseResult<'_, &[u8]> {
     // hnv-unreserved  =  "[" / "]" / "/" / "?" / ":" / "+" / "$"
     take_while1(|x: u8| b"[]/?:+$".contains(&x))(src)
 }
@@ -1,29 +1,37 @@
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_while},
+    bytes::complete::{tag, tag_no_case, take_while},
     character::is_digit,
-    combinator::map,
-    sequence::{separated_pair, tuple},
-    IResult, ParseTo,
+    combinator::{map, opt},
+    sequence::{preceded, tuple},
+    ParseTo,
 };
 
-use crate::{message::Method, parse_utils::parse_host};
+use crate::{
+    message::{Host, Method},
+    parse_utils::{parse_host, ParseResult},
+};
 
-use super::{transportparam::TransportParam, userparam::UserParam};
+use super::{
+    escape::{canonical, decode_lossy, is_param_char},
+    teluri::pvalue,
+    transportparam::TransportParam,
+    userparam::UserParam,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum UriParameter {
     Transport(TransportParam),
     User(UserParam),
     Method(Method),
     Ttl(u8),
-    Maddr(String),
+    Maddr(Host),
     Lr,
     Other { name: String, value: String },
 }
 
 impl UriParameter {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         map(
             tuple((
                 tag(b";"),
@@ -40,52 +48,87 @@ impl UriParameter {
             |(_, parsed_parameter)| parsed_parameter,
         )(src)
     }
+
+    pub fn name(&self) -> String {
+        match self {
+            UriParameter::Transport(_) => "transport".to_owned(),
+            UriParameter::User(_) => "user".to_owned(),
+            UriParameter::Method(_) => "method".to_owned(),
+            UriParameter::Ttl(_) => "ttl".to_owned(),
+            UriParameter::Maddr(_) => "maddr".to_owned(),
+            UriParameter::Lr => "lr".to_owned(),
+            UriParameter::Other { name, .. } => decode_lossy(name),
+        }
+    }
+
+    // None for a parameter without a value
+    pub fn value(&self) -> Option<String> {
+        match self {
+            UriParameter::Transport(transport) => Some(transport.to_string()),
+            UriParameter::User(user) => Some(user.to_string()),
+            UriParameter::Method(method) => Some(method.to_string()),
+            UriParameter::Ttl(ttl) => Some(ttl.to_string()),
+            UriParameter::Maddr(maddr) => Some(maddr.to_string()),
+            UriParameter::Lr => None,
+            UriParameter::Other { value, .. } if value.is_empty() => None,
+            UriParameter::Other { value, .. } => Some(decode_lossy(value)),
+        }
+    }
+
+    // Name and value as compared (RFC 3261 19.1.4), both ignoring case
+    pub(crate) fn key(&self) -> (String, String) {
+        let (name, value) = match self {
+            UriParameter::Other { name, value } => (
+                canonical(name, is_param_char),
+                canonical(value, is_param_char),
+            ),
+            _ => (self.name(), self.value().unwrap_or_default()),
+        };
+        (name.to_ascii_lowercase(), value.to_ascii_lowercase())
+    }
 }
 
-impl ToString for UriParameter {
-    fn to_string(&self) -> String {
-        format!(
-            ";{}",
-            match self {
-                UriParameter::Transport(transport) =>
-                    format!("transport={}", transport.to_string()),
-                UriParameter::User(user) => format!("user={}", user.to_string()),
-                UriParameter::Method(method) => format!("method={}", method.to_string()),
-                UriParameter::Ttl(ttl) => format!("ttl={}", ttl),
-                UriParameter::Maddr(maddr) => format!("maddr={}", maddr),
-                UriParameter::Lr => "lr".to_string(),
-                UriParameter::Other { name, value } => format!(
-                    "{}{}",
-                    name,
-                    if value.is_empty() {
-                        String::new()
-                    } else {
-                        format!("={}", value)
-                    }
-                ),
+impl std::fmt::Display for UriParameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UriParameter::Transport(transport) => write!(f, ";transport={}", transport),
+            UriParameter::User(user) => write!(f, ";user={}", user),
+            UriParameter::Method(method) => write!(f, ";method={}", method),
+            UriParameter::Ttl(ttl) => write!(f, ";ttl={}", ttl),
+            UriParameter::Maddr(maddr) => write!(f, ";maddr={}", maddr),
+            UriParameter::Lr => write!(f, ";lr"),
+            UriParameter::Other { name, value } => {
+                write!(f, ";{}", canonical(name, is_param_char))?;
+                if !value.is_empty() {
+                    write!(f, "={}", canonical(value, is_param_char))?;
+                }
+                Ok(())
             }
-        )
+        }
     }
 }
 
-fn parse_transport(src: &[u8]) -> IResult<&[u8], UriParameter> {
+// Parameter names are case-insensitive (RFC 3261 19.1.4)
+fn parse_transport(src: &[u8]) -> ParseResult<'_, UriParameter> {
     map(
-        tuple((tag(b"transport="), TransportParam::parse)),
+        tuple((tag_no_case(b"transport="), TransportParam::parse)),
         |(_, param)| UriParameter::Transport(param),
     )(src)
 }
-fn parse_user(src: &[u8]) -> IResult<&[u8], UriParameter> {
-    map(tuple((tag(b"user="), UserParam::parse)), |(_, param)| {
-        UriParameter::User(param)
-    })(src)
+fn parse_user(src: &[u8]) -> ParseResult<'_, UriParameter> {
+    map(
+        tuple((tag_no_case(b"user="), UserParam::parse)),
+        |(_, param)| UriParameter::User(param),
+    )(src)
 }
-fn parse_method(src: &[u8]) -> IResult<&[u8], UriParameter> {
-    map(tuple((tag(b"method="), Method::parse)), |(_, param)| {
-        UriParameter::Method(param)
-    })(src)
+fn parse_method(src: &[u8]) -> ParseResult<'_, UriParameter> {
+    map(
+        tuple((tag_no_case(b"method="), Method::parse)),
+        |(_, param)| UriParameter::Method(param),
+    )(src)
 }
-fn parse_ttl(src: &[u8]) -> IResult<&[u8], UriParameter> {
-    let (rest, (_, ttl)) = tuple((tag(b"ttl="), take_while(is_digit)))(src)?;
+fn parse_ttl(src: &[u8]) -> ParseResult<'_, UriParameter> {
+    let (rest, (_, ttl)) = tuple((tag_no_case(b"ttl="), take_while(is_digit)))(src)?;
     Ok((
         rest,
         UriParameter::Ttl(
@@ -97,24 +140,31 @@ fn parse_ttl(src: &[u8]) -> IResult<&[u8], UriParameter> {
         ),
     ))
 }
-fn parse_maddr(src: &[u8]) -> IResult<&[u8], UriParameter> {
-    map(tuple((tag(b"maddr="), parse_host)), |(_, param)| {
+fn parse_maddr(src: &[u8]) -> ParseResult<'_, UriParameter> {
+    map(tuple((tag_no_case(b"maddr="), parse_host)), |(_, param)| {
         UriParameter::Maddr(param)
     })(src)
 }
-fn parse_lr(src: &[u8]) -> IResult<&[u8], UriParameter> {
-    map(tag(b"lr"), |_| UriParameter::Lr)(src)
+fn parse_lr(src: &[u8]) -> ParseResult<'_, UriParameter> {
+    // lr-param  =  "lr", so ";lrx=1" is some other-param; the SP ends a
+    // Request-URI. The ";lr=on" of many deployments is taken as one too
+    let (rest, _) = tuple((tag_no_case(b"lr"), opt(preceded(tag(b"="), pvalue))))(src)?;
+    match rest.first() {
+        None | Some(b';' | b'?' | b'>' | b' ') => Ok((rest, UriParameter::Lr)),
+        Some(_) => Err(nom::Err::Error(nom::error::make_error(
+            src,
+            nom::error::ErrorKind::Verify,
+        ))),
+    }
 }
-fn parse_other(src: &[u8]) -> IResult<&[u8], UriParameter> {
+fn parse_other(src: &[u8]) -> ParseResult<'_, UriParameter> {
+    // other-param  =  pname [ "=" pvalue ]
+    // pname        =  1*paramchar
     map(
-        separated_pair(
-            take_while(|x: u8| !b"=".contains(&x) && x.is_ascii_graphic()),
-            tag(b"="),
-            take_while(|x: u8| x.is_ascii_graphic()),
-        ),
+        tuple((pvalue, opt(preceded(tag(b"="), pvalue)))),
         |(name, value)| UriParameter::Other {
-            name: std::str::from_utf8(name).unwrap().to_owned(),
-            value: std::str::from_utf8(value).unwrap().to_owned(),
+            name,
+            value: value.unwrap_or_default(),
         },
     )(src)
 }
@@ -135,4 +185,59 @@ mod tests {
             unreachable!()
         }
     }
+
+    #[test]
+    fn valueless_and_escaped_work() {
+        let (rest, param) = UriParameter::parse(b";unknown-no-value>").unwrap();
+        assert_eq!(b">", rest);
+        assert_eq!(";unknown-no-value", param.to_string());
+        let (rest, param) = UriParameter::parse(b";n%61me=v%61lue%25%34%31;lr").unwrap();
+        assert_eq!(b";lr", rest);
+        assert_eq!(";name=value%2541", param.to_string());
+        assert_eq!("name", param.name());
+        assert_eq!(Some("value%41".to_owned()), param.value());
+
+        let (_, param) = UriParameter::parse(b";Transport=TCP").unwrap();
+        assert!(matches!(
+            param,
+            UriParameter::Transport(TransportParam::Tcp)
+        ));
+        assert_eq!(";transport=tcp", param.to_string());
+        let (_, param) = UriParameter::parse(b";transport=WSS").unwrap();
+        assert!(matches!(
+            param,
+            UriParameter::Transport(TransportParam::Wss)
+        ));
+        assert_eq!(";transport=wss", param.to_string());
+    }
+
+    #[test]
+    fn lr_is_not_a_prefix() {
+        for raw in [&b";lr"[..], b";LR;", b";lr?", b";lr>", b";lr SIP/2.0"] {
+            let (rest, param) = UriParameter::parse(raw).unwrap();
+            assert!(matches!(param, UriParameter::Lr));
+            assert_eq!(&raw[3..], rest);
+        }
+        let (rest, param) = UriParameter::parse(b";lr=on;transport=tcp").unwrap();
+        assert!(matches!(param, UriParameter::Lr));
+        assert_eq!(b";transport=tcp", rest);
+        assert_eq!(";lr", param.to_string());
+        let (rest, param) = UriParameter::parse(b";lrx=1>").unwrap();
+        assert_eq!(b">", rest);
+        assert_eq!("lrx", param.name());
+        assert_eq!(Some("1".to_owned()), param.value());
+        let (rest, param) = UriParameter::parse(b";LRfoo").unwrap();
+        assert!(rest.is_empty());
+        assert!(matches!(param, UriParameter::Other { .. }));
+        assert_eq!(";LRfoo", param.to_string());
+    }
+
+    #[test]
+    fn test_method() {
+        let (rest, param) = UriParameter::parse(b";method=SUBSCRIBE;lr").unwrap();
+        assert_eq!(b";lr", rest);
+        assert_eq!(";method=SUBSCRIBE", param.to_string());
+        let (_, param) = UriParameter::parse(b";method=FOOBAR").unwrap();
+        assert_eq!(";method=FOOBAR", param.to_string());
+    }
 }
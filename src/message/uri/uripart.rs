@@ -1,12 +1,22 @@
-#[derive(Debug)]
+use nom::{
+    bytes::complete::{tag, take_while1},
+    combinator::map,
+    multi::many1,
+    sequence::{preceded, tuple},
+};
+
+use crate::parse_utils::{escaped, ParseResult};
+
+#[derive(Debug, Clone)]
 pub enum UriPart {
     Hier {
         path: HierPath,
         query: Option<String>,
     },
+    Opaque(String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum HierPath {
     Net {
         authority: String,
@@ -15,7 +25,188 @@ pub enum HierPath {
     Abs(AbsPath),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AbsPath {
-    segments: Vec<String>,
+    pub segments: Vec<String>,
+}
+
+impl UriPart {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // absoluteURI    =  scheme ":" ( hier-part / opaque-part )
+        // hier-part      =  ( net-path / abs-path ) [ "?" query ]
+        // opaque-part    =  uric-no-slash *uric
+        if src.first() == Some(&b'/') {
+            map(
+                tuple((HierPath::parse, nom::combinator::opt(parse_query))),
+                |(path, query)| Self::Hier { path, query },
+            )(src)
+        } else {
+            let (rest, first) = uric_no_slash(src)?;
+            let (rest, tail) = uric_string(is_uric_char)(rest)?;
+            let mut opaque = first;
+            opaque.push_str(&tail);
+            Ok((rest, Self::Opaque(opaque)))
+        }
+    }
+}
+
+impl HierPath {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // net-path       =  "//" authority [ abs-path ]
+        if let Ok((rest, _)) = tag::<_, _, ()>(b"//")(src) {
+            let (rest, authority) = uric_string(is_authority_char)(rest)?;
+            let (rest, abs) = nom::combinator::opt(AbsPath::parse)(rest)?;
+            Ok((rest, Self::Net { authority, abs }))
+        } else {
+            map(AbsPath::parse, Self::Abs)(src)
+        }
+    }
+}
+
+impl AbsPath {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // abs-path       =  "/" path-segments
+        // path-segments  =  segment *( "/" segment )
+        map(
+            many1(preceded(tag(b"/"), uric_string(is_pchar_or_param))),
+            |segments| Self { segments },
+        )(src)
+    }
+}
+
+impl std::fmt::Display for UriPart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UriPart::Hier { path, query } => {
+                write!(f, "{}", path)?;
+                if let Some(query) = query {
+                    write!(f, "?{}", query)?;
+                }
+                Ok(())
+            }
+            UriPart::Opaque(opaque) => write!(f, "{}", opaque),
+        }
+    }
+}
+
+impl std::fmt::Display for HierPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HierPath::Net { authority, abs } => {
+                write!(f, "//{}", authority)?;
+                if let Some(abs) = abs {
+                    write!(f, "{}", abs)?;
+                }
+                Ok(())
+            }
+            HierPath::Abs(abs) => write!(f, "{}", abs),
+        }
+    }
+}
+
+impl std::fmt::Display for AbsPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for segment in self.segments.iter() {
+            write!(f, "/{}", segment)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_query(src: &[u8]) -> ParseResult<'_, String> {
+    // query          =  *uric
+    preceded(tag(b"?"), uric_string(is_uric_char))(src)
+}
+
+fn uric_no_slash(src: &[u8]) -> ParseResult<'_, String> {
+    // uric-no-slash  =  unreserved / escaped / ";" / "?" / ":" / "@" / "&" / "=" / "+" / "$" / ","
+    if let Ok((rest, escape)) = escaped(src) {
+        return Ok((rest, String::from_utf8_lossy(escape).into_owned()));
+    }
+    match src.first() {
+        Some(c) if *c != b'/' && is_uric_char(*c) => Ok((&src[1..], (*c as char).to_string())),
+        _ => Err(nom::Err::Error(nom::error::make_error(
+            src,
+            nom::error::ErrorKind::Fail,
+        ))),
+    }
+}
+
+fn uric_string<'a>(
+    allowed: impl Fn(u8) -> bool + Copy,
+) -> impl FnMut(&'a [u8]) -> ParseResult<'a, String> {
+    // Zero or more allowed characters or escaped octets, kept escaped
+    nom::multi::fold_many0(
+        nom::branch::alt((take_while1(move |x: u8| x != b'%' && allowed(x)), escaped)),
+        String::new,
+        |mut acc, item: &[u8]| {
+            acc.push_str(&String::from_utf8_lossy(item));
+            acc
+        },
+    )
+}
+
+fn is_unreserved(c: u8) -> bool {
+    // unreserved  =  alphanum / mark
+    c.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&c)
+}
+
+fn is_uric_char(c: u8) -> bool {
+    // uric  =  reserved / unreserved / escaped
+    // reserved  =  ";" / "/" / "?" / ":" / "@" / "&" / "=" / "+" / "$" / ","
+    is_unreserved(c) || b";/?:@&=+$,".contains(&c)
+}
+
+fn is_authority_char(c: u8) -> bool {
+    // authority  =  srvr / reg-name
+    is_unreserved(c) || b";:@&=+$,[]".contains(&c)
+}
+
+fn is_pchar_or_param(c: u8) -> bool {
+    // segment  =  *pchar *( ";" param )
+    // pchar    =  unreserved / escaped / ":" / "@" / "&" / "=" / "+" / "$" / ","
+    is_unreserved(c) || b":@&=+$,;".contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opaque_works() {
+        let (rest, part) = UriPart::parse(b"service:sos>").unwrap();
+        assert_eq!(b">", rest);
+        assert!(matches!(part, UriPart::Opaque(ref s) if s == "service:sos"));
+    }
+
+    #[test]
+    fn net_path_works() {
+        let (rest, part) =
+            UriPart::parse(b"//www.example.com:8080/a/b%20c?x=1&y=2 SIP/2.0").unwrap();
+        assert_eq!(b" SIP/2.0", rest);
+        let UriPart::Hier {
+            path: HierPath::Net { authority, abs },
+            query,
+        } = &part
+        else {
+            unreachable!()
+        };
+        assert_eq!("www.example.com:8080", authority);
+        assert_eq!(vec!["a", "b%20c"], abs.as_ref().unwrap().segments);
+        assert_eq!(Some("x=1&y=2".to_owned()), *query);
+        assert_eq!("//www.example.com:8080/a/b%20c?x=1&y=2", part.to_string());
+    }
+
+    #[test]
+    fn abs_path_works() {
+        let (rest, part) = UriPart::parse(b"/path/to/resource").unwrap();
+        assert!(rest.is_empty());
+        assert_eq!("/path/to/resource", part.to_string());
+    }
+
+    #[test]
+    fn empty_must_fail() {
+        assert!(UriPart::parse(b"").is_err());
+        assert!(UriPart::parse(b">").is_err());
+    }
 }
@@ -1,38 +1,47 @@
+use super::escape::{canonical, decode_lossy, is_password_char, is_user_char};
+use crate::parse_utils::ParseResult;
 use nom::{
     bytes::complete::{tag, take_while},
     combinator::map,
     sequence::tuple,
-    IResult,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct UserInfo {
     pub user: String,
     pub password: Option<String>,
 }
 
 impl UserInfo {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
         // userinfo = ( user / telephone-subscriber ) [ ":" password ] "@"
         map(
             tuple((parse_user, parse_password, tag(b"@"))),
             |(user, password, _)| Self { user, password },
         )(src)
     }
+
+    pub fn decoded_user(&self) -> String {
+        decode_lossy(&self.user)
+    }
+
+    pub fn decoded_password(&self) -> Option<String> {
+        self.password.as_deref().map(decode_lossy)
+    }
 }
 
-impl ToString for UserInfo {
-    fn to_string(&self) -> String {
-        let pass = if let Some(ref pass) = self.password {
-            format!(":{}", pass)
-        } else {
-            String::new()
-        };
-        format!("{}{}", self.user, pass)
+impl std::fmt::Display for UserInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The trailing "@" belongs to the enclosing URI
+        write!(f, "{}", canonical(&self.user, is_user_char))?;
+        if let Some(ref pass) = self.password {
+            write!(f, ":{}", canonical(pass, is_password_char))?;
+        }
+        Ok(())
     }
 }
 
-fn parse_user(src: &[u8]) -> IResult<&[u8], String> {
+fn parse_user(src: &[u8]) -> ParseResult<'_, String> {
     let (rest, user_bytes) = take_while(|x: u8| !b":@".contains(&x) && x.is_ascii_graphic())(src)?;
     if user_bytes.is_empty() {
         Err(nom::Err::Error(nom::error::make_error(
@@ -40,11 +49,11 @@ fn parse_user(src: &[u8]) -> IResult<&[u8], String> {
             nom::error::ErrorKind::Fail,
         )))
     } else {
-        Ok((rest, std::str::from_utf8(user_bytes).unwrap().to_owned()))
+        Ok((rest, String::from_utf8_lossy(user_bytes).into_owned()))
     }
 }
 
-fn parse_password(src: &[u8]) -> IResult<&[u8], Option<String>> {
+fn parse_password(src: &[u8]) -> ParseResult<'_, Option<String>> {
     let Ok((rest, _)) = tag::<_, _, ()>(b":")(src) else {
         return Ok((src, None));
     };
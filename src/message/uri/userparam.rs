@@ -1,32 +1,30 @@
-use nom::{branch::alt, bytes::complete::tag, combinator::map, IResult};
+use nom::combinator::map;
 
-use crate::parse_utils::token;
+use crate::parse_utils::{token, ParseResult};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum UserParam {
     Ip,
     Phone,
     Other(String),
 }
-const PHONE: &[u8] = b"phone";
-const IP: &[u8] = b"ip";
-
 impl UserParam {
-    pub fn parse(src: &[u8]) -> IResult<&[u8], Self> {
-        map(alt((tag(PHONE), tag(IP), token)), |param| match param {
-            PHONE => Self::Phone,
-            IP => Self::Ip,
-            _ => Self::Other(std::str::from_utf8(param).unwrap().to_owned()),
+    pub fn parse(src: &[u8]) -> ParseResult<'_, Self> {
+        // user-param  =  "user=" ( "phone" / "ip" / other-user)
+        map(token, |param| match param.to_ascii_lowercase().as_slice() {
+            b"phone" => Self::Phone,
+            b"ip" => Self::Ip,
+            _ => Self::Other(String::from_utf8_lossy(param).into_owned()),
         })(src)
     }
 }
 
-impl ToString for UserParam {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for UserParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            UserParam::Ip => "ip".to_string(),
-            UserParam::Phone => "phone".to_string(),
-            UserParam::Other(other) => other.to_owned(),
+            UserParam::Ip => write!(f, "ip"),
+            UserParam::Phone => write!(f, "phone"),
+            UserParam::Other(other) => write!(f, "{}", other),
         }
     }
 }
@@ -0,0 +1,252 @@
+use super::{
+    header::{Value, WellKnown},
+    Message, Method, StartLine, StatusCode,
+};
+use crate::error::Error;
+
+// Mandatory in every request (RFC 3261 8.1.1); responses carry all but
+// Max-Forwards
+const MANDATORY: [WellKnown; 6] = [
+    WellKnown::To,
+    WellKnown::From,
+    WellKnown::CSeq,
+    WellKnown::CallId,
+    WellKnown::MaxForwards,
+    WellKnown::Via,
+];
+
+// Headers whose grammar has no room for a list of values (RFC 3261 7.3.1)
+const SINGLE_VALUED: [WellKnown; 14] = [
+    WellKnown::To,
+    WellKnown::From,
+    WellKnown::CSeq,
+    WellKnown::CallId,
+    WellKnown::MaxForwards,
+    WellKnown::ContentLength,
+    WellKnown::ContentType,
+    WellKnown::ContentDisposition,
+    WellKnown::Date,
+    WellKnown::Expires,
+    WellKnown::MimeVersion,
+    WellKnown::MinExpires,
+    WellKnown::ReplyTo,
+    WellKnown::Subject,
+];
+
+// Max-Forwards is an integer in the range 0-255 (RFC 3261 20.22)
+const MAX_FORWARDS_LIMIT: usize = 255;
+
+// A well formed message that breaks a rule of the protocol
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    MissingHeader(WellKnown),
+    DuplicateHeader(WellKnown),
+    MalformedHeader(Error),
+    CSeqMethodMismatch { request: Method, cseq: Method },
+    ContentLengthMismatch { header: usize, body: usize },
+    InvalidMaxForwards(usize),
+    // Max-Forwards reached zero before the request reached its target
+    TooManyHops,
+}
+
+impl Violation {
+    // The response a UAS or proxy rejects the request with (RFC 3261 8.2
+    // and 16.3)
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Violation::TooManyHops => StatusCode::TOO_MANY_HOPS,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    pub fn reason_phrase(&self) -> String {
+        match self {
+            Violation::MissingHeader(name) => format!("Missing {} Header", name.as_str()),
+            Violation::DuplicateHeader(name) => format!("Multiple {} Headers", name.as_str()),
+            Violation::MalformedHeader(e) => e.reason_phrase(),
+            Violation::CSeqMethodMismatch { .. } => "CSeq Method Mismatch".to_owned(),
+            Violation::ContentLengthMismatch { .. } => "Content-Length Mismatch".to_owned(),
+            Violation::InvalidMaxForwards(_) => "Invalid Max-Forwards".to_owned(),
+            Violation::TooManyHops => StatusCode::TOO_MANY_HOPS.canonical_reason().to_owned(),
+        }
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::MissingHeader(name) => write!(f, "missing {} header", name.as_str()),
+            Violation::DuplicateHeader(name) => {
+                write!(f, "more than one {} header", name.as_str())
+            }
+            Violation::MalformedHeader(e) => write!(f, "{}", e),
+            Violation::CSeqMethodMismatch { request, cseq } => {
+                write!(f, "CSeq method {} does not match {}", cseq, request)
+            }
+            Violation::ContentLengthMismatch { header, body } => {
+                write!(
+                    f,
+                    "Content-Length is {} but the body has {} bytes",
+                    header, body
+                )
+            }
+            Violation::InvalidMaxForwards(n) => {
+                write!(f, "Max-Forwards {} is above {}", n, MAX_FORWARDS_LIMIT)
+            }
+            Violation::TooManyHops => write!(f, "Max-Forwards is zero"),
+        }
+    }
+}
+
+impl std::error::Error for Violation {}
+
+impl Message {
+    // Checks what a message must hold beyond its grammar. Violations come in
+    // the order RFC 3261 16.3 checks them, so the first one is what to
+    // reject a request with
+    pub fn validate(&self) -> Result<(), Vec<Violation>> {
+        let mut violations = vec![];
+        let request = match &self.start_line {
+            StartLine::Request(line) => Some(&line.method),
+            StartLine::Status(_) => None,
+        };
+
+        for name in MANDATORY {
+            if name == WellKnown::MaxForwards && request.is_none() {
+                continue;
+            }
+            let name_str = name.as_str();
+            match self.headers.get_all(name_str).as_slice() {
+                [] => violations.push(Violation::MissingHeader(name)),
+                headers => violations.extend(
+                    headers
+                        .iter()
+                        .filter_map(|header| header.value().err())
+                        .map(|e| Violation::MalformedHeader(e.clone())),
+                ),
+            }
+        }
+        for name in SINGLE_VALUED {
+            if self.headers.get_all(name.as_str()).len() > 1 {
+                violations.push(Violation::DuplicateHeader(name));
+            }
+        }
+
+        if let (Some(request), Some(Ok(Value::CSeq { method, .. }))) =
+            (request, self.headers.cseq().map(|header| header.value()))
+        {
+            if method != request {
+                violations.push(Violation::CSeqMethodMismatch {
+                    request: request.clone(),
+                    cseq: method.clone(),
+                });
+            }
+        }
+        if let Some(length) = self.headers.content_length() {
+            if length != self.body.len() {
+                violations.push(Violation::ContentLengthMismatch {
+                    header: length,
+                    body: self.body.len(),
+                });
+            }
+        }
+
+        if let Some(Ok(Value::MaxForwards(n))) =
+            self.headers.max_forwards().map(|header| header.value())
+        {
+            if *n > MAX_FORWARDS_LIMIT {
+                violations.push(Violation::InvalidMaxForwards(*n));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    // What a proxy checks before forwarding a request (RFC 3261 16.3 step
+    // 3). A UAS takes it with a zero Max-Forwards all the same (RFC 4475
+    // 3.3.11)
+    pub fn check_max_forwards(&self) -> Result<(), Violation> {
+        if !self.start_line.is_request() {
+            return Ok(());
+        }
+        match self.headers.max_forwards().map(|header| header.value()) {
+            Some(Ok(Value::MaxForwards(0))) => Err(Violation::TooManyHops),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(src: &str) -> Message {
+        let (rest, message) = Message::parse(src.as_bytes()).unwrap();
+        assert!(rest.is_empty());
+        message
+    }
+
+    #[test]
+    fn valid_messages_pass() {
+        let request = message("INVITE sip:bob@biloxi.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nMax-Forwards: 70\r\nTo: Bob <sip:bob@biloxi.com>\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\nCall-ID: a84b4c76e66710\r\nCSeq: 314159 INVITE\r\nContent-Length: 4\r\n\r\nv=0\n");
+        assert_eq!(Ok(()), request.validate());
+        let response = message("SIP/2.0 200 OK\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nTo: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\nCall-ID: a84b4c76e66710\r\nCSeq: 314159 INVITE\r\n\r\n");
+        assert_eq!(Ok(()), response.validate());
+    }
+
+    #[test]
+    fn violations_are_found() {
+        let request = message("OPTIONS sip:bob@biloxi.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nMax-Forwards: 0\r\nTo: Bob <sip:bob@biloxi.com>\r\nTo: Carol <sip:carol@chicago.com>\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\nCSeq: 1 INVITE\r\n\r\n");
+        let violations = request.validate().unwrap_err();
+        assert_eq!(
+            vec![
+                Violation::MissingHeader(WellKnown::CallId),
+                Violation::DuplicateHeader(WellKnown::To),
+                Violation::CSeqMethodMismatch {
+                    request: Method::Options,
+                    cseq: Method::Invite
+                },
+            ],
+            violations
+        );
+        assert_eq!(StatusCode::BAD_REQUEST, violations[0].status_code());
+        assert_eq!("Missing Call-ID Header", violations[0].reason_phrase());
+        // Only a proxy turns it away
+        let violation = request.check_max_forwards().unwrap_err();
+        assert_eq!(Violation::TooManyHops, violation);
+        assert_eq!(StatusCode::TOO_MANY_HOPS, violation.status_code());
+
+        let mut request = message("MESSAGE sip:bob@biloxi.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nMax-Forwards: 256\r\nTo: <sip:bob@biloxi.com>\r\nFrom: <sip:alice@atlanta.com>;tag=1928301774\r\nCall-ID: a84b4c76e66710\r\nCSeq: 1 MESSAGE\r\nContent-Length: 2\r\n\r\nhi");
+        request.body = b"hello".to_vec().into_boxed_slice();
+        assert_eq!(
+            Err(vec![
+                Violation::ContentLengthMismatch { header: 2, body: 5 },
+                Violation::InvalidMaxForwards(256),
+            ]),
+            request.validate()
+        );
+    }
+
+    #[test]
+    fn max_forwards_is_a_proxy_check() {
+        let request = message("OPTIONS sip:bob@biloxi.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nMax-Forwards: 1\r\nTo: <sip:bob@biloxi.com>\r\nFrom: <sip:alice@atlanta.com>;tag=1928301774\r\nCall-ID: a84b4c76e66710\r\nCSeq: 1 OPTIONS\r\n\r\n");
+        assert_eq!(Ok(()), request.check_max_forwards());
+        let response = message("SIP/2.0 200 OK\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nMax-Forwards: 0\r\n\r\n");
+        assert_eq!(Ok(()), response.check_max_forwards());
+    }
+
+    #[test]
+    fn malformed_mandatory_headers_are_violations() {
+        let request = message("BYE sip:bob@biloxi.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nMax-Forwards: 70\r\nTo: <sip:bob@biloxi.com>\r\nFrom: <sip:alice@atlanta.com>;tag=1928301774\r\nCall-ID: a84b4c76e66710\r\nCSeq: one BYE\r\n\r\n");
+        let violations = request.validate().unwrap_err();
+        assert!(matches!(
+            violations.as_slice(),
+            [Violation::MalformedHeader(_)]
+        ));
+        assert_eq!("Malformed CSeq header", violations[0].reason_phrase());
+    }
+}
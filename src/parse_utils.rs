@@ -5,17 +5,20 @@ pub const CRLF: &[u8] = b"\r\n";
 pub const SIP_VERSION: &[u8] = b"SIP/2.0";
 pub const DQUOTE: &[u8] = b"\"";
 
+use crate::{error::Error, message::Host};
 use nom::{
-    bytes::complete::{tag, take_while, take_while1, take_while_m_n},
+    branch::alt,
+    bytes::complete::{tag, take_while1, take_while_m_n},
     character::complete::{space0, space1},
-    multi::many_m_n,
-    sequence::tuple,
+    combinator::{map, recognize},
+    multi::{fold_many0, many0_count, many_m_n},
+    sequence::{preceded, tuple},
     IResult, ParseTo,
 };
 
-pub type ParseResult<'a, T> = IResult<&'a [u8], T>;
+pub type ParseResult<'a, T> = IResult<&'a [u8], T, Error>;
 
-pub fn lws(src: &[u8]) -> ParseResult<u8> {
+pub fn lws(src: &[u8]) -> ParseResult<'_, u8> {
     // LWS  =  [*WSP CRLF] 1*WSP ; linear whitespace
     nom::combinator::map(
         tuple((many_m_n(0, 1, tuple((space0, tag(CRLF)))), space1)),
@@ -23,59 +26,69 @@ pub fn lws(src: &[u8]) -> ParseResult<u8> {
     )(src)
 }
 
-pub fn sws(src: &[u8]) -> ParseResult<u8> {
+pub fn sws(src: &[u8]) -> ParseResult<'_, u8> {
     // SWS  =  [LWS] ; sep whitespace
     nom::combinator::map(many_m_n(0, 1, lws), |_| 0x20)(src)
 }
 
 // According to RFC2234 (SP / HTAB) is the same as WSP
-pub fn hcolon(src: &[u8]) -> ParseResult<u8> {
+pub fn hcolon(src: &[u8]) -> ParseResult<'_, u8> {
     // HCOLON = *( SP / HTAB ) ":" SWS
     nom::combinator::map(tuple((space0, tag(b":" as &[u8]), sws)), |_| 0x3a)(src)
 }
 
-pub fn colon(src: &[u8]) -> ParseResult<u8> {
+pub fn colon(src: &[u8]) -> ParseResult<'_, u8> {
     // COLON   =  SWS ":" SWS
     nom::combinator::map(tuple((sws, tag(b":" as &[u8]), sws)), |_| 0x3a)(src)
 }
 
-pub fn comma(src: &[u8]) -> ParseResult<u8> {
+pub fn comma(src: &[u8]) -> ParseResult<'_, u8> {
     // COMMA   =  SWS "," SWS
     nom::combinator::map(tuple((sws, tag(b"," as &[u8]), sws)), |_| 0x2c)(src)
 }
 
-pub fn semi(src: &[u8]) -> ParseResult<u8> {
+pub fn slash(src: &[u8]) -> ParseResult<'_, u8> {
+    // SLASH   =  SWS "/" SWS ; slash
+    nom::combinator::map(tuple((sws, tag(b"/" as &[u8]), sws)), |_| 0x2f)(src)
+}
+
+pub fn star(src: &[u8]) -> ParseResult<'_, u8> {
+    // STAR    =  SWS "*" SWS ; asterisk
+    nom::combinator::map(tuple((sws, tag(b"*" as &[u8]), sws)), |_| 0x2a)(src)
+}
+
+pub fn semi(src: &[u8]) -> ParseResult<'_, u8> {
     // SWS ";" SWS ; semicolon
     nom::combinator::map(tuple((sws, tag(b";" as &[u8]), sws)), |_| 0x3b)(src)
 }
 
-pub fn equal(src: &[u8]) -> ParseResult<u8> {
+pub fn equal(src: &[u8]) -> ParseResult<'_, u8> {
     // SWS "=" SWS ; equal
     nom::combinator::map(tuple((sws, tag(b"=" as &[u8]), sws)), |_| 0x3d)(src)
 }
 
-pub fn raquot(src: &[u8]) -> ParseResult<u8> {
+pub fn raquot(src: &[u8]) -> ParseResult<'_, u8> {
     // RAQUOT  =  ">" SWS
     nom::combinator::map(tuple((tag(b">"), sws)), |_| 0x3e)(src)
 }
 
-pub fn laquot(src: &[u8]) -> ParseResult<u8> {
+pub fn laquot(src: &[u8]) -> ParseResult<'_, u8> {
     // LAQUOT  =  SWS "<"
     nom::combinator::map(tuple((sws, tag(b"<"))), |_| 0x3c)(src)
 }
 
-pub fn token(src: &[u8]) -> ParseResult<&[u8]> {
+pub fn token(src: &[u8]) -> ParseResult<'_, &[u8]> {
     // 1*(alphanum / "-" / "." / "!" / "%" / "*" / "_" / "+" / "`" / "'" / "~" )
     take_while1(|x: u8| x.is_ascii_alphanumeric() || b"-.!%*_+`'~".contains(&x))(src)
 }
 
-pub fn word(src: &[u8]) -> ParseResult<&[u8]> {
+pub fn word(src: &[u8]) -> ParseResult<'_, &[u8]> {
     // word = 1*(alphanum / "-" / "." / "!" / "%" / "*" / "_" / "+" / "`" / "'" / "~" /
     // "(" / ")" / "<" / ">" / ":" / "\" / DQUOTE / "/" / "[" / "]" / "?" / "{" / "}" )
     take_while1(|x: u8| x.is_ascii_alphanumeric() || b"-.!%*_+`'~()<>:\\\"/[]?{}".contains(&x))(src)
 }
 
-pub fn escaped(src: &[u8]) -> ParseResult<&[u8]> {
+pub fn escaped(src: &[u8]) -> ParseResult<'_, &[u8]> {
     // escaped = "%" HEXDIG HEXDIG
     let _ = tuple((
         tag(b"%"),
@@ -84,14 +97,13 @@ pub fn escaped(src: &[u8]) -> ParseResult<&[u8]> {
     Ok((&src[3..], &src[..3]))
 }
 
-pub fn unreserved1(src: &[u8]) -> ParseResult<&[u8]> {
+pub fn unreserved1(src: &[u8]) -> ParseResult<'_, &[u8]> {
     // unreserved  =  alphanum / mark
     // mark        =  "-" / "_" / "." / "!" / "~" / "*" / "'" / "(" / ")"
     take_while1(|x: u8| x.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&x))(src)
 }
 
-// TODO: make this fn complient with IPv6
-pub fn parse_host(src: &[u8]) -> ParseResult<String> {
+pub fn parse_host(src: &[u8]) -> ParseResult<'_, Host> {
     // host             =  hostname / IPv4address / IPv6reference
     // hostname         =  *( domainlabel "." ) toplabel [ "." ]
     // domainlabel      =  alphanum
@@ -103,31 +115,34 @@ pub fn parse_host(src: &[u8]) -> ParseResult<String> {
     // hexpart        =  hexseq / hexseq "::" [ hexseq ] / "::" [ hexseq ]
     // hexseq         =  hex4 *( ":" hex4)
     // hex4           =  1*4HEXDIG
-    nom::combinator::map(
-        take_while(|x: u8| !b":;?()[]<>".contains(&x) && x.is_ascii_graphic()),
-        |host| std::str::from_utf8(host).unwrap().to_owned(),
-    )(src)
+    Host::parse(src)
 }
 
-pub fn parse_usize<'a>() -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], usize> {
-    nom::combinator::map(take_while1(|x: u8| x.is_ascii_digit()), |num: &[u8]| {
-        num.parse_to().unwrap()
-    })
+pub fn parse_usize<'a>() -> impl FnMut(&'a [u8]) -> ParseResult<'a, usize> {
+    digits
 }
 
-pub fn parse_port<'a>() -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], u16> {
-    nom::combinator::map(take_while1(|x: u8| x.is_ascii_digit()), |num: &[u8]| {
-        num.parse_to().unwrap()
-    })
+pub fn parse_port<'a>() -> impl FnMut(&'a [u8]) -> ParseResult<'a, u16> {
+    digits
 }
 
-pub fn parse_u8<'a>() -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], u8> {
-    nom::combinator::map(take_while1(|x: u8| x.is_ascii_digit()), |num: &[u8]| {
-        num.parse_to().unwrap()
-    })
+pub fn parse_u8<'a>() -> impl FnMut(&'a [u8]) -> ParseResult<'a, u8> {
+    digits
 }
 
-pub fn text_utf8_byte(src: &[u8]) -> ParseResult<u8> {
+pub fn digits<T: std::str::FromStr>(src: &[u8]) -> ParseResult<'_, T> {
+    // 1*DIGIT, a value that does not fit the type is an error
+    let (rest, num) = take_while1(|x: u8| x.is_ascii_digit())(src)?;
+    match num.parse_to() {
+        Some(num) => Ok((rest, num)),
+        None => Err(nom::Err::Error(nom::error::make_error(
+            src,
+            nom::error::ErrorKind::Verify,
+        ))),
+    }
+}
+
+pub fn text_utf8_byte(src: &[u8]) -> ParseResult<'_, u8> {
     let c = src
         .first()
         .ok_or_else(|| nom::Err::Error(nom::error::make_error(src, nom::error::ErrorKind::Fail)))?;
@@ -147,18 +162,106 @@ pub fn text_utf8_byte(src: &[u8]) -> ParseResult<u8> {
     }
 }
 
-pub fn parse_quoted_string(src: &[u8]) -> ParseResult<String> {
-    nom::combinator::map(
-        tuple((
-            sws,
-            tag(DQUOTE),
-            take_while(|x: u8| !DQUOTE.contains(&x)),
-            tag(DQUOTE),
-        )),
-        |(_, _, bytes, _)| String::from_utf8(bytes.to_vec()).unwrap_or_default(),
+pub fn parse_quoted_string(src: &[u8]) -> ParseResult<'_, String> {
+    // quoted-string  =  SWS DQUOTE *(qdtext / quoted-pair ) DQUOTE
+    let (rest, (_, _, bytes, _)) = tuple((
+        sws,
+        tag(DQUOTE),
+        fold_many0(alt((qdtext, quoted_pair)), Vec::new, |mut acc, part| {
+            acc.extend_from_slice(part);
+            acc
+        }),
+        tag(DQUOTE),
+    ))(src)?;
+    match String::from_utf8(bytes) {
+        Ok(s) => Ok((rest, s)),
+        Err(_) => Err(nom::Err::Error(nom::error::make_error(
+            src,
+            nom::error::ErrorKind::Verify,
+        ))),
+    }
+}
+
+fn qdtext(src: &[u8]) -> ParseResult<'_, &[u8]> {
+    // qdtext  =  LWS / %x21 / %x23-5B / %x5D-7E / UTF8-NONASCII
+    // A folded line reads as a single SP
+    alt((
+        map(tuple((space0, tag(CRLF), space1)), |_| SP),
+        take_while1(|x: u8| matches!(x, 0x09 | 0x20 | 0x21 | 0x23..=0x5B | 0x5D..=0x7E | 0x80..)),
+    ))(src)
+}
+
+pub fn quoted_pair(src: &[u8]) -> ParseResult<'_, &[u8]> {
+    // quoted-pair  =  "\" (%x00-09 / %x0B-0C / %x0E-7F)
+    preceded(
+        tag(b"\\"),
+        take_while_m_n(1, 1, |x: u8| x <= 0x7F && x != b'\r' && x != b'\n'),
     )(src)
 }
 
+// Nesting deeper than this is refused rather than recursed into
+const MAX_COMMENT_DEPTH: usize = 16;
+
+pub fn comment(src: &[u8]) -> ParseResult<'_, &[u8]> {
+    // comment  =  LPAREN *(ctext / quoted-pair / comment) RPAREN
+    // LPAREN   =  SWS "(" SWS
+    // RPAREN   =  SWS ")" SWS
+    // The text between the outer parentheses is returned as is
+    let (rest, inner) = comment_at(MAX_COMMENT_DEPTH)(src)?;
+    let (rest, _) = sws(rest)?;
+    Ok((rest, inner))
+}
+
+fn comment_at(depth: usize) -> impl Fn(&[u8]) -> ParseResult<'_, &[u8]> {
+    move |src| {
+        let (start, _) = tuple((sws, tag(b"(")))(src)?;
+        if depth == 0 {
+            return Err(nom::Err::Failure(nom::error::make_error(
+                start,
+                nom::error::ErrorKind::TooLarge,
+            )));
+        }
+        let (end, _) =
+            many0_count(alt((ctext, quoted_pair, recognize(comment_at(depth - 1)))))(start)?;
+        let (rest, _) = tag(b")")(end)?;
+        Ok((rest, &start[..start.len() - end.len()]))
+    }
+}
+
+fn ctext(src: &[u8]) -> ParseResult<'_, &[u8]> {
+    // ctext  =  %x21-27 / %x2A-5B / %x5D-7E / UTF8-NONASCII / LWS
+    alt((
+        recognize(tuple((space0, tag(CRLF), space1))),
+        take_while1(
+            |x: u8| matches!(x, 0x09 | 0x20 | 0x21..=0x27 | 0x2A..=0x5B | 0x5D..=0x7E | 0x80..),
+        ),
+    ))(src)
+}
+
+// Writes a quoted-string, escaping what qdtext cannot carry. CR and LF
+// cannot be escaped at all and are written as SP
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\r' | '\n' => quoted.push(' '),
+            '\t' => quoted.push(c),
+            '\0'..='\x1F' | '\x7F' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
 fn next_non_whitespace(src: &[u8]) -> Option<u8> {
     for c in src.iter() {
         if ![0x09, 0x20].contains(c) {
@@ -188,6 +291,49 @@ mod tests {
         assert!(token(b"#SIPrules!").is_err());
     }
 
+    #[test]
+    fn quoted_string_works() {
+        let (rest, s) = parse_quoted_string(b" \"J Rosenberg \\\\\\\"\" <sip:").unwrap();
+        assert_eq!(b" <sip:", rest);
+        assert_eq!("J Rosenberg \\\"", s);
+        let (_, s) = parse_quoted_string(b"\"a\r\n b\"").unwrap();
+        assert_eq!("a b", s);
+        let (_, s) = parse_quoted_string("\"мама\\;\"".as_bytes()).unwrap();
+        assert_eq!("мама;", s);
+
+        assert!(parse_quoted_string(b"\"unterminated").is_err());
+        assert!(parse_quoted_string(b"\"bad \xff utf-8\"").is_err());
+        assert!(parse_quoted_string(b"\"\\\r\n\"").is_err());
+    }
+
+    #[test]
+    fn quote_works() {
+        for s in ["", "plain", "J Rosenberg \\\"", "\"\"", "tab\tand \x01"] {
+            let quoted = quote(s);
+            assert_eq!(
+                Ok((b"".as_slice(), s.to_owned())),
+                parse_quoted_string(quoted.as_bytes())
+            );
+        }
+        assert_eq!("\"a\\\"b\\\\\"", quote("a\"b\\"));
+        assert_eq!("\"a  b\"", quote("a\r\nb"));
+    }
+
+    #[test]
+    fn comment_works() {
+        let (rest, c) = comment(b" (Ubuntu; \\) (nested (twice))) rest").unwrap();
+        assert_eq!(b"rest", rest);
+        assert_eq!(b"Ubuntu; \\) (nested (twice))", c);
+        let (rest, c) = comment(b"(a\r\n b)").unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(b"a\r\n b", c);
+
+        assert!(comment(b"(unbalanced (one)").is_err());
+        assert!(comment(b"token").is_err());
+        let deep = format!("{}{}", "(".repeat(100), ")".repeat(100));
+        assert!(comment(deep.as_bytes()).is_err());
+    }
+
     #[test]
     fn lws_works() {
         assert_eq!(0x20, lws(b" ").unwrap().1);
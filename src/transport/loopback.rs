@@ -0,0 +1,152 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::mpsc;
+
+use super::{check_secure, Incoming, Transport, TransportError};
+use crate::{
+    codec::CodecError,
+    message::{header, Message, ParseOptions},
+};
+
+type Datagram = (SocketAddr, Vec<u8>);
+
+// Endpoints in memory, for running a whole stack without sockets. Nothing
+// is lost or reordered, so tests over it are deterministic.
+#[derive(Clone, Default)]
+pub struct Network {
+    endpoints: Arc<Mutex<HashMap<SocketAddr, mpsc::UnboundedSender<Datagram>>>>,
+}
+
+// Behaves as UDP does: one message per send, written out and parsed again
+// on the other end
+pub struct Loopback {
+    network: Network,
+    local_addr: SocketAddr,
+    rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<Datagram>>,
+    options: ParseOptions,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bind(&self, addr: SocketAddr) -> std::io::Result<Loopback> {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        if endpoints.contains_key(&addr) {
+            return Err(std::io::ErrorKind::AddrInUse.into());
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        endpoints.insert(addr, tx);
+        Ok(Loopback {
+            network: self.clone(),
+            local_addr: addr,
+            rx: tokio::sync::Mutex::new(rx),
+            options: ParseOptions::STRICT,
+        })
+    }
+}
+
+impl Loopback {
+    pub fn parse_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    // Delivers bytes as they are, e.g. to see how malformed input is handled
+    pub fn send_raw(&self, bytes: &[u8], target: SocketAddr) -> Result<(), TransportError> {
+        let endpoints = self.network.endpoints.lock().unwrap();
+        let endpoint = endpoints.get(&target).ok_or(TransportError::Io(
+            std::io::ErrorKind::ConnectionRefused.into(),
+        ))?;
+        endpoint
+            .send((self.local_addr, bytes.to_vec()))
+            .map_err(|_| TransportError::Closed)
+    }
+}
+
+impl Drop for Loopback {
+    fn drop(&mut self) {
+        self.network
+            .endpoints
+            .lock()
+            .unwrap()
+            .remove(&self.local_addr);
+    }
+}
+
+impl Transport for Loopback {
+    fn protocol(&self) -> header::Transport {
+        header::Transport::Udp
+    }
+
+    fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    async fn recv(&self) -> Result<Incoming, TransportError> {
+        let (source, bytes) = self
+            .rx
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(TransportError::Closed)?;
+        let message = Message::parse_datagram(&bytes, &self.options).map_err(|error| {
+            TransportError::Malformed {
+                source,
+                error: CodecError::Parse(error),
+            }
+        })?;
+        Ok(Incoming {
+            message,
+            source,
+            destination: self.local_addr,
+            transport: self.protocol(),
+            peer_certificates: None,
+        })
+    }
+
+    async fn send(&self, message: &Message, target: SocketAddr) -> Result<(), TransportError> {
+        check_secure(message, &self.protocol())?;
+        self.send_raw(&message.to_bytes(), target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_works() {
+        let network = Network::new();
+        let a = network.bind("192.0.2.1:5060".parse().unwrap()).unwrap();
+        let b = network.bind("192.0.2.2:5060".parse().unwrap()).unwrap();
+        assert!(network.bind(b.local_addr()).is_err());
+
+        let src = b"SIP/2.0 200 OK\r\nCall-ID: a\r\nContent-Length: 0\r\n\r\n";
+        let (_, message) = Message::parse(src).unwrap();
+        for _ in 0..3 {
+            a.send(&message, b.local_addr()).await.unwrap();
+        }
+        a.send_raw(b"SIP/2.0 200\r\n\r\n", b.local_addr()).unwrap();
+        for _ in 0..3 {
+            let incoming = b.recv().await.unwrap();
+            assert_eq!(a.local_addr(), incoming.source);
+            assert_eq!(src.as_slice(), incoming.message.to_bytes());
+        }
+        assert!(matches!(
+            b.recv().await,
+            Err(TransportError::Malformed { .. })
+        ));
+
+        let addr = a.local_addr();
+        drop(a);
+        assert!(b.send(&message, addr).await.is_err());
+        assert!(network.bind(addr).is_ok());
+    }
+}
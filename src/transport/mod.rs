@@ -0,0 +1,104 @@
+pub mod loopback;
+pub mod tcp;
+pub mod tls;
+pub mod udp;
+pub mod ws;
+
+pub use loopback::{Loopback, Network};
+pub use tcp::Tcp;
+pub use tls::{PeerCertificates, Tls, TlsConfig};
+pub use udp::Udp;
+pub use ws::Ws;
+
+use std::{future::Future, net::SocketAddr};
+
+use crate::{
+    codec::CodecError,
+    message::{header, Message, StartLine},
+};
+
+// A message as it arrived, with where it came from and how
+#[derive(Debug)]
+pub struct Incoming {
+    pub message: Message,
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+    pub transport: header::Transport,
+    // What the peer authenticated with, on TLS connections where it did
+    pub peer_certificates: Option<PeerCertificates>,
+}
+
+#[derive(Debug)]
+pub enum TransportError {
+    Io(std::io::Error),
+    // The peer sent something that is not a SIP message. The transport is
+    // still usable, a stream transport closes the connection though.
+    Malformed {
+        source: SocketAddr,
+        error: CodecError,
+    },
+    // A peer failed the TLS or WebSocket handshake, other connections are
+    // unaffected
+    Handshake {
+        peer: SocketAddr,
+        error: std::io::Error,
+    },
+    // A request for a sips: URI on a transport other than TLS or WSS
+    NotSecure,
+    Closed,
+}
+
+// Moves messages between the network and the application, whatever the
+// network is
+pub trait Transport: Send + Sync + 'static {
+    // As named in the Via header of requests sent over it
+    fn protocol(&self) -> header::Transport;
+
+    fn local_addr(&self) -> SocketAddr;
+
+    // The next message from any peer
+    fn recv(&self) -> impl Future<Output = Result<Incoming, TransportError>> + Send;
+
+    fn send(
+        &self,
+        message: &Message,
+        target: SocketAddr,
+    ) -> impl Future<Output = Result<(), TransportError>> + Send;
+}
+
+// A request for a sips: URI is sent over TLS on every hop (RFC 3261 26.2.2)
+pub(crate) fn check_secure(
+    message: &Message,
+    protocol: &header::Transport,
+) -> Result<(), TransportError> {
+    match &message.start_line {
+        StartLine::Request(line) if line.uri.is_secure() && !protocol.is_secure() => {
+            Err(TransportError::NotSecure)
+        }
+        _ => Ok(()),
+    }
+}
+
+impl From<std::io::Error> for TransportError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl std::fmt::Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransportError::Io(e) => write!(f, "{}", e),
+            TransportError::Malformed { source, error } => {
+                write!(f, "bad message from {}: {}", source, error)
+            }
+            TransportError::Handshake { peer, error } => {
+                write!(f, "handshake with {} failed: {}", peer, error)
+            }
+            TransportError::NotSecure => write!(f, "sips: request on an insecure transport"),
+            TransportError::Closed => write!(f, "transport closed"),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
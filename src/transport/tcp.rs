@@ -0,0 +1,396 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::StreamExt;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc,
+    task::JoinHandle,
+};
+use tokio_util::codec::FramedRead;
+
+use super::{check_secure, tls::PeerCertificates, Incoming, Transport, TransportError};
+use crate::{
+    codec::{Codec, CodecError},
+    message::{header, Message},
+};
+
+// How long a connection stays open with nothing sent or received on it
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+// Messages waiting to be written on one connection, or to be received
+const QUEUE_SIZE: usize = 64;
+
+pub(super) type Queue = tokio::sync::Mutex<mpsc::Receiver<Result<Incoming, TransportError>>>;
+
+// Connections are kept per peer, whichever side opened them, so requests
+// and responses to a peer share one connection (RFC 3261 18.2.2)
+pub struct Tcp {
+    connections: Arc<Connections>,
+    incoming: Queue,
+    accept: JoinHandle<()>,
+}
+
+// The open connections of a stream transport, and how messages are taken
+// off them: a codec on a plain stream, or parse options where something
+// else frames them
+pub(super) struct Connections<F = Codec> {
+    pub local_addr: SocketAddr,
+    pub protocol: header::Transport,
+    pub idle_timeout: Duration,
+    pub framing: F,
+    peers: Mutex<HashMap<SocketAddr, mpsc::Sender<Vec<u8>>>>,
+    incoming: mpsc::Sender<Result<Incoming, TransportError>>,
+}
+
+impl Tcp {
+    pub async fn bind(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        Self::bind_with(addr, DEFAULT_IDLE_TIMEOUT, Codec::new()).await
+    }
+
+    pub async fn bind_with(
+        addr: impl ToSocketAddrs,
+        idle_timeout: Duration,
+        codec: Codec,
+    ) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let (connections, incoming) = Connections::new(
+            listener.local_addr()?,
+            header::Transport::Tcp,
+            idle_timeout,
+            codec,
+        );
+        let accept = tokio::spawn(accept(Arc::clone(&connections), listener));
+        Ok(Self {
+            connections,
+            incoming,
+            accept,
+        })
+    }
+
+    // Peers with an open connection
+    pub fn connections(&self) -> Vec<SocketAddr> {
+        self.connections.peers()
+    }
+
+    async fn connect(&self, target: SocketAddr) -> Result<mpsc::Sender<Vec<u8>>, TransportError> {
+        let stream = TcpStream::connect(target).await?;
+        let destination = stream.local_addr()?;
+        Ok(self.connections.open(stream, target, destination, None))
+    }
+}
+
+impl Drop for Tcp {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+impl<F> Connections<F> {
+    pub fn new(
+        local_addr: SocketAddr,
+        protocol: header::Transport,
+        idle_timeout: Duration,
+        framing: F,
+    ) -> (Arc<Self>, Queue) {
+        let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+        let connections = Arc::new(Self {
+            local_addr,
+            protocol,
+            idle_timeout,
+            framing,
+            peers: Mutex::new(HashMap::new()),
+            incoming: tx,
+        });
+        (connections, tokio::sync::Mutex::new(rx))
+    }
+
+    // A connection to the peer, taking the place of any older one. What is
+    // sent to the peer is queued on the receiver.
+    pub fn register(&self, peer: SocketAddr) -> (mpsc::Sender<Vec<u8>>, mpsc::Receiver<Vec<u8>>) {
+        let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+        self.peers.lock().unwrap().insert(peer, tx.clone());
+        (tx, rx)
+    }
+
+    // Once a connection is closed, unless a newer connection to the peer
+    // took its place
+    pub fn forget(&self, tx: &mpsc::Sender<Vec<u8>>) {
+        self.peers
+            .lock()
+            .unwrap()
+            .retain(|_, other| !other.same_channel(tx));
+    }
+
+    // A request whose top Via has an alias parameter makes the connection
+    // the way to its sent-by too (RFC 5923 5). Only over TLS, and only for
+    // the address the peer connected from, so nobody takes over the traffic
+    // of another host (RFC 5923 9)
+    pub fn alias(&self, message: &Message, peer: SocketAddr, tx: &mpsc::Sender<Vec<u8>>) {
+        if !self.protocol.is_secure() || !message.start_line.is_request() {
+            return;
+        }
+        let Some(Ok(header::Value::Via(via))) = message.headers.via().map(header::Header::value)
+        else {
+            return;
+        };
+        let Some(parm) = via.first().filter(|parm| parm.has_alias()) else {
+            return;
+        };
+        let Some(ip) = parm.host().ip().filter(|ip| *ip == peer.ip()) else {
+            return;
+        };
+        let port = parm
+            .port()
+            .unwrap_or_else(|| parm.transport().default_port());
+        self.peers
+            .lock()
+            .unwrap()
+            .entry(SocketAddr::new(ip, port))
+            .or_insert_with(|| tx.clone());
+    }
+
+    pub fn get(&self, peer: SocketAddr) -> Option<mpsc::Sender<Vec<u8>>> {
+        self.peers.lock().unwrap().get(&peer).cloned()
+    }
+
+    pub fn peers(&self) -> Vec<SocketAddr> {
+        self.peers.lock().unwrap().keys().copied().collect()
+    }
+
+    // False once nobody receives anymore
+    pub async fn report(&self, incoming: Result<Incoming, TransportError>) -> bool {
+        self.incoming.send(incoming).await.is_ok()
+    }
+}
+
+impl Connections {
+    // Serves a stream, framed by the codec
+    pub fn open<S>(
+        self: &Arc<Self>,
+        stream: S,
+        peer: SocketAddr,
+        destination: SocketAddr,
+        peer_certificates: Option<PeerCertificates>,
+    ) -> mpsc::Sender<Vec<u8>>
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (tx, rx) = self.register(peer);
+        let connection = Connection {
+            connections: Arc::clone(self),
+            peer,
+            destination,
+            peer_certificates,
+            tx: tx.clone(),
+        };
+        tokio::spawn(connection.serve(stream, rx));
+        tx
+    }
+}
+
+pub(super) async fn recv(queue: &Queue) -> Result<Incoming, TransportError> {
+    queue
+        .lock()
+        .await
+        .recv()
+        .await
+        .ok_or(TransportError::Closed)?
+}
+
+async fn accept(connections: Arc<Connections>, listener: TcpListener) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                let destination = stream.local_addr().unwrap_or(connections.local_addr);
+                connections.open(stream, peer, destination, None);
+            }
+            Err(e) => {
+                if !connections.report(Err(e.into())).await {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+struct Connection {
+    connections: Arc<Connections>,
+    peer: SocketAddr,
+    destination: SocketAddr,
+    peer_certificates: Option<PeerCertificates>,
+    tx: mpsc::Sender<Vec<u8>>,
+}
+
+impl Connection {
+    // Reads and writes the connection until it fails, the peer closes it or
+    // it has been idle for too long
+    async fn serve<S>(self, stream: S, mut outgoing: mpsc::Receiver<Vec<u8>>)
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (read, mut write) = tokio::io::split(stream);
+        let mut frames = FramedRead::new(read, self.connections.framing.clone());
+        loop {
+            tokio::select! {
+                frame = frames.next() => match frame {
+                    Some(Ok(message)) => {
+                        self.connections.alias(&message, self.peer, &self.tx);
+                        let incoming = Incoming {
+                            message,
+                            source: self.peer,
+                            destination: self.destination,
+                            transport: self.connections.protocol.clone(),
+                            peer_certificates: self.peer_certificates.clone(),
+                        };
+                        if !self.connections.report(Ok(incoming)).await {
+                            break;
+                        }
+                    }
+                    Some(Err(CodecError::Io(_))) | None => break,
+                    // Framing is lost with the message, so is the connection
+                    Some(Err(error)) => {
+                        let error = TransportError::Malformed { source: self.peer, error };
+                        self.connections.report(Err(error)).await;
+                        break;
+                    }
+                },
+                bytes = outgoing.recv() => match bytes {
+                    Some(bytes) => {
+                        if write.write_all(&bytes).await.is_err() {
+                            break;
+                        }
+                    }
+                    None => break,
+                },
+                _ = tokio::time::sleep(self.connections.idle_timeout) => break,
+            }
+        }
+        let _ = write.shutdown().await;
+        self.connections.forget(&self.tx);
+    }
+}
+
+impl Transport for Tcp {
+    fn protocol(&self) -> header::Transport {
+        header::Transport::Tcp
+    }
+
+    fn local_addr(&self) -> SocketAddr {
+        self.connections.local_addr
+    }
+
+    async fn recv(&self) -> Result<Incoming, TransportError> {
+        recv(&self.incoming).await
+    }
+
+    async fn send(&self, message: &Message, target: SocketAddr) -> Result<(), TransportError> {
+        check_secure(message, &self.protocol())?;
+        let bytes = message.to_bytes();
+        let tx = match self.connections.get(target) {
+            Some(tx) => tx,
+            None => self.connect(target).await?,
+        };
+        // The connection may have closed since it was looked up
+        if let Err(mpsc::error::SendError(bytes)) = tx.send(bytes).await {
+            let tx = self.connect(target).await?;
+            tx.send(bytes).await.map_err(|_| TransportError::Closed)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BYE: &[u8] = b"BYE sip:bob@192.0.2.4 SIP/2.0\r\nVia: SIP/2.0/TCP pc33.atlanta.com;branch=z9hG4bKnashds8\r\nMax-Forwards: 70\r\nTo: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\nCall-ID: a84b4c76e66710\r\nCSeq: 231 BYE\r\nContent-Length: 0\r\n\r\n";
+
+    #[tokio::test]
+    async fn connections_are_reused() {
+        let a = Tcp::bind("127.0.0.1:0").await.unwrap();
+        let b = Tcp::bind("127.0.0.1:0").await.unwrap();
+        let (_, bye) = Message::parse(BYE).unwrap();
+
+        a.send(&bye, b.local_addr()).await.unwrap();
+        let incoming = b.recv().await.unwrap();
+        assert_eq!(BYE, incoming.message.to_bytes());
+        assert_eq!(header::Transport::Tcp, incoming.transport);
+        assert_eq!(b.local_addr(), incoming.destination);
+
+        // The answer goes back over the connection the request came on,
+        // not to the listening port of the peer
+        assert_ne!(a.local_addr(), incoming.source);
+        b.send(&bye, incoming.source).await.unwrap();
+        let answer = a.recv().await.unwrap();
+        assert_eq!(b.local_addr(), answer.source);
+        assert_eq!(vec![b.local_addr()], a.connections());
+        assert_eq!(vec![incoming.source], b.connections());
+    }
+
+    #[tokio::test]
+    async fn aliases_need_tls() {
+        let a = Tcp::bind("127.0.0.1:0").await.unwrap();
+        let b = Tcp::bind("127.0.0.1:0").await.unwrap();
+        let bye = String::from_utf8(BYE.to_vec())
+            .unwrap()
+            .replace("pc33.atlanta.com;", &format!("{};alias;", a.local_addr()));
+        let (_, aliased) = Message::parse(bye.as_bytes()).unwrap();
+        a.send(&aliased, b.local_addr()).await.unwrap();
+        let incoming = b.recv().await.unwrap();
+
+        // Anyone could claim the sent-by over plain TCP, so requests to it
+        // open a connection of their own
+        assert_eq!(vec![incoming.source], b.connections());
+        let (_, bye) = Message::parse(BYE).unwrap();
+        b.send(&bye, a.local_addr()).await.unwrap();
+        assert_eq!(a.local_addr(), a.recv().await.unwrap().destination);
+        assert_eq!(2, a.connections().len());
+    }
+
+    #[tokio::test]
+    async fn idle_connections_are_closed() {
+        let codec = Codec::new();
+        let a = Tcp::bind_with("127.0.0.1:0", Duration::from_millis(50), codec.clone())
+            .await
+            .unwrap();
+        let b = Tcp::bind_with("127.0.0.1:0", Duration::from_secs(60), codec)
+            .await
+            .unwrap();
+        let (_, bye) = Message::parse(BYE).unwrap();
+        a.send(&bye, b.local_addr()).await.unwrap();
+        b.recv().await.unwrap();
+        assert_eq!(1, a.connections().len());
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(a.connections().is_empty());
+        // The peer sees the close and forgets the connection too
+        assert!(b.connections().is_empty());
+
+        // Sending again opens a new one
+        a.send(&bye, b.local_addr()).await.unwrap();
+        assert!(b.recv().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn bad_streams_are_reported() {
+        let b = Tcp::bind("127.0.0.1:0").await.unwrap();
+        let mut stream = TcpStream::connect(b.local_addr()).await.unwrap();
+        stream
+            .write_all(b"OPTIONS sip:x.com SIP/2.0\r\nCall-ID: x\r\n\r\n")
+            .await
+            .unwrap();
+        assert!(matches!(
+            b.recv().await,
+            Err(TransportError::Malformed {
+                error: CodecError::MissingContentLength,
+                ..
+            })
+        ));
+    }
+}
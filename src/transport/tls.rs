@@ -0,0 +1,463 @@
+use std::{io, net::SocketAddr, path::Path, sync::Arc, time::Duration};
+
+use rustls::{
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName},
+    server::WebPkiClientVerifier,
+    ClientConfig, RootCertStore, ServerConfig,
+};
+use tokio::{
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc,
+    task::JoinHandle,
+};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+use super::{
+    tcp::{self, Connections, Queue, DEFAULT_IDLE_TIMEOUT},
+    Incoming, Transport, TransportError,
+};
+use crate::{
+    codec::Codec,
+    message::{header, Message},
+};
+
+// A peer that takes longer than this to complete the handshake is dropped
+pub(super) const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The chain a peer presented, its own certificate first
+pub type PeerCertificates = Arc<[CertificateDer<'static>]>;
+
+// The certificate and key this side presents, and the CAs peers are
+// checked against
+pub struct TlsConfig {
+    chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    roots: RootCertStore,
+    mutual: bool,
+}
+
+impl TlsConfig {
+    // PEM files, the certificate file may hold intermediate CAs after the
+    // certificate itself
+    pub fn from_pem_files(cert: impl AsRef<Path>, key: impl AsRef<Path>) -> io::Result<Self> {
+        let chain = CertificateDer::pem_file_iter(cert)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(invalid_data)?;
+        if chain.is_empty() {
+            return Err(invalid_data("no certificate in file"));
+        }
+        let key = PrivateKeyDer::from_pem_file(key).map_err(invalid_data)?;
+        Ok(Self {
+            chain,
+            key,
+            roots: RootCertStore::empty(),
+            mutual: false,
+        })
+    }
+
+    // Trusts the CA certificates in a PEM file for checking peers
+    pub fn trust_pem_file(mut self, ca: impl AsRef<Path>) -> io::Result<Self> {
+        for cert in CertificateDer::pem_file_iter(ca).map_err(invalid_data)? {
+            self.roots
+                .add(cert.map_err(invalid_data)?)
+                .map_err(invalid_data)?;
+        }
+        Ok(self)
+    }
+
+    // Mutual TLS: clients without a certificate from a trusted CA are
+    // turned away
+    pub fn require_client_certificates(mut self) -> Self {
+        self.mutual = true;
+        self
+    }
+
+    pub(super) fn acceptor(&self) -> io::Result<TlsAcceptor> {
+        let config = self.server_config().map_err(invalid_input)?;
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+
+    pub(super) fn connector(&self) -> io::Result<TlsConnector> {
+        let config = self.client_config().map_err(invalid_input)?;
+        Ok(TlsConnector::from(Arc::new(config)))
+    }
+
+    fn server_config(&self) -> Result<ServerConfig, rustls::Error> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()?;
+        let builder = if self.mutual {
+            let verifier =
+                WebPkiClientVerifier::builder_with_provider(Arc::new(self.roots.clone()), provider)
+                    .build()
+                    .map_err(|e| rustls::Error::General(e.to_string()))?;
+            builder.with_client_cert_verifier(verifier)
+        } else {
+            builder.with_no_client_auth()
+        };
+        builder.with_single_cert(self.chain.clone(), self.key.clone_key())
+    }
+
+    // The same certificate goes to servers asking for one
+    fn client_config(&self) -> Result<ClientConfig, rustls::Error> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(self.roots.clone())
+            .with_client_auth_cert(self.chain.clone(), self.key.clone_key())
+    }
+}
+
+// TCP with TLS on top (RFC 3261 26.2). Connections are reused the way TCP
+// ones are.
+pub struct Tls {
+    connections: Arc<Connections>,
+    incoming: Queue,
+    connector: TlsConnector,
+    accept: JoinHandle<()>,
+}
+
+impl Tls {
+    pub async fn bind(addr: impl ToSocketAddrs, config: &TlsConfig) -> io::Result<Self> {
+        Self::bind_with(addr, config, DEFAULT_IDLE_TIMEOUT, Codec::new()).await
+    }
+
+    pub async fn bind_with(
+        addr: impl ToSocketAddrs,
+        config: &TlsConfig,
+        idle_timeout: Duration,
+        codec: Codec,
+    ) -> io::Result<Self> {
+        let acceptor = config.acceptor()?;
+        let connector = config.connector()?;
+        let listener = TcpListener::bind(addr).await?;
+        let (connections, incoming) = Connections::new(
+            listener.local_addr()?,
+            header::Transport::Tls,
+            idle_timeout,
+            codec,
+        );
+        let accept = tokio::spawn(accept(Arc::clone(&connections), listener, acceptor));
+        Ok(Self {
+            connections,
+            incoming,
+            connector,
+            accept,
+        })
+    }
+
+    // Peers with an open connection
+    pub fn connections(&self) -> Vec<SocketAddr> {
+        self.connections.peers()
+    }
+
+    // Opens a connection to a server whose certificate is for `server_name`,
+    // e.g. the host of a sips: URI (RFC 5922). Messages sent to `target`
+    // afterwards go over it.
+    pub async fn connect(
+        &self,
+        target: SocketAddr,
+        server_name: &str,
+    ) -> Result<PeerCertificates, TransportError> {
+        let server_name = ServerName::try_from(server_name.to_owned())
+            .map_err(|e| TransportError::Io(invalid_input(e)))?;
+        let (_, peer_certificates) = self.open(target, server_name).await?;
+        Ok(peer_certificates)
+    }
+
+    async fn open(
+        &self,
+        target: SocketAddr,
+        server_name: ServerName<'static>,
+    ) -> Result<(mpsc::Sender<Vec<u8>>, PeerCertificates), TransportError> {
+        let stream = TcpStream::connect(target).await?;
+        let destination = stream.local_addr()?;
+        let stream = tokio::time::timeout(
+            HANDSHAKE_TIMEOUT,
+            self.connector.connect(server_name, stream),
+        )
+        .await
+        .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()))
+        .map_err(|error| TransportError::Handshake {
+            peer: target,
+            error,
+        })?;
+        let peer_certificates: PeerCertificates = stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .unwrap_or_default()
+            .into();
+        let tx = self.connections.open(
+            stream,
+            target,
+            destination,
+            Some(Arc::clone(&peer_certificates)),
+        );
+        Ok((tx, peer_certificates))
+    }
+
+    // Without a name, the certificate of the server has to be for its address
+    async fn open_to(&self, target: SocketAddr) -> Result<mpsc::Sender<Vec<u8>>, TransportError> {
+        let (tx, _) = self
+            .open(target, ServerName::IpAddress(target.ip().into()))
+            .await?;
+        Ok(tx)
+    }
+}
+
+impl Drop for Tls {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+async fn accept(connections: Arc<Connections>, listener: TcpListener, acceptor: TlsAcceptor) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                if !connections.report(Err(e.into())).await {
+                    return;
+                }
+                continue;
+            }
+        };
+        // Handshakes run on their own so a slow peer holds up nobody else
+        let connections = Arc::clone(&connections);
+        let acceptor = acceptor.clone();
+        tokio::spawn(async move {
+            let destination = stream.local_addr().unwrap_or(connections.local_addr);
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    let peer_certificates = stream
+                        .get_ref()
+                        .1
+                        .peer_certificates()
+                        .map(PeerCertificates::from);
+                    connections.open(stream, peer, destination, peer_certificates);
+                }
+                Ok(Err(error)) => {
+                    connections
+                        .report(Err(TransportError::Handshake { peer, error }))
+                        .await;
+                }
+                Err(_) => {
+                    let error = io::ErrorKind::TimedOut.into();
+                    connections
+                        .report(Err(TransportError::Handshake { peer, error }))
+                        .await;
+                }
+            }
+        });
+    }
+}
+
+impl Transport for Tls {
+    fn protocol(&self) -> header::Transport {
+        header::Transport::Tls
+    }
+
+    fn local_addr(&self) -> SocketAddr {
+        self.connections.local_addr
+    }
+
+    async fn recv(&self) -> Result<Incoming, TransportError> {
+        tcp::recv(&self.incoming).await
+    }
+
+    async fn send(&self, message: &Message, target: SocketAddr) -> Result<(), TransportError> {
+        let bytes = message.to_bytes();
+        let tx = match self.connections.get(target) {
+            Some(tx) => tx,
+            None => self.open_to(target).await?,
+        };
+        // The connection may have closed since it was looked up
+        if let Err(mpsc::error::SendError(bytes)) = tx.send(bytes).await {
+            let tx = self.open_to(target).await?;
+            tx.send(bytes).await.map_err(|_| TransportError::Closed)?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid_data(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn invalid_input(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::path::PathBuf;
+
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair, SanType};
+
+    use super::*;
+    use crate::transport::Tcp;
+
+    const OPTIONS: &[u8] = b"OPTIONS sips:carol@chicago.com SIP/2.0\r\nVia: SIP/2.0/TLS pc33.atlanta.com;branch=z9hG4bKhjhs8ass877\r\nMax-Forwards: 70\r\nTo: <sips:carol@chicago.com>\r\nFrom: Alice <sips:alice@atlanta.com>;tag=1928301774\r\nCall-ID: a84b4c76e66710\r\nCSeq: 63104 OPTIONS\r\nContent-Length: 0\r\n\r\n";
+
+    // A CA and certificates it issued, written out as PEM files
+    pub(crate) struct Pki {
+        pub dir: PathBuf,
+        ca: rcgen::Certificate,
+        ca_key: KeyPair,
+    }
+
+    impl Pki {
+        pub fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("udith-{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let ca_key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            params
+                .distinguished_name
+                .push(DnType::CommonName, "Udith Test CA");
+            let ca = params.self_signed(&ca_key).unwrap();
+            std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+            Self { dir, ca, ca_key }
+        }
+
+        // For localhost and 127.0.0.1
+        pub fn issue(&self, name: &str) -> TlsConfig {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(vec!["localhost".to_owned()]).unwrap();
+            params
+                .subject_alt_names
+                .push(SanType::IpAddress([127, 0, 0, 1].into()));
+            params.distinguished_name.push(DnType::CommonName, name);
+            let cert = params.signed_by(&key, &self.ca, &self.ca_key).unwrap();
+            let cert_path = self.dir.join(format!("{}.pem", name));
+            let key_path = self.dir.join(format!("{}.key", name));
+            std::fs::write(&cert_path, cert.pem()).unwrap();
+            std::fs::write(&key_path, key.serialize_pem()).unwrap();
+            TlsConfig::from_pem_files(cert_path, key_path)
+                .unwrap()
+                .trust_pem_file(self.dir.join("ca.pem"))
+                .unwrap()
+        }
+    }
+
+    impl Drop for Pki {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn it_works() {
+        let pki = Pki::new("tls");
+        let a = Tls::bind("127.0.0.1:0", &pki.issue("a")).await.unwrap();
+        let b = Tls::bind("127.0.0.1:0", &pki.issue("b")).await.unwrap();
+        let (_, options) = Message::parse(OPTIONS).unwrap();
+
+        a.send(&options, b.local_addr()).await.unwrap();
+        let incoming = b.recv().await.unwrap();
+        assert_eq!(OPTIONS, incoming.message.to_bytes());
+        assert_eq!(header::Transport::Tls, incoming.transport);
+        // Nobody asked the client for a certificate
+        assert!(incoming.peer_certificates.is_none());
+
+        b.send(&options, incoming.source).await.unwrap();
+        let answer = a.recv().await.unwrap();
+        assert_eq!(b.local_addr(), answer.source);
+        assert_eq!(1, answer.peer_certificates.unwrap().len());
+        assert_eq!(vec![b.local_addr()], a.connections());
+
+        // By name, over a connection of its own
+        let c = Tls::bind("127.0.0.1:0", &pki.issue("c")).await.unwrap();
+        let certificates = c.connect(b.local_addr(), "localhost").await.unwrap();
+        assert_eq!(1, certificates.len());
+        assert!(c.connect(b.local_addr(), "example.com").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn mutual_tls_works() {
+        let pki = Pki::new("mtls");
+        let config = pki.issue("server").require_client_certificates();
+        let server = Tls::bind("127.0.0.1:0", &config).await.unwrap();
+        let (_, options) = Message::parse(OPTIONS).unwrap();
+
+        let client = pki.issue("client");
+        let client = Tls::bind("127.0.0.1:0", &client).await.unwrap();
+        client.send(&options, server.local_addr()).await.unwrap();
+        let incoming = server.recv().await.unwrap();
+        let certificates = incoming.peer_certificates.unwrap();
+        assert_eq!(1, certificates.len());
+
+        // Issued by a CA the server does not know
+        let other = Pki::new("mtls-other");
+        let stranger = other
+            .issue("stranger")
+            .trust_pem_file(pki.dir.join("ca.pem"))
+            .unwrap();
+        let stranger = Tls::bind("127.0.0.1:0", &stranger).await.unwrap();
+        // TLS 1.3 clients finish before the server checks them
+        let _ = stranger.send(&options, server.local_addr()).await;
+        assert!(matches!(
+            server.recv().await,
+            Err(TransportError::Handshake { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn aliases_are_reused() {
+        let pki = Pki::new("alias");
+        let a = Tls::bind("127.0.0.1:0", &pki.issue("a")).await.unwrap();
+        let b = Tls::bind("127.0.0.1:0", &pki.issue("b")).await.unwrap();
+        let alias = |sent_by: SocketAddr| {
+            let options = String::from_utf8(OPTIONS.to_vec())
+                .unwrap()
+                .replace("pc33.atlanta.com;", &format!("{};alias;", sent_by));
+            Message::parse(options.as_bytes()).unwrap().1
+        };
+        a.send(&alias(a.local_addr()), b.local_addr())
+            .await
+            .unwrap();
+        let incoming = b.recv().await.unwrap();
+
+        // Requests to the sent-by go over the connection already open
+        let (_, options) = Message::parse(OPTIONS).unwrap();
+        b.send(&options, a.local_addr()).await.unwrap();
+        assert_eq!(incoming.source, a.recv().await.unwrap().destination);
+        assert_eq!(vec![b.local_addr()], a.connections());
+        let mut peers = b.connections();
+        peers.sort();
+        let mut expected = vec![incoming.source, a.local_addr()];
+        expected.sort();
+        assert_eq!(expected, peers);
+
+        // Not for an address the peer did not connect from
+        let spoofed = "192.0.2.1:5061".parse().unwrap();
+        a.send(&alias(spoofed), b.local_addr()).await.unwrap();
+        b.recv().await.unwrap();
+        assert!(!b.connections().contains(&spoofed));
+    }
+
+    #[tokio::test]
+    async fn sips_needs_tls() {
+        let (_, options) = Message::parse(OPTIONS).unwrap();
+        let tcp = Tcp::bind("127.0.0.1:0").await.unwrap();
+        assert!(matches!(
+            tcp.send(&options, tcp.local_addr()).await,
+            Err(TransportError::NotSecure)
+        ));
+        let (_, response) = Message::parse(
+            b"SIP/2.0 200 OK\r\nTo: <sips:carol@chicago.com>\r\nContent-Length: 0\r\n\r\n",
+        )
+        .unwrap();
+        assert!(tcp.send(&response, tcp.local_addr()).await.is_ok());
+    }
+
+    #[test]
+    fn bad_files_are_rejected() {
+        let pki = Pki::new("files");
+        let ca = pki.dir.join("ca.pem");
+        assert!(TlsConfig::from_pem_files(&ca, &ca).is_err());
+        assert!(TlsConfig::from_pem_files(pki.dir.join("missing.pem"), &ca).is_err());
+    }
+}
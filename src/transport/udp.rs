@@ -0,0 +1,105 @@
+use std::net::SocketAddr;
+
+use tokio::net::{ToSocketAddrs, UdpSocket};
+
+use super::{check_secure, Incoming, Transport, TransportError};
+use crate::{
+    codec::CodecError,
+    message::{header, Message, ParseOptions},
+};
+
+// Large enough for any datagram
+const MAX_DATAGRAM_SIZE: usize = 65535;
+
+// One message per datagram (RFC 3261 18.1.1)
+pub struct Udp {
+    socket: UdpSocket,
+    local_addr: SocketAddr,
+    options: ParseOptions,
+}
+
+impl Udp {
+    pub async fn bind(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        Self::new(UdpSocket::bind(addr).await?)
+    }
+
+    pub fn new(socket: UdpSocket) -> std::io::Result<Self> {
+        Ok(Self {
+            local_addr: socket.local_addr()?,
+            socket,
+            options: ParseOptions::STRICT,
+        })
+    }
+
+    pub fn parse_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl Transport for Udp {
+    fn protocol(&self) -> header::Transport {
+        header::Transport::Udp
+    }
+
+    fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    async fn recv(&self) -> Result<Incoming, TransportError> {
+        let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+        let (len, source) = self.socket.recv_from(&mut buf).await?;
+        let message = Message::parse_datagram(&buf[..len], &self.options).map_err(|error| {
+            TransportError::Malformed {
+                source,
+                error: CodecError::Parse(error),
+            }
+        })?;
+        Ok(Incoming {
+            message,
+            source,
+            destination: self.local_addr,
+            transport: self.protocol(),
+            peer_certificates: None,
+        })
+    }
+
+    async fn send(&self, message: &Message, target: SocketAddr) -> Result<(), TransportError> {
+        check_secure(message, &self.protocol())?;
+        self.socket.send_to(&message.to_bytes(), target).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: &[u8] = b"OPTIONS sip:carol@chicago.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bKhjhs8ass877\r\nMax-Forwards: 70\r\nTo: <sip:carol@chicago.com>\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\nCall-ID: a84b4c76e66710\r\nCSeq: 63104 OPTIONS\r\nContent-Length: 0\r\n\r\n";
+
+    #[tokio::test]
+    async fn it_works() {
+        let a = Udp::bind("127.0.0.1:0").await.unwrap();
+        let b = Udp::bind("127.0.0.1:0").await.unwrap();
+        let (_, message) = Message::parse(OPTIONS).unwrap();
+        a.send(&message, b.local_addr()).await.unwrap();
+        let incoming = b.recv().await.unwrap();
+        assert_eq!(a.local_addr(), incoming.source);
+        assert_eq!(b.local_addr(), incoming.destination);
+        assert_eq!(header::Transport::Udp, incoming.transport);
+        assert_eq!(OPTIONS, incoming.message.to_bytes());
+
+        // A bad datagram is reported and the next one still arrives
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        socket
+            .send_to(b"not sip\r\n\r\n", b.local_addr())
+            .await
+            .unwrap();
+        assert!(matches!(
+            b.recv().await,
+            Err(TransportError::Malformed { source, .. }) if source == socket.local_addr().unwrap()
+        ));
+        socket.send_to(OPTIONS, b.local_addr()).await.unwrap();
+        assert!(b.recv().await.is_ok());
+    }
+}
@@ -0,0 +1,554 @@
+use std::{future::Future, io, net::SocketAddr, sync::Arc, time::Duration};
+
+use futures::{SinkExt, StreamExt};
+use rustls::pki_types::ServerName;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc,
+    task::JoinHandle,
+};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+use tokio_tungstenite::{
+    tungstenite::{
+        self,
+        client::IntoClientRequest,
+        handshake::server::{ErrorResponse, Request, Response},
+        http::{header::SEC_WEBSOCKET_PROTOCOL, HeaderValue, StatusCode},
+        protocol::WebSocketConfig,
+        Message as Frame,
+    },
+    WebSocketStream,
+};
+
+use super::{
+    check_secure,
+    tcp::{self, Connections, Queue, DEFAULT_IDLE_TIMEOUT},
+    tls::{PeerCertificates, TlsConfig, HANDSHAKE_TIMEOUT},
+    Incoming, Transport, TransportError,
+};
+use crate::{
+    codec::CodecError,
+    message::{header, Message, ParseOptions},
+};
+
+// Both sides name it in the handshake (RFC 7118 4.1)
+const SUBPROTOCOL: &str = "sip";
+
+// As for a message on a stream transport
+const MAX_MESSAGE_SIZE: usize = 65535;
+
+// SIP over WebSocket (RFC 7118), WSS when configured with TLS. Every text or
+// binary WebSocket message carries one SIP message.
+pub struct Ws {
+    connections: Arc<Connections<ParseOptions>>,
+    incoming: Queue,
+    connector: Option<TlsConnector>,
+    accept: JoinHandle<()>,
+}
+
+impl Ws {
+    pub async fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Self::bind_with(addr, None, DEFAULT_IDLE_TIMEOUT, ParseOptions::STRICT).await
+    }
+
+    pub async fn bind_secure(addr: impl ToSocketAddrs, config: &TlsConfig) -> io::Result<Self> {
+        Self::bind_with(
+            addr,
+            Some(config),
+            DEFAULT_IDLE_TIMEOUT,
+            ParseOptions::STRICT,
+        )
+        .await
+    }
+
+    pub async fn bind_with(
+        addr: impl ToSocketAddrs,
+        tls: Option<&TlsConfig>,
+        idle_timeout: Duration,
+        options: ParseOptions,
+    ) -> io::Result<Self> {
+        let (acceptor, connector, protocol) = match tls {
+            Some(config) => (
+                Some(config.acceptor()?),
+                Some(config.connector()?),
+                header::Transport::Wss,
+            ),
+            None => (None, None, header::Transport::Ws),
+        };
+        let listener = TcpListener::bind(addr).await?;
+        // Messages are framed by WebSocket, only parsing is left
+        let (connections, incoming) =
+            Connections::new(listener.local_addr()?, protocol, idle_timeout, options);
+        let accept = tokio::spawn(accept(Arc::clone(&connections), listener, acceptor));
+        Ok(Self {
+            connections,
+            incoming,
+            connector,
+            accept,
+        })
+    }
+
+    // Peers with an open connection
+    pub fn connections(&self) -> Vec<SocketAddr> {
+        self.connections.peers()
+    }
+
+    async fn connect(&self, target: SocketAddr) -> Result<mpsc::Sender<Vec<u8>>, TransportError> {
+        let stream = TcpStream::connect(target).await?;
+        let destination = stream.local_addr()?;
+        let Some(connector) = &self.connector else {
+            let socket = handshake(target, dial("ws", target, stream)).await?;
+            let connection = Connection::new(&self.connections, target, destination, None);
+            return Ok(connection.open(socket));
+        };
+        // Without a name, the certificate of the server has to be for its
+        // address
+        let server_name = ServerName::IpAddress(target.ip().into());
+        let (socket, peer_certificates) = handshake(target, async {
+            let stream = connector.connect(server_name, stream).await?;
+            let peer_certificates: PeerCertificates = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .unwrap_or_default()
+                .into();
+            Ok((dial("wss", target, stream).await?, peer_certificates))
+        })
+        .await?;
+        let connection = Connection::new(
+            &self.connections,
+            target,
+            destination,
+            Some(peer_certificates),
+        );
+        Ok(connection.open(socket))
+    }
+}
+
+impl Drop for Ws {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+async fn accept(
+    connections: Arc<Connections<ParseOptions>>,
+    listener: TcpListener,
+    acceptor: Option<TlsAcceptor>,
+) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                if !connections.report(Err(e.into())).await {
+                    return;
+                }
+                continue;
+            }
+        };
+        // Handshakes run on their own so a slow peer holds up nobody else
+        let connections = Arc::clone(&connections);
+        let acceptor = acceptor.clone();
+        tokio::spawn(async move {
+            let destination = stream.local_addr().unwrap_or(connections.local_addr);
+            let opened = match acceptor {
+                None => handshake(peer, upgrade(stream)).await.map(|socket| {
+                    Connection::new(&connections, peer, destination, None).open(socket);
+                }),
+                Some(acceptor) => handshake(peer, async {
+                    let stream = acceptor.accept(stream).await?;
+                    let peer_certificates = stream
+                        .get_ref()
+                        .1
+                        .peer_certificates()
+                        .map(PeerCertificates::from);
+                    Ok((upgrade(stream).await?, peer_certificates))
+                })
+                .await
+                .map(|(socket, peer_certificates)| {
+                    Connection::new(&connections, peer, destination, peer_certificates)
+                        .open(socket);
+                }),
+            };
+            if let Err(error) = opened {
+                connections.report(Err(error)).await;
+            }
+        });
+    }
+}
+
+// TLS, when there is any, and the WebSocket handshake on top of it
+async fn handshake<T>(
+    peer: SocketAddr,
+    handshake: impl Future<Output = io::Result<T>>,
+) -> Result<T, TransportError> {
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake)
+        .await
+        .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()))
+        .map_err(|error| TransportError::Handshake { peer, error })
+}
+
+async fn upgrade<S>(stream: S) -> io::Result<WebSocketStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    tokio_tungstenite::accept_hdr_async_with_config(stream, negotiate, Some(config()))
+        .await
+        .map_err(io_error)
+}
+
+// Clients that do not offer the sip subprotocol are turned away. The
+// signature is the one tungstenite calls back with.
+#[allow(clippy::result_large_err)]
+fn negotiate(request: &Request, mut response: Response) -> Result<Response, ErrorResponse> {
+    let offered = request
+        .headers()
+        .get_all(SEC_WEBSOCKET_PROTOCOL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|protocol| protocol.trim() == SUBPROTOCOL);
+    if !offered {
+        let mut error = ErrorResponse::new(Some("the sip subprotocol is required".to_owned()));
+        *error.status_mut() = StatusCode::BAD_REQUEST;
+        return Err(error);
+    }
+    response.headers_mut().insert(
+        SEC_WEBSOCKET_PROTOCOL,
+        HeaderValue::from_static(SUBPROTOCOL),
+    );
+    Ok(response)
+}
+
+// The server has to answer with the sip subprotocol, tungstenite checks it
+async fn dial<S>(scheme: &str, target: SocketAddr, stream: S) -> io::Result<WebSocketStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut request = format!("{}://{}/", scheme, target)
+        .into_client_request()
+        .map_err(io_error)?;
+    request.headers_mut().insert(
+        SEC_WEBSOCKET_PROTOCOL,
+        HeaderValue::from_static(SUBPROTOCOL),
+    );
+    let (socket, _) = tokio_tungstenite::client_async_with_config(request, stream, Some(config()))
+        .await
+        .map_err(io_error)?;
+    Ok(socket)
+}
+
+fn config() -> WebSocketConfig {
+    WebSocketConfig {
+        max_message_size: Some(MAX_MESSAGE_SIZE),
+        max_frame_size: Some(MAX_MESSAGE_SIZE),
+        ..Default::default()
+    }
+}
+
+fn io_error(e: tungstenite::Error) -> io::Error {
+    match e {
+        tungstenite::Error::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e),
+    }
+}
+
+// Text when it can be, which is nearly always (RFC 7118 5.1)
+fn frame(bytes: Vec<u8>) -> Frame {
+    String::from_utf8(bytes).map_or_else(|e| Frame::Binary(e.into_bytes()), Frame::Text)
+}
+
+// The sent-by of a WebSocket client is made up (RFC 7118 5), the connection
+// is the only way back to it. The server names the connection in the top Via
+// of requests, with received and rport (RFC 3261 18.2.1, RFC 3581 4).
+fn mark_received(message: &mut Message, peer: SocketAddr) {
+    let Some(Ok(header::Value::Via(via))) = message
+        .headers
+        .get_mut("Via")
+        .map(header::Header::value_mut)
+    else {
+        return;
+    };
+    if let Some(parm) = via.first_mut() {
+        parm.set_received(peer.ip().into());
+        parm.set_rport(peer.port());
+    }
+}
+
+// The connection a response goes back over, as marked on its request
+fn response_target(message: &Message) -> Option<SocketAddr> {
+    let Some(Ok(header::Value::Via(via))) = message.headers.via().map(header::Header::value) else {
+        return None;
+    };
+    let parm = via.first()?;
+    Some(SocketAddr::new(parm.received()?.ip()?, parm.rport()?))
+}
+
+struct Connection {
+    connections: Arc<Connections<ParseOptions>>,
+    peer: SocketAddr,
+    destination: SocketAddr,
+    peer_certificates: Option<PeerCertificates>,
+}
+
+impl Connection {
+    fn new(
+        connections: &Arc<Connections<ParseOptions>>,
+        peer: SocketAddr,
+        destination: SocketAddr,
+        peer_certificates: Option<PeerCertificates>,
+    ) -> Self {
+        Self {
+            connections: Arc::clone(connections),
+            peer,
+            destination,
+            peer_certificates,
+        }
+    }
+
+    fn open<S>(self, socket: WebSocketStream<S>) -> mpsc::Sender<Vec<u8>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (tx, rx) = self.connections.register(self.peer);
+        tokio::spawn(self.serve(socket, tx.clone(), rx));
+        tx
+    }
+
+    // Reads and writes the connection until it fails, the peer closes it or
+    // it has been idle for too long
+    async fn serve<S>(
+        self,
+        mut socket: WebSocketStream<S>,
+        tx: mpsc::Sender<Vec<u8>>,
+        mut outgoing: mpsc::Receiver<Vec<u8>>,
+    ) where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        loop {
+            tokio::select! {
+                frame = socket.next() => match frame {
+                    Some(Ok(Frame::Text(text))) => {
+                        if !self.receive(text.as_bytes(), &tx).await {
+                            break;
+                        }
+                    }
+                    Some(Ok(Frame::Binary(data))) => {
+                        if !self.receive(&data, &tx).await {
+                            break;
+                        }
+                    }
+                    // Pings are answered by the socket, a close ends the
+                    // stream
+                    Some(Ok(_)) => {}
+                    Some(Err(_)) | None => break,
+                },
+                bytes = outgoing.recv() => match bytes {
+                    Some(bytes) => {
+                        if socket.send(frame(bytes)).await.is_err() {
+                            break;
+                        }
+                    }
+                    None => break,
+                },
+                _ = tokio::time::sleep(self.connections.idle_timeout) => break,
+            }
+        }
+        let _ = socket.close(None).await;
+        self.connections.forget(&tx);
+    }
+
+    // False once nobody receives anymore
+    async fn receive(&self, data: &[u8], tx: &mpsc::Sender<Vec<u8>>) -> bool {
+        let mut message = match Message::parse_datagram(data, &self.connections.framing) {
+            Ok(message) => message,
+            // Framing is up to WebSocket, so only the message is lost
+            Err(error) => {
+                let error = TransportError::Malformed {
+                    source: self.peer,
+                    error: CodecError::Parse(error),
+                };
+                return self.connections.report(Err(error)).await;
+            }
+        };
+        if message.start_line.is_request() {
+            mark_received(&mut message, self.peer);
+        }
+        self.connections.alias(&message, self.peer, tx);
+        let incoming = Incoming {
+            message,
+            source: self.peer,
+            destination: self.destination,
+            transport: self.connections.protocol.clone(),
+            peer_certificates: self.peer_certificates.clone(),
+        };
+        self.connections.report(Ok(incoming)).await
+    }
+}
+
+impl Transport for Ws {
+    fn protocol(&self) -> header::Transport {
+        self.connections.protocol.clone()
+    }
+
+    fn local_addr(&self) -> SocketAddr {
+        self.connections.local_addr
+    }
+
+    async fn recv(&self) -> Result<Incoming, TransportError> {
+        tcp::recv(&self.incoming).await
+    }
+
+    async fn send(&self, message: &Message, target: SocketAddr) -> Result<(), TransportError> {
+        check_secure(message, &self.protocol())?;
+        let bytes = message.to_bytes();
+        // A client cannot be connected to, a response on a closed connection
+        // is dropped (RFC 7118 5)
+        if !message.start_line.is_request() {
+            let tx = response_target(message)
+                .and_then(|peer| self.connections.get(peer))
+                .or_else(|| self.connections.get(target))
+                .ok_or(TransportError::Closed)?;
+            return tx.send(bytes).await.map_err(|_| TransportError::Closed);
+        }
+        let tx = match self.connections.get(target) {
+            Some(tx) => tx,
+            None => self.connect(target).await?,
+        };
+        // The connection may have closed since it was looked up
+        if let Err(mpsc::error::SendError(bytes)) = tx.send(bytes).await {
+            let tx = self.connect(target).await?;
+            tx.send(bytes).await.map_err(|_| TransportError::Closed)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{message, transport::tls::tests::Pki};
+
+    const REGISTER: &[u8] = b"REGISTER sip:proxy.example.com SIP/2.0\r\nVia: SIP/2.0/WS df7jal23ls0d.invalid;branch=z9hG4bK56sdasks\r\nMax-Forwards: 70\r\nTo: <sip:alice@example.com>\r\nFrom: <sip:alice@example.com>;tag=65bnmj.34asd\r\nCall-ID: aiuy7k9njasd\r\nCSeq: 1 REGISTER\r\nContact: <sip:alice@df7jal23ls0d.invalid;transport=ws>\r\n\r\n";
+
+    #[tokio::test]
+    async fn it_works() {
+        let browser = Ws::bind("127.0.0.1:0").await.unwrap();
+        let proxy = Ws::bind("127.0.0.1:0").await.unwrap();
+        let (_, register) = Message::parse(REGISTER).unwrap();
+
+        browser.send(&register, proxy.local_addr()).await.unwrap();
+        let incoming = proxy.recv().await.unwrap();
+        assert_eq!(header::Transport::Ws, incoming.transport);
+        assert_eq!(proxy.local_addr(), incoming.destination);
+        let via = incoming.message.headers.via().unwrap().value().unwrap();
+        assert_eq!(
+            format!(
+                "SIP/2.0/WS df7jal23ls0d.invalid;branch=z9hG4bK56sdasks;received=127.0.0.1;rport={}",
+                incoming.source.port()
+            ),
+            via.to_string()
+        );
+
+        // Back over the connection of the request, wherever it is addressed
+        let ok =
+            message::Response::from_request(&incoming.message, message::StatusCode::OK).unwrap();
+        proxy
+            .send(&ok, "192.0.2.1:5060".parse().unwrap())
+            .await
+            .unwrap();
+        let answer = browser.recv().await.unwrap();
+        assert_eq!(proxy.local_addr(), answer.source);
+        assert!(!answer.message.start_line.is_request());
+        assert_eq!(vec![proxy.local_addr()], browser.connections());
+        assert_eq!(vec![incoming.source], proxy.connections());
+    }
+
+    #[tokio::test]
+    async fn frames_carry_one_message() {
+        let proxy = Ws::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        let mut socket = dial("ws", proxy.local_addr(), stream).await.unwrap();
+
+        // A bad message does not cost the connection
+        socket
+            .send(Frame::Text("not sip\r\n\r\n".to_owned()))
+            .await
+            .unwrap();
+        assert!(matches!(
+            proxy.recv().await,
+            Err(TransportError::Malformed { .. })
+        ));
+        socket.send(Frame::Binary(REGISTER.to_vec())).await.unwrap();
+        let incoming = proxy.recv().await.unwrap();
+        assert!(incoming.message.start_line.is_request());
+
+        let ok =
+            message::Response::from_request(&incoming.message, message::StatusCode::OK).unwrap();
+        proxy.send(&ok, incoming.source).await.unwrap();
+        let Some(Ok(Frame::Text(text))) = socket.next().await else {
+            panic!("no text frame");
+        };
+        assert!(text.starts_with("SIP/2.0 200 OK\r\n"));
+
+        // Responses never open a connection
+        socket.close(None).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(proxy.connections().is_empty());
+        assert!(matches!(
+            proxy.send(&ok, incoming.source).await,
+            Err(TransportError::Closed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn sip_subprotocol_is_required() {
+        let proxy = Ws::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        let request = format!("ws://{}/", proxy.local_addr());
+        let refused = tokio_tungstenite::client_async(request, stream).await;
+        assert!(matches!(
+            refused,
+            Err(tungstenite::Error::Http(response)) if response.status() == StatusCode::BAD_REQUEST
+        ));
+        assert!(matches!(
+            proxy.recv().await,
+            Err(TransportError::Handshake { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn wss_works() {
+        let pki = Pki::new("wss");
+        let browser = Ws::bind_secure("127.0.0.1:0", &pki.issue("browser"))
+            .await
+            .unwrap();
+        let proxy = Ws::bind_secure("127.0.0.1:0", &pki.issue("proxy"))
+            .await
+            .unwrap();
+        assert_eq!(header::Transport::Wss, proxy.protocol());
+        let register = String::from_utf8(REGISTER.to_vec())
+            .unwrap()
+            .replace("sip:", "sips:")
+            .replace("SIP/2.0/WS ", "SIP/2.0/WSS ");
+        let (_, register) = Message::parse(register.as_bytes()).unwrap();
+
+        browser.send(&register, proxy.local_addr()).await.unwrap();
+        let incoming = proxy.recv().await.unwrap();
+        assert_eq!(header::Transport::Wss, incoming.transport);
+        assert!(incoming.peer_certificates.is_none());
+        let ok =
+            message::Response::from_request(&incoming.message, message::StatusCode::OK).unwrap();
+        proxy.send(&ok, incoming.source).await.unwrap();
+        let answer = browser.recv().await.unwrap();
+        assert_eq!(1, answer.peer_certificates.unwrap().len());
+
+        // sips: is for WSS only
+        let plain = Ws::bind("127.0.0.1:0").await.unwrap();
+        assert!(matches!(
+            plain.send(&register, proxy.local_addr()).await,
+            Err(TransportError::NotSecure)
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+use std::net::SocketAddr;
+
+use udith::{
+    message::{
+        header::{MediaType, Transport as Protocol},
+        Host, Message, Method, Request, StartLine, Uri,
+    },
+    transport::{Loopback, Network, Transport, TransportError},
+};
+
+const UDITH: &str = "192.0.2.10:5060";
+const PHONE: &str = "192.0.2.20:5060";
+
+fn spawn_udith(network: &Network) -> SocketAddr {
+    let addr = UDITH.parse().unwrap();
+    tokio::spawn(udith::run(network.bind(addr).unwrap()));
+    addr
+}
+
+fn phone(network: &Network) -> Loopback {
+    network.bind(PHONE.parse().unwrap()).unwrap()
+}
+
+fn status(message: &Message) -> u16 {
+    match &message.start_line {
+        StartLine::Status(line) => line.status_code.as_u16(),
+        StartLine::Request(line) => panic!("{} request", line.method),
+    }
+}
+
+fn options(phone: &Loopback, udith: SocketAddr) -> udith::message::RequestBuilder {
+    let uri = format!("sip:{}", udith).parse::<Uri>().unwrap();
+    Request::builder(Method::Options, uri).via(
+        Protocol::Udp,
+        Host::Ipv4("192.0.2.20".parse().unwrap()),
+        Some(phone.local_addr().port()),
+    )
+}
+
+#[tokio::test]
+async fn requests_are_answered() {
+    let network = Network::new();
+    let udith = spawn_udith(&network);
+    let phone = phone(&network);
+
+    let request = options(&phone, udith).build().unwrap();
+    phone.send(&request, udith).await.unwrap();
+    let answer = phone.recv().await.unwrap();
+    assert_eq!(udith, answer.source);
+    assert_eq!(200, status(&answer.message));
+    assert_eq!(
+        request.headers.call_id().unwrap().to_string(),
+        answer.message.headers.call_id().unwrap().to_string()
+    );
+}
+
+#[tokio::test]
+async fn invalid_requests_are_rejected() {
+    let network = Network::new();
+    let udith = spawn_udith(&network);
+    let phone = phone(&network);
+
+    // Malformed input gets no answer, the next request still does
+    phone.send_raw(b"OPTIONS\r\n\r\n", udith).unwrap();
+    let request = options(&phone, udith).max_forwards(256).build().unwrap();
+    phone.send(&request, udith).await.unwrap();
+    let answer = phone.recv().await.unwrap();
+    assert_eq!(400, status(&answer.message));
+    // Only a proxy turns away a request out of hops
+    let request = options(&phone, udith).max_forwards(0).build().unwrap();
+    phone.send(&request, udith).await.unwrap();
+    let answer = phone.recv().await.unwrap();
+    assert_eq!(200, status(&answer.message));
+
+    let request = options(&phone, udith).cseq(7).build().unwrap();
+    let request = String::from_utf8(request.to_bytes())
+        .unwrap()
+        .replace("CSeq: 7 OPTIONS", "CSeq: 7 INVITE");
+    phone.send_raw(request.as_bytes(), udith).unwrap();
+    let answer = phone.recv().await.unwrap();
+    assert_eq!(400, status(&answer.message));
+    let StartLine::Status(line) = &answer.message.start_line else {
+        unreachable!()
+    };
+    assert_eq!(b"CSeq Method Mismatch", &*line.reason_phrase);
+
+    let request = options(&phone, udith).build().unwrap();
+    let request = String::from_utf8(request.to_bytes())
+        .unwrap()
+        .replace(&format!("To: <sip:{}>", udith), "To: <sip:@>");
+    phone.send_raw(request.as_bytes(), udith).unwrap();
+    let answer = phone.recv().await.unwrap();
+    assert_eq!(400, status(&answer.message));
+    let StartLine::Status(line) = &answer.message.start_line else {
+        unreachable!()
+    };
+    assert_eq!(b"Malformed To header", &*line.reason_phrase);
+}
+
+#[tokio::test]
+async fn short_bodies_are_rejected() {
+    let network = Network::new();
+    let udith = spawn_udith(&network);
+    let phone = phone(&network);
+
+    let request = options(&phone, udith)
+        .body(MediaType::new("text", "plain"), &b"hello"[..])
+        .build()
+        .unwrap();
+    let request = String::from_utf8(request.to_bytes())
+        .unwrap()
+        .replace("Content-Length: 5", "Content-Length: 9");
+    phone.send_raw(request.as_bytes(), udith).unwrap();
+    let answer = phone.recv().await.unwrap();
+    assert_eq!(400, status(&answer.message));
+    let StartLine::Status(line) = &answer.message.start_line else {
+        unreachable!()
+    };
+    assert_eq!(b"Content-Length Mismatch", &*line.reason_phrase);
+}
+
+#[tokio::test]
+async fn closed_endpoints_refuse() {
+    let network = Network::new();
+    let phone = phone(&network);
+    let request = options(&phone, UDITH.parse().unwrap()).build().unwrap();
+    assert!(matches!(
+        phone.send(&request, UDITH.parse().unwrap()).await,
+        Err(TransportError::Io(_))
+    ));
+}
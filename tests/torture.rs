@@ -0,0 +1,844 @@
+// RFC 4475 (SIP torture tests) and RFC 5118 (IPv6 torture tests) messages,
+// each with the outcome the RFC expects from a parser
+use std::net::Ipv6Addr;
+
+use udith::{
+    error::{Element, Error},
+    message::{
+        header::{Contact, Value, WellKnown},
+        start_line::{RequestLine, StatusLine},
+        Host, Message, Method, StartLine, StatusCode, Uri, Violation,
+    },
+};
+
+fn fixture(path: &str) -> Vec<u8> {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/torture")
+        .join(path);
+    std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+// Frames as exactly one message
+fn framed(path: &str) -> Message {
+    let src = fixture(path);
+    let (rest, message) = Message::parse(&src).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    assert!(rest.is_empty(), "{}: {} bytes left", path, rest.len());
+    message
+}
+
+// Frames as one message and every header value parses
+fn valid(path: &str) -> Message {
+    let message = framed(path);
+    for header in message.headers.iter() {
+        if let Err(e) = header.value() {
+            panic!("{}: {} {}", path, header.name(), e);
+        }
+    }
+    message
+}
+
+// Well formed, but not acceptable to a UAS or proxy
+fn violations(path: &str) -> Vec<Violation> {
+    match valid(path).validate() {
+        Ok(()) => panic!("{}: no violations", path),
+        Err(violations) => violations,
+    }
+}
+
+fn rejected(path: &str) -> Error {
+    match Message::parse(&fixture(path)) {
+        Ok(_) => panic!("{}: parsed", path),
+        Err(e) => e,
+    }
+}
+
+fn header_error(message: &Message, name: &str) -> Error {
+    match message.headers.get(name).map(|header| header.value()) {
+        Some(Err(e)) => e.clone(),
+        Some(Ok(value)) => panic!("{} parsed as {}", name, value),
+        None => panic!("no {}", name),
+    }
+}
+
+fn value(message: &Message, name: &str) -> String {
+    match message.headers.get(name).map(|header| header.value()) {
+        Some(Ok(value)) => value.to_string(),
+        Some(Err(e)) => panic!("{}: {}", name, e),
+        None => panic!("no {}", name),
+    }
+}
+
+fn values(message: &Message, name: &str) -> Vec<String> {
+    message
+        .headers
+        .get_all(name)
+        .into_iter()
+        .map(|header| header.value().unwrap().to_string())
+        .collect()
+}
+
+fn request(message: &Message) -> &RequestLine {
+    match &message.start_line {
+        StartLine::Request(request) => request,
+        StartLine::Status(_) => panic!("not a request"),
+    }
+}
+
+fn status(message: &Message) -> &StatusLine {
+    match &message.start_line {
+        StartLine::Status(status) => status,
+        StartLine::Request(_) => panic!("not a response"),
+    }
+}
+
+fn cseq(message: &Message) -> (u32, Method) {
+    match message.headers.cseq().map(|header| header.value()) {
+        Some(Ok(Value::CSeq { num, method })) => (*num, method.clone()),
+        _ => panic!("no CSeq"),
+    }
+}
+
+fn contact(message: &Message) -> &Contact {
+    match message.headers.contact().map(|header| header.value()) {
+        Some(Ok(Value::Contact(contact))) => contact,
+        _ => panic!("no Contact"),
+    }
+}
+
+fn host(uri: &Uri) -> (&Host, Option<u16>) {
+    match uri {
+        Uri::Sip(uri) | Uri::Sips(uri) => (&uri.hostport.host, uri.hostport.port),
+        _ => panic!("{} is not a SIP URI", uri),
+    }
+}
+
+fn parse_uri(src: &str) -> Uri {
+    match Uri::parse(src.as_bytes()) {
+        Ok((b"", uri)) => uri,
+        _ => panic!("{}", src),
+    }
+}
+
+fn ipv6(addr: &str) -> Host {
+    Host::Ipv6(addr.parse::<Ipv6Addr>().unwrap())
+}
+
+// RFC 4475 3.1.1: valid messages
+
+#[test]
+fn short_tortuous_invite() {
+    // wsinv, 3.1.1.1
+    let message = valid("rfc4475/wsinv.sip");
+    let request = request(&message);
+    assert_eq!(Method::Invite, request.method);
+    assert_eq!(
+        "sip:vivekg@chair-dnrc.example.com;unknownparam",
+        request.uri.to_string()
+    );
+    assert_eq!(
+        "sip:vivekg@chair-dnrc.example.com;tag=1918181833n",
+        value(&message, "To")
+    );
+    assert_eq!("68", value(&message, "Max-Forwards"));
+    assert_eq!((9, Method::Invite), cseq(&message));
+    assert_eq!(
+        vec![
+            "SIP/2.0/UDP 192.0.2.2;branch=390skdjuw",
+            "SIP/2.0/TCP spindle.example.com;branch=z9hG4bK9ikj8, SIP/2.0/UDP 192.168.255.111;branch=z9hG4bK30239",
+        ],
+        values(&message, "Via")
+    );
+    assert_eq!(
+        "\"J Rosenberg \\\\\\\"\" <sip:jdrosen@example.com>;tag=98asjd8",
+        value(&message, "From")
+    );
+    assert_eq!(
+        "\"Quoted string \\\"\\\"\" <sip:jdrosen@example.com>;newparam=newvalue;secondparam;q=0.33",
+        value(&message, "Contact")
+    );
+    assert_eq!("", value(&message, "Subject"));
+    assert_eq!(
+        "newfangled value continued newfangled value",
+        value(&message, "NewFangledHeader")
+    );
+    assert_eq!(";;,,;;,;", value(&message, "UnknownHeaderWithUnusualValue"));
+    let routes = message.headers.route();
+    assert_eq!(1, routes.len());
+    assert!(routes[0].is_loose());
+    assert_eq!(
+        "sip:services.example.com;lr;unknownwith=value;unknown-no-value",
+        routes[0].uri().to_string()
+    );
+    assert!(message.content_type().unwrap().is("application", "sdp"));
+    assert_eq!(message.headers.content_length(), Some(message.body.len()));
+}
+
+#[test]
+fn wide_range_of_valid_characters() {
+    // intmeth, 3.1.1.2
+    let message = valid("rfc4475/intmeth.sip");
+    let method = Method::Extension("!interesting-Method0123456789_*+`.%indeed'~".to_owned());
+    let request = request(&message);
+    assert_eq!(method, request.method);
+    let Uri::Sip(uri) = &request.uri else {
+        panic!("{}", request.uri)
+    };
+    let userinfo = uri.userinfo.as_ref().unwrap();
+    assert_eq!(
+        "1_unusual.URI~(to-be!sure)&isn't+it$/crazy?,/;;*",
+        userinfo.user
+    );
+    assert_eq!(
+        Some("&it+has=1,weird!*pas$wo~d_too.(doesn't-it)"),
+        userinfo.password.as_deref()
+    );
+    assert_eq!(
+        "SIP/2.0/TCP host1.example.com;branch=z9hG4bK-.!%66*_+`'~",
+        value(&message, "Via")
+    );
+    match message.headers.to().map(|header| header.value()) {
+        Some(Ok(Value::To { address, .. })) => assert_eq!(
+            "sip:1_unusual.URI~(to-be!sure)&isn't+it$/crazy?,/;;*@example.com",
+            address.spec.uri().to_string()
+        ),
+        _ => panic!("no To"),
+    }
+    assert_eq!(
+        "intmeth.word%ZK-!.*_+'@word`~)(><:\\/\"][?}{",
+        value(&message, "Call-ID")
+    );
+    assert_eq!((139122385, method), cseq(&message));
+    assert_eq!("255", value(&message, "Max-Forwards"));
+    assert!(message.headers.contains("extensionHeader-!.%*+_`'~"));
+    assert_eq!(
+        "token1~` token2'+_ token3*%!.- <sip:mundane@example.com>;fromParam''~+*_!.-%=\"работающий\";tag=_token~1'+`*%!-.",
+        value(&message, "From")
+    );
+}
+
+#[test]
+fn valid_use_of_escapes() {
+    // esc01, 3.1.1.3: escaped and unescaped forms are equivalent, output
+    // escapes only what has to be
+    let message = valid("rfc4475/esc01.sip");
+    let uri = &request(&message).uri;
+    assert_eq!("sip:sips%3Auser%40example.com@example.net", uri.to_string());
+    let Uri::Sip(sip) = uri else {
+        panic!("{}", uri)
+    };
+    assert_eq!(
+        "sips:user@example.com",
+        sip.userinfo.as_ref().unwrap().decoded_user()
+    );
+    assert_eq!("sip:user@example.com", value(&message, "To"));
+    assert_eq!(
+        "<sip:I%20have%20spaces@example.net>;tag=938",
+        value(&message, "From")
+    );
+    let contact = contact(&message).addresses()[0].uri();
+    assert!(parse_uri("sip:caller@host5.example.net;lr;name=value%25%34%31").uri_eq(contact));
+    assert_eq!(
+        "sip:caller@host5.example.net;lr;name=value%2541",
+        contact.to_string()
+    );
+    assert!(message.content_type().unwrap().is("application", "sdp"));
+}
+
+#[test]
+fn escaped_nulls_in_uris() {
+    // escnull, 3.1.1.4
+    let message = valid("rfc4475/escnull.sip");
+    assert_eq!("sip:null-%00-null@example.com", value(&message, "To"));
+    assert_eq!(
+        vec![
+            "<sip:%00@host5.example.com>",
+            "<sip:%00%00@host5.example.com>"
+        ],
+        values(&message, "Contact")
+    );
+    assert_eq!(Some(0), message.headers.content_length());
+}
+
+#[test]
+fn use_of_percent_when_not_an_escape() {
+    // esc02, 3.1.1.5
+    let message = valid("rfc4475/esc02.sip");
+    let method = Method::Extension("RE%47IST%45R".to_owned());
+    assert_eq!(method, request(&message).method);
+    assert_eq!((29344, method), cseq(&message));
+    assert_eq!(
+        "\"%Z%45\" <sip:resource@example.com>",
+        value(&message, "To")
+    );
+    // Header names are not unescaped, C%6Fntact is an extension header
+    assert_eq!(2, values(&message, "Contact").len());
+    assert_eq!(
+        "<sip:alias2@host2.example.com>",
+        value(&message, "C%6Fntact")
+    );
+}
+
+#[test]
+fn message_with_no_lws_between_display_name_and_laquot() {
+    // lwsdisp, 3.1.1.6
+    let message = valid("rfc4475/lwsdisp.sip");
+    assert_eq!(
+        "caller <sip:caller@example.com>;tag=323",
+        value(&message, "From")
+    );
+}
+
+#[test]
+fn long_values_in_header_fields() {
+    // longreq, 3.1.1.7
+    let message = valid("rfc4475/longreq.sip");
+    assert_eq!(33, message.headers.get_all("Via").len());
+    assert_eq!(
+        "SIP/2.0/TCP sip33.example.com",
+        message.headers.via().unwrap().value().unwrap().to_string()
+    );
+    assert_eq!(7, message.headers.record_route().len());
+    assert!(value(&message, "Call-ID").len() > 300);
+    assert!(value(&message, "To").contains(&"extreme".repeat(40)));
+    assert_eq!(message.headers.content_length(), Some(message.body.len()));
+}
+
+#[test]
+fn extra_trailing_octets_in_udp_datagram() {
+    // dblreq, 3.1.1.8: the REGISTER is processed, the rest is left over
+    let src = fixture("rfc4475/dblreq.sip");
+    let (rest, message) = Message::parse(&src).unwrap();
+    assert_eq!(Method::Register, request(&message).method);
+    assert!(message.body.is_empty());
+    assert!(rest.starts_with(b"INVITE sip:joe@example.com SIP/2.0\r\n"));
+}
+
+#[test]
+fn semicolon_separated_parameters_in_uri_user_part() {
+    // semiuri, 3.1.1.9
+    let message = valid("rfc4475/semiuri.sip");
+    let Uri::Sip(uri) = &request(&message).uri else {
+        panic!("not a SIP URI")
+    };
+    assert_eq!(
+        "user;par=u%40example.net",
+        uri.userinfo.as_ref().unwrap().user
+    );
+    assert_eq!(Host::Domain("example.com".to_owned()), uri.hostport.host);
+    assert!(uri.parameters.is_empty());
+    assert_eq!(6, message.headers.accept().len());
+}
+
+#[test]
+fn varied_and_unknown_transport_types() {
+    // transports, 3.1.1.10
+    let message = valid("rfc4475/transports.sip");
+    let transports = values(&message, "Via")
+        .into_iter()
+        .map(|via| via.split(' ').next().unwrap().to_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            "SIP/2.0/UDP",
+            "SIP/2.0/SCTP",
+            "SIP/2.0/TLS",
+            "SIP/2.0/UNKNOWN",
+            "SIP/2.0/TCP"
+        ],
+        transports
+    );
+}
+
+#[test]
+fn multipart_mime_message() {
+    // mpart01, 3.1.1.11: the body is opaque, binary parts included
+    let message = valid("rfc4475/mpart01.sip");
+    let content_type = message.content_type().unwrap();
+    assert!(content_type.is("multipart", "mixed"));
+    assert_eq!(
+        Some("boundary42".to_owned()),
+        content_type.param("boundary").map(ToString::to_string)
+    );
+    assert_eq!(message.headers.content_length(), Some(message.body.len()));
+    assert!(message.body.ends_with(b"--boundary42--\r\n"));
+}
+
+#[test]
+fn unusual_reason_phrase() {
+    // unreason, 3.1.1.12
+    let message = valid("rfc4475/unreason.sip");
+    let status = status(&message);
+    assert_eq!(200, u16::from(status.status_code));
+    assert_eq!(
+        "= 2**3 * 5**2 но сто девяносто девять - простое",
+        std::str::from_utf8(&status.reason_phrase).unwrap()
+    );
+}
+
+#[test]
+fn empty_reason_phrase() {
+    // noreason, 3.1.1.13
+    let message = valid("rfc4475/noreason.sip");
+    let status = status(&message);
+    assert_eq!(100, u16::from(status.status_code));
+    assert!(status.reason_phrase.is_empty());
+}
+
+// RFC 4475 3.1.2: invalid messages
+
+#[test]
+fn extraneous_header_field_separators() {
+    // badinv01, 3.1.2.1
+    let message = framed("rfc4475/badinv01.sip");
+    let e = header_error(&message, "Via");
+    assert_eq!(
+        &[Element::Header("Via".to_owned()), Element::ViaParam],
+        e.elements()
+    );
+    header_error(&message, "Contact");
+}
+
+#[test]
+fn content_length_larger_than_message() {
+    // clerr, 3.1.2.2
+    let e = rejected("rfc4475/clerr.sip");
+    assert_eq!(Some(&Element::Body), e.element());
+}
+
+#[test]
+fn negative_content_length() {
+    // ncl, 3.1.2.3
+    let e = rejected("rfc4475/ncl.sip");
+    assert_eq!(
+        Some(&Element::Header("Content-Length".to_owned())),
+        e.element()
+    );
+}
+
+#[test]
+fn request_scalar_fields_with_overlarge_values() {
+    // scalar02, 3.1.2.4
+    let message = framed("rfc4475/scalar02.sip");
+    let e = header_error(&message, "CSeq");
+    assert_eq!("value out of range", e.description());
+}
+
+#[test]
+fn response_scalar_fields_with_overlarge_values() {
+    // scalarlg, 3.1.2.5
+    let message = framed("rfc4475/scalarlg.sip");
+    let e = header_error(&message, "CSeq");
+    assert_eq!("value out of range", e.description());
+}
+
+#[test]
+fn unterminated_quoted_string_in_display_name() {
+    // quotbal, 3.1.2.6
+    let message = framed("rfc4475/quotbal.sip");
+    header_error(&message, "To");
+}
+
+#[test]
+fn angle_brackets_enclosing_request_uri() {
+    // ltgtruri, 3.1.2.7
+    let e = rejected("rfc4475/ltgtruri.sip");
+    assert_eq!(&[Element::StartLine, Element::Uri], e.elements());
+}
+
+#[test]
+fn malformed_sip_request_uri_embedded_lws() {
+    // lwsruri, 3.1.2.8
+    let e = rejected("rfc4475/lwsruri.sip");
+    assert_eq!(Some(&Element::StartLine), e.element());
+}
+
+#[test]
+fn multiple_sp_separating_request_line_elements() {
+    // lwsstart, 3.1.2.9
+    let e = rejected("rfc4475/lwsstart.sip");
+    assert_eq!(&[Element::StartLine, Element::Uri], e.elements());
+}
+
+#[test]
+fn sp_characters_at_end_of_request_line() {
+    // trws, 3.1.2.10
+    let e = rejected("rfc4475/trws.sip");
+    assert_eq!(Some(&Element::StartLine), e.element());
+}
+
+#[test]
+fn escaped_headers_in_sip_request_uri() {
+    // escruri, 3.1.2.11
+    let e = rejected("rfc4475/escruri.sip");
+    assert_eq!(&[Element::StartLine, Element::Uri], e.elements());
+}
+
+#[test]
+fn invalid_time_zone_in_date_header_field() {
+    // baddate, 3.1.2.12: Date is carried as is and never interpreted, so
+    // the message is processed without looking at it
+    let message = valid("rfc4475/baddate.sip");
+    assert_eq!("Fri, 01 Jan 2010 16:00:00 EST", value(&message, "Date"));
+}
+
+#[test]
+fn failure_to_enclose_name_addr_uri_in_angle_brackets() {
+    // regbadct, 3.1.2.13
+    let message = framed("rfc4475/regbadct.sip");
+    header_error(&message, "Contact");
+}
+
+#[test]
+fn spaces_within_addr_spec() {
+    // badaspec, 3.1.2.14
+    let message = framed("rfc4475/badaspec.sip");
+    header_error(&message, "To");
+}
+
+#[test]
+fn non_token_characters_in_display_name() {
+    // baddn, 3.1.2.15
+    let message = framed("rfc4475/baddn.sip");
+    header_error(&message, "From");
+    header_error(&message, "To");
+}
+
+#[test]
+fn unknown_protocol_version() {
+    // badvers, 3.1.2.16
+    let e = rejected("rfc4475/badvers.sip");
+    assert_eq!(&[Element::StartLine, Element::SipVersion], e.elements());
+}
+
+#[test]
+fn start_line_and_cseq_method_mismatch() {
+    // mismatch01 and mismatch02, 3.1.2.17 and 3.1.2.18: well formed, the
+    // mismatch is for validation to reject with a 400
+    let message = valid("rfc4475/mismatch01.sip");
+    assert_eq!(Method::Options, request(&message).method);
+    assert_eq!((8, Method::Invite), cseq(&message));
+    assert_eq!(
+        vec![Violation::CSeqMethodMismatch {
+            request: Method::Options,
+            cseq: Method::Invite
+        }],
+        violations("rfc4475/mismatch01.sip")
+    );
+    let message = valid("rfc4475/mismatch02.sip");
+    assert_eq!(
+        Method::Extension("NEWMETHOD".to_owned()),
+        request(&message).method
+    );
+    assert_eq!((8, Method::Invite), cseq(&message));
+    let violations = violations("rfc4475/mismatch02.sip");
+    assert!(matches!(
+        violations.as_slice(),
+        [Violation::CSeqMethodMismatch { .. }]
+    ));
+    assert_eq!(StatusCode::BAD_REQUEST, violations[0].status_code());
+}
+
+#[test]
+fn overlarge_response_code() {
+    // bigcode, 3.1.2.19
+    let e = rejected("rfc4475/bigcode.sip");
+    assert_eq!(&[Element::StartLine, Element::StatusCode], e.elements());
+}
+
+// RFC 4475 3.2 and 3.3: transaction and application layer semantics, all
+// syntactically valid
+
+#[test]
+fn missing_transaction_identifier() {
+    // badbranch, 3.2.1
+    let message = valid("rfc4475/badbranch.sip");
+    assert_eq!(
+        "SIP/2.0/UDP 192.0.2.1;branch=z9hG4bK",
+        value(&message, "Via")
+    );
+}
+
+#[test]
+fn missing_required_header_fields() {
+    // insuf, 3.3.1
+    let message = valid("rfc4475/insuf.sip");
+    assert!(message.headers.sip_sweet_six().is_none());
+    assert!(!message.headers.contains("Call-ID"));
+    assert!(!message.headers.contains("From"));
+    assert!(!message.headers.contains("To"));
+    let violations = violations("rfc4475/insuf.sip");
+    assert_eq!(
+        vec![
+            Violation::MissingHeader(WellKnown::To),
+            Violation::MissingHeader(WellKnown::From),
+            Violation::MissingHeader(WellKnown::CallId),
+            Violation::MissingHeader(WellKnown::MaxForwards),
+        ],
+        violations
+    );
+    assert_eq!(StatusCode::BAD_REQUEST, violations[0].status_code());
+}
+
+#[test]
+fn request_uri_with_unknown_scheme() {
+    // unkscm and novelsc, 3.3.2 and 3.3.3
+    let message = valid("rfc4475/unkscm.sip");
+    assert_eq!("nobodyKnowsThisScheme", request(&message).uri.scheme());
+    let message = valid("rfc4475/novelsc.sip");
+    let uri = &request(&message).uri;
+    assert_eq!("soap.beep", uri.scheme());
+    assert_eq!("soap.beep://192.0.2.103:3002", uri.to_string());
+}
+
+#[test]
+fn unknown_uri_schemes_in_header_fields() {
+    // unksm2, 3.3.4
+    let message = valid("rfc4475/unksm2.sip");
+    assert_eq!("isbn:2983792873", value(&message, "To"));
+    assert_eq!(
+        "<http://www.example.com>;tag=3234233",
+        value(&message, "From")
+    );
+    assert_eq!("name", contact(&message).addresses()[0].uri().scheme());
+}
+
+#[test]
+fn proxy_require_and_require() {
+    // bext01, 3.3.5
+    let message = valid("rfc4475/bext01.sip");
+    assert_eq!(
+        vec!["nothingSupportsThis", "nothingSupportsThisEither"],
+        message.headers.requires()
+    );
+    assert_eq!(
+        vec!["noProxiesSupportThis", "norDoAnyProxiesSupportThis"],
+        message.headers.proxy_requires()
+    );
+}
+
+#[test]
+fn unknown_content_type() {
+    // invut, 3.3.6
+    let message = valid("rfc4475/invut.sip");
+    assert!(message
+        .content_type()
+        .unwrap()
+        .is("application", "unknownformat"));
+    assert_eq!(message.headers.content_length(), Some(message.body.len()));
+}
+
+#[test]
+fn unknown_authorization_scheme() {
+    // regaut01, 3.3.7
+    let message = valid("rfc4475/regaut01.sip");
+    assert_eq!(
+        "NoOneKnowsThisScheme opaque-data=here",
+        value(&message, "Authorization")
+    );
+    assert_eq!(Some(0), message.headers.content_length());
+}
+
+#[test]
+fn multiple_values_in_single_value_required_fields() {
+    // multi01, 3.3.8: each value parses, having two is for validation to
+    // reject
+    let message = valid("rfc4475/multi01.sip");
+    assert_eq!(2, message.headers.get_all("CSeq").len());
+    assert_eq!(2, message.headers.get_all("Call-ID").len());
+    assert_eq!(2, message.headers.get_all("From").len());
+    assert_eq!(2, message.headers.get_all("To").len());
+    assert_eq!(2, message.headers.get_all("Max-Forwards").len());
+    assert_eq!(
+        vec![
+            Violation::DuplicateHeader(WellKnown::To),
+            Violation::DuplicateHeader(WellKnown::From),
+            Violation::DuplicateHeader(WellKnown::CSeq),
+            Violation::DuplicateHeader(WellKnown::CallId),
+            Violation::DuplicateHeader(WellKnown::MaxForwards),
+        ],
+        violations("rfc4475/multi01.sip")
+    );
+}
+
+#[test]
+fn multiple_content_length_values() {
+    // mcl01, 3.3.9
+    let e = rejected("rfc4475/mcl01.sip");
+    assert_eq!(
+        Some(&Element::Header("Content-Length".to_owned())),
+        e.element()
+    );
+}
+
+#[test]
+fn response_with_broadcast_via() {
+    // bcast, 3.3.10: well formed, where the response would go is for the
+    // transaction layer to worry about
+    let message = valid("rfc4475/bcast.sip");
+    assert_eq!(StatusCode::OK, status(&message).status_code);
+    assert_eq!(
+        "SIP/2.0/UDP 255.255.255.255;branch=z9hG4bK1saber23",
+        values(&message, "Via")[1]
+    );
+    assert!(message.validate().is_ok());
+}
+
+#[test]
+fn zero_max_forwards() {
+    // zeromf, 3.3.11
+    let message = valid("rfc4475/zeromf.sip");
+    assert_eq!("0", value(&message, "Max-Forwards"));
+    // A UAS processes it normally, only a proxy answers with 483
+    assert_eq!(Ok(()), message.validate());
+    let violation = message.check_max_forwards().unwrap_err();
+    assert_eq!(Violation::TooManyHops, violation);
+    assert_eq!(StatusCode::TOO_MANY_HOPS, violation.status_code());
+}
+
+#[test]
+fn registrar_contact_parameters() {
+    // cparam01 and cparam02, 3.3.12 and 3.3.13: outside angle brackets
+    // the parameter belongs to the header field, inside to the URI
+    let message = valid("rfc4475/cparam01.sip");
+    let address = &contact(&message).addresses()[0];
+    assert_eq!(
+        "sip:+19725552222@gw1.example.net",
+        address.uri().to_string()
+    );
+    assert_eq!(1, address.generic_params().count());
+    let message = valid("rfc4475/cparam02.sip");
+    let address = &contact(&message).addresses()[0];
+    assert_eq!(
+        "sip:+19725552222@gw1.example.net;unknownparam",
+        address.uri().to_string()
+    );
+    assert_eq!(0, address.generic_params().count());
+}
+
+#[test]
+fn register_with_a_uri_with_escaped_header() {
+    // regescrt, 3.3.14: headers are fine in a Contact URI
+    let message = valid("rfc4475/regescrt.sip");
+    let Uri::Sip(uri) = contact(&message).addresses()[0].uri() else {
+        panic!("not a SIP URI")
+    };
+    assert_eq!(1, uri.headers.len());
+}
+
+#[test]
+fn unacceptable_accept_offering() {
+    // sdp01, 3.3.15: well formed, a UAS answers 406 as no SDP answer is
+    // acceptable to the caller
+    let message = valid("rfc4475/sdp01.sip");
+    let accept = message.headers.accept();
+    assert_eq!(1, accept.len());
+    assert_eq!("text/nobodyKnowsThis", accept[0].media_range.to_string());
+    assert_eq!(
+        "application/sdp",
+        message.content_type().unwrap().to_string()
+    );
+    assert_eq!(message.headers.content_length(), Some(message.body.len()));
+    assert!(message.validate().is_ok());
+}
+
+// RFC 4475 3.4: backward compatibility
+
+#[test]
+fn rfc_2543_syntax() {
+    // inv2543, 3.4.1: no branch, parameters on bare addr-specs
+    let message = valid("rfc4475/inv2543.sip");
+    assert_eq!("SIP/2.0/UDP iftgw.example.com", value(&message, "Via"));
+    assert_eq!(
+        "sip:+16505552222@ss1.example.net;user=phone",
+        value(&message, "To")
+    );
+    assert_eq!(1, message.headers.record_route().len());
+    // RFC 2543 had no Max-Forwards requirement
+    assert_eq!(
+        vec![Violation::MissingHeader(WellKnown::MaxForwards)],
+        violations("rfc4475/inv2543.sip")
+    );
+}
+
+// RFC 5118. Not covered: 4.6 and 4.8 put IPv6 addresses in SDP bodies,
+// which are carried as opaque bytes, and 4.10 is about the RFC 3261 ABNF
+// for IPv4 addresses embedded in IPv6 references.
+
+#[test]
+fn ipv6_in_sip_request_uri() {
+    // 4.1
+    let message = valid("rfc5118/ipv6-good.sip");
+    assert_eq!((&ipv6("2001:db8::10"), None), host(&request(&message).uri));
+    assert_eq!(
+        "SIP/2.0/UDP [2001:db8::9:1];branch=z9hG4bKas3-111",
+        value(&message, "Via")
+    );
+    assert_eq!(
+        (&ipv6("2001:db8::1"), None),
+        host(contact(&message).addresses()[0].uri())
+    );
+}
+
+#[test]
+fn ipv6_without_enclosing_brackets() {
+    // 4.2
+    let e = rejected("rfc5118/ipv6-bad.sip");
+    assert_eq!(&[Element::StartLine, Element::Uri], e.elements());
+}
+
+#[test]
+fn port_ambiguous_in_sip_request_uri() {
+    // 4.3: the last group is part of the address, not a port
+    let message = valid("rfc5118/port-ambiguous.sip");
+    assert_eq!(
+        (&ipv6("2001:db8::10:5070"), None),
+        host(&request(&message).uri)
+    );
+}
+
+#[test]
+fn port_unambiguous_in_sip_request_uri() {
+    // 4.4
+    let message = valid("rfc5118/port-unambiguous.sip");
+    assert_eq!(
+        (&ipv6("2001:db8::10"), Some(5070)),
+        host(&request(&message).uri)
+    );
+}
+
+#[test]
+fn ipv6_reference_delimiters_in_via_received() {
+    // 4.5: both forms are accepted, the bare one is written
+    for path in [
+        "rfc5118/via-received-param-with-delim.sip",
+        "rfc5118/via-received-param-no-delim.sip",
+    ] {
+        let message = valid(path);
+        assert!(value(&message, "Via").contains(";received=2001:db8::9:255;"));
+    }
+}
+
+#[test]
+fn sip_request_with_ipv4_and_ipv6_in_header_fields() {
+    // 4.7
+    let message = valid("rfc5118/mult-ip-in-header.sip");
+    assert_eq!(
+        vec![
+            "SIP/2.0/UDP [2001:db8::9:1]:6050;branch=z9hG4bKas3-111",
+            "SIP/2.0/UDP 192.0.2.1;branch=z9hG4bKjhja8781hjuaij65144",
+            "SIP/2.0/TCP [2001:db8::9:255];branch=z9hG4bK451jj;received=192.0.2.200",
+        ],
+        values(&message, "Via")
+    );
+}
+
+#[test]
+fn ipv4_mapped_ipv6_addresses() {
+    // 4.9
+    let message = valid("rfc5118/ipv4-mapped-ipv6.sip");
+    assert_eq!(
+        (&ipv6("::ffff:192.0.2.10"), None),
+        host(&request(&message).uri)
+    );
+    assert_eq!(message.headers.content_length(), Some(message.body.len()));
+}